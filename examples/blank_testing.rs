@@ -19,7 +19,7 @@ fn main() {
 
     //let texture = tiny_game_framework::Texture::Path("src/images/grass.jpeg".into()); // not &¨str. i want String Ü
     
-    let light = Light { position: vec3(10.0, 2.0, 5.0), color: Vec3::ONE*5.};
+    let light = Light::new(vec3(10.0, 2.0, 5.0), Vec3::ONE*5.);
     renderer.add_light("l", light);
 
     let size = 10;
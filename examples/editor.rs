@@ -0,0 +1,68 @@
+use glfw::Key;
+use tiny_game_framework::{
+    glam::{vec2, vec3, vec4},
+    gl::{Clear, ClearColor, Enable, COLOR_BUFFER_BIT, DEPTH_BUFFER_BIT, DEPTH_TEST},
+    renderer_inspector, Cuboid, EditorGrid, EventLoop, GizmoMode, Light, ProjectionType, Renderer, ShaderType, Sphere,
+};
+
+// click a mesh or pick it in the inspector and drag the handles, 1/2/3 switch between
+// moving, rotating and scaling. hold the right mouse button to look around
+fn main() {
+    let resolution = vec2(800., 800.);
+    let mut el = EventLoop::new(resolution.x as u32, resolution.y as u32);
+    let mut renderer = Renderer::new();
+    renderer.camera.set_projection(ProjectionType::Perspective);
+    renderer.camera.update(vec3(0.0, 3.0, 8.0));
+
+    unsafe {
+        Enable(DEPTH_TEST);
+    }
+
+    let mut cube = Cuboid::new(vec3(1., 1., 1.), vec4(1.0, 0.5, 0.2, 1.0)).mesh();
+    cube.set_shader_type(&ShaderType::Full);
+    cube.setup_mesh();
+    cube.set_position(vec3(-1.5, 0.5, 0.0));
+    renderer.add_mesh("cube", cube).unwrap();
+
+    let mut ball = Sphere::new(32, 0.5, vec4(0.2, 0.6, 1.0, 1.0)).mesh();
+    ball.set_shader_type(&ShaderType::Full);
+    ball.setup_mesh();
+    ball.set_position(vec3(1.5, 0.5, 0.0));
+    renderer.add_mesh("ball", ball).unwrap();
+
+    renderer.add_light("light", Light::new(vec3(4.0, 8.0, 4.0), vec3(1.0, 1.0, 1.0)));
+
+    renderer.grid = Some(EditorGrid::new());
+    renderer.gizmo.enabled = true;
+
+    while !el.window.should_close() {
+        el.update();
+        renderer.update(&el);
+
+        if el.event_handler.rmb {
+            // the first call with the cursor still visible just remembers where it is
+            renderer.camera.mouse_callback(el.event_handler.mouse_pos.x, el.event_handler.mouse_pos.y, &el.window);
+            el.window.set_cursor_mode(glfw::CursorMode::Disabled);
+        } else {
+            el.window.set_cursor_mode(glfw::CursorMode::Normal);
+        }
+        renderer.camera.input(&el.window, &el.window.glfw);
+        renderer.camera.update(renderer.camera.pos);
+
+        for (key, mode) in [(Key::Num1, GizmoMode::Translate), (Key::Num2, GizmoMode::Rotate), (Key::Num3, GizmoMode::Scale)] {
+            if el.event_handler.key_just_pressed(key) {
+                renderer.gizmo.set_mode(mode);
+            }
+        }
+
+        let frame = el.ui.frame(&mut el.window);
+        renderer_inspector(&mut renderer, frame);
+
+        unsafe {
+            ClearColor(0.15, 0.15, 0.17, 1.0);
+            Clear(COLOR_BUFFER_BIT | DEPTH_BUFFER_BIT);
+            renderer.draw(&el);
+            el.ui.draw();
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use tiny_game_framework::{
+    glam::{vec2, vec3},
+    gl::{Clear, ClearColor, Enable, COLOR_BUFFER_BIT, DEPTH_BUFFER_BIT, DEPTH_TEST},
+    EventLoop, Light, Model, ProjectionType, Renderer,
+};
+
+// views a .gltf or .glb file: `cargo run --example gltf -- model.glb`
+fn main() {
+    let path = std::env::args().nth(1).expect("usage: gltf <model.gltf | model.glb>");
+
+    let resolution = vec2(800., 800.);
+    let mut el = EventLoop::new(resolution.x as u32, resolution.y as u32);
+    let mut renderer = Renderer::new();
+    renderer.camera.set_projection(ProjectionType::Perspective);
+
+    el.window.set_cursor_mode(glfw::CursorMode::Disabled);
+
+    unsafe {
+        Enable(DEPTH_TEST);
+    }
+
+    let mut model = Model::new(&path).unwrap();
+
+    // play the first animation, `Renderer::update` moves it along
+    if let Some(name) = model.animations.first().map(|a| a.name.clone()) {
+        model.play(&name, true).unwrap();
+    }
+
+    // fit it in a 4 unit box in front of the camera
+    if let Some(bounds) = model.local_bounds() {
+        let scale = 4.0 / bounds.size().max_element().max(f32::EPSILON);
+        model.set_scale(vec3(scale, scale, scale));
+        model.set_position(vec3(0.0, 0.0, -6.0) - bounds.center() * scale);
+    }
+    renderer.add_model("model", model);
+
+    renderer.add_light("sun", Light::directional(vec3(-0.5, -1.0, -1.0), vec3(1.0, 1.0, 1.0)).with_intensity(3.0));
+
+    while !el.window.should_close() {
+        el.update();
+        renderer.update(&el);
+
+        renderer.camera.mouse_callback(el.event_handler.mouse_pos.x, el.event_handler.mouse_pos.y, &el.window);
+        renderer.camera.input(&el.window, &el.window.glfw);
+        renderer.camera.update(renderer.camera.pos);
+
+        unsafe {
+            ClearColor(0.1, 0.1, 0.1, 1.0);
+            Clear(COLOR_BUFFER_BIT | DEPTH_BUFFER_BIT);
+            renderer.draw(&el);
+        }
+    }
+}
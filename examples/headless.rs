@@ -0,0 +1,41 @@
+use tiny_game_framework::{
+    glam::{vec3, vec4},
+    gl::{Clear, ClearColor, COLOR_BUFFER_BIT, DEPTH_BUFFER_BIT},
+    compare_golden, Cuboid, EventLoop, Light, Renderer, ShaderType,
+};
+
+// renders a few frames without ever showing a window. a display connection is still
+// needed, on machines with none run it under `xvfb-run cargo run --example headless`
+fn main() {
+    let mut el = EventLoop::new_headless(256, 256);
+    let mut renderer = Renderer::new();
+
+    let mut c = Cuboid::new(vec3(0.5, 0.5, 0.5), vec4(1.0, 0.5, 0.2, 1.0)).mesh();
+    c.set_shader_type(&ShaderType::Full);
+    c.setup_mesh();
+    renderer.add_mesh("c", c).unwrap();
+
+    renderer.add_light("sun", Light::directional(vec3(-1.0, -1.0, -1.0), vec3(1.0, 1.0, 1.0)));
+
+    for _ in 0..10 {
+        el.update();
+        renderer.update(&el);
+
+        unsafe {
+            ClearColor(0.1, 0.1, 0.1, 1.0);
+            Clear(COLOR_BUFFER_BIT | DEPTH_BUFFER_BIT);
+            renderer.draw(&el);
+        }
+    }
+
+    // read back before the next `el.update()` swaps the buffers
+    let frame = el.screenshot();
+    frame.save("headless.png").unwrap();
+
+    // pass a golden png to check the frame against it
+    if let Some(golden) = std::env::args().nth(1) {
+        compare_golden(&frame, &golden, 2).unwrap();
+    }
+
+    println!("rendered 10 frames headless, t = {:.3}", el.time);
+}
@@ -0,0 +1,68 @@
+use tiny_game_framework::{
+    glam::{vec2, vec3, vec4},
+    gl::{Clear, ClearColor, Enable, COLOR_BUFFER_BIT, DEPTH_BUFFER_BIT, DEPTH_TEST},
+    Cuboid, EventLoop, Light, ProjectionType, Renderer, ShaderType,
+};
+
+const LIGHTS: usize = 256;
+
+// a floor lit by a few hundred small point lights, press R to remove them one by one
+fn main() {
+    let resolution = vec2(800., 800.);
+    let mut el = EventLoop::new(resolution.x as u32, resolution.y as u32);
+    let mut renderer = Renderer::new();
+    renderer.camera.set_projection(ProjectionType::Perspective);
+
+    el.window.set_cursor_mode(glfw::CursorMode::Disabled);
+
+    unsafe {
+        Enable(DEPTH_TEST);
+    }
+
+    let mut floor = Cuboid::new(vec3(40., 0.2, 40.), vec4(1.0, 1.0, 1.0, 1.0)).mesh();
+    floor.set_shader_type(&ShaderType::Full);
+    floor.material_mut().set_uniform("ambient_strength", 0.02);
+    floor.material_mut().set_uniform("shininess", 64.0);
+    floor.setup_mesh();
+    floor.add_position(vec3(-20., -1., -20.));
+    renderer.add_mesh("floor", floor).unwrap();
+
+    for i in 0..LIGHTS {
+        let hue = i as f32 / LIGHTS as f32 * std::f32::consts::TAU;
+        let color = vec3(hue.sin() * 0.5 + 0.5, (hue + 2.1).sin() * 0.5 + 0.5, (hue + 4.2).sin() * 0.5 + 0.5);
+        renderer.add_light(&format!("light{}", i), Light::point(vec3(0.0, 0.0, 0.0), color, 3.0).with_intensity(2.0));
+    }
+
+    let mut removed = 0;
+
+    while !el.window.should_close() {
+        el.update();
+        renderer.update(&el);
+
+        renderer.camera.mouse_callback(el.event_handler.mouse_pos.x, el.event_handler.mouse_pos.y, &el.window);
+        renderer.camera.input(&el.window, &el.window.glfw);
+        renderer.camera.update(renderer.camera.pos);
+
+        if el.is_key_down(glfw::Key::R) && removed < LIGHTS {
+            renderer.remove_light(&format!("light{}", removed)).unwrap();
+            removed += 1;
+        }
+
+        for i in removed..LIGHTS {
+            let t = el.time * 0.3 + i as f32 * 0.61;
+            let radius = 2.0 + (i % 16) as f32;
+            let light = renderer.get_light_mut(&format!("light{}", i)).unwrap();
+            light.transform.set_position(vec3(t.cos() * radius, -0.5, t.sin() * radius));
+        }
+
+        let frame = el.ui.frame(&mut el.window);
+        frame.text(format!("{} lights", renderer.lights.len()));
+
+        unsafe {
+            ClearColor(0.0, 0.0, 0.0, 1.0);
+            Clear(COLOR_BUFFER_BIT | DEPTH_BUFFER_BIT);
+            renderer.draw(&el);
+            el.ui.draw();
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use tiny_game_framework::{
+    glam::{vec2, vec3, vec4},
+    gl::{Clear, ClearColor, Enable, COLOR_BUFFER_BIT, DEPTH_BUFFER_BIT, DEPTH_TEST},
+    Environment, EventLoop, Light, ProjectionType, Renderer, ShaderType, Sphere,
+};
+
+// a grid of spheres going from rough to smooth (left to right) and dielectric to metal (bottom to top).
+// pass an equirectangular .hdr to light them with it: `cargo run --example pbr -- sky.hdr`
+fn main() {
+    let resolution = vec2(800., 800.);
+    let mut el = EventLoop::new(resolution.x as u32, resolution.y as u32);
+    let mut renderer = Renderer::new();
+    renderer.camera.set_projection(ProjectionType::Perspective);
+
+    el.window.set_cursor_mode(glfw::CursorMode::Disabled);
+
+    unsafe {
+        Enable(DEPTH_TEST);
+    }
+
+    if let Some(hdr) = std::env::args().nth(1) {
+        renderer.set_environment(Some(Environment::from_hdr(&hdr).unwrap()));
+    }
+
+    for row in 0..5 {
+        for col in 0..5 {
+            let mut s = Sphere::new(16, 0.4, vec4(0.9, 0.2, 0.2, 1.0)).mesh();
+            s.set_shader_type(&ShaderType::Pbr);
+            s.material_mut().set_uniform("metallic", row as f32 / 4.0);
+            s.material_mut().set_uniform("roughness", (col as f32 / 4.0).max(0.05));
+            s.setup_mesh();
+            s.add_position(vec3(col as f32 - 2.0, row as f32 - 2.0, -5.0));
+            renderer.add_mesh(&format!("sphere{}{}", row, col), s).unwrap();
+        }
+    }
+
+    renderer.add_light("sun", Light::directional(vec3(-0.5, -1.0, -1.0), vec3(1.0, 1.0, 1.0)).with_intensity(3.0));
+
+    while !el.window.should_close() {
+        el.update();
+        renderer.update(&el);
+
+        renderer.camera.mouse_callback(el.event_handler.mouse_pos.x, el.event_handler.mouse_pos.y, &el.window);
+        renderer.camera.input(&el.window, &el.window.glfw);
+        renderer.camera.update(renderer.camera.pos);
+
+        unsafe {
+            ClearColor(0.1, 0.1, 0.1, 1.0);
+            Clear(COLOR_BUFFER_BIT | DEPTH_BUFFER_BIT);
+            renderer.draw(&el);
+        }
+    }
+}
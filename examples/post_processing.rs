@@ -0,0 +1,70 @@
+use tiny_game_framework::{
+    glam::{vec2, vec3, vec4},
+    gl::{Enable, DEPTH_TEST},
+    Cuboid, EventLoop, Light, PostEffect, PostProcess, Renderer, Shader, ShaderType, POST_PROCESS_VS,
+};
+
+// a user pass: chromatic aberration that wobbles over time
+static ABERRATION_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D screen;
+uniform float time;
+
+void main()
+{
+    vec2 offset = vec2(0.002 + 0.002 * sin(time), 0.0);
+    float r = texture(screen, TexCoord + offset).r;
+    float g = texture(screen, TexCoord).g;
+    float b = texture(screen, TexCoord - offset).b;
+    FragColor = vec4(r, g, b, 1.0);
+}
+"#;
+
+fn main() {
+    let resolution = vec2(800., 800.);
+    let mut el = EventLoop::new(resolution.x as u32, resolution.y as u32);
+    let mut renderer = Renderer::new();
+
+    el.window.set_cursor_mode(glfw::CursorMode::Disabled);
+
+    unsafe {
+        Enable(DEPTH_TEST);
+    }
+
+    let mut c = Cuboid::new(vec3(1., 1., 1.), vec4(1.0, 0.8, 0.6, 1.0)).mesh();
+    c.set_shader_type(&ShaderType::Full);
+    c.setup_mesh();
+    renderer.add_mesh("c", c).unwrap();
+
+    // bright enough to bloom
+    renderer.add_light("light", Light::new(vec3(10.0, 10.0, 10.0), vec3(4.0, 4.0, 4.0)));
+
+    let mut post = PostProcess::new(vec![
+        PostEffect::Bloom { threshold: 1.0, intensity: 0.8, iterations: 4 },
+        PostEffect::Tonemap { exposure: 1.0, gamma: 2.2 },
+        PostEffect::Custom(Shader::new_pipeline(POST_PROCESS_VS, ABERRATION_FS)),
+        PostEffect::Vignette { strength: 0.6, radius: 0.3 },
+        PostEffect::Fxaa,
+    ]).unwrap();
+
+    while !el.window.should_close() {
+        el.update();
+        renderer.update(&el);
+
+        renderer.camera.input(&el.window, &el.window.glfw);
+        renderer.camera.mouse_callback(el.event_handler.mouse_pos.x, el.event_handler.mouse_pos.y, &el.window);
+        renderer.camera.update(renderer.camera.pos);
+
+        let frame = el.ui.frame(&mut el.window);
+        frame.text("post processing");
+
+        unsafe {
+            renderer.draw_post(&el, &mut post);
+            el.ui.draw();
+        }
+    }
+}
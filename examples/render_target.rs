@@ -0,0 +1,68 @@
+use glam::Quat;
+use tiny_game_framework::{
+    glam::{vec2, vec3, vec4},
+    gl::{Clear, ClearColor, Enable, COLOR_BUFFER_BIT, DEPTH_BUFFER_BIT, DEPTH_TEST},
+    imgui, Camera, Cuboid, EventLoop, Light, Quad, Renderer, RenderTarget, ShaderType, TextureSlot,
+};
+
+fn main() {
+    let resolution = vec2(800., 800.);
+    let mut el = EventLoop::new(resolution.x as u32, resolution.y as u32);
+    let mut renderer = Renderer::new();
+
+    el.window.set_cursor_mode(glfw::CursorMode::Disabled);
+
+    unsafe {
+        Enable(DEPTH_TEST);
+    }
+
+    let target = RenderTarget::new(512, 512).unwrap();
+
+    let mut c = Cuboid::new(vec3(1., 1., 1.), vec4(1.0, 0.3, 0.1, 1.0)).mesh();
+    c.set_shader_type(&ShaderType::Full);
+    c.setup_mesh();
+    renderer.add_mesh("c", c).unwrap();
+
+    // the "security camera screen", showing what `security_cam` sees.
+    // it sits behind both cameras so it never samples the target while drawing into it
+    let mut screen = Quad::new(vec3(4., 4., 0.), vec4(1.0, 1.0, 1.0, 1.0)).mesh();
+    screen.material_mut().set_texture(TextureSlot::Albedo, target.texture_handle());
+    screen.setup_mesh();
+    screen.add_position(vec3(-2., -2., 6.));
+    renderer.add_mesh("screen", screen).unwrap();
+
+    renderer.add_light("light", Light::new(vec3(10.0, 10.0, 10.0), vec3(1.0, 1.0, 1.0)));
+
+    let mut security_cam = Camera::new();
+    security_cam.set_projection(tiny_game_framework::ProjectionType::Perspective);
+    security_cam.update(vec3(0.0, 2.0, 4.0));
+
+    while !el.window.should_close() {
+        el.update();
+        renderer.update(&el);
+
+        renderer.camera.input(&el.window, &el.window.glfw);
+        renderer.camera.mouse_callback(el.event_handler.mouse_pos.x, el.event_handler.mouse_pos.y, &el.window);
+        renderer.camera.update(renderer.camera.pos);
+
+        let c = renderer.get_mesh_mut("c").unwrap();
+        c.set_rotation(Quat::from_euler(glam::EulerRot::XYZ, el.time, el.time * 0.5, 0.0));
+
+        let frame = el.ui.frame(&mut el.window);
+        frame.window("security camera").build(|| {
+            imgui::Image::new(target.imgui_texture_id(), [256.0, 256.0])
+                .uv0([0.0, 1.0])
+                .uv1([1.0, 0.0])
+                .build(frame);
+        });
+
+        unsafe {
+            ClearColor(0.05, 0.05, 0.1, 1.0);
+            renderer.draw_to_with_camera(&el, &target, &security_cam);
+
+            Clear(COLOR_BUFFER_BIT | DEPTH_BUFFER_BIT);
+            renderer.draw(&el);
+            el.ui.draw();
+        }
+    }
+}
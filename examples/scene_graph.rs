@@ -0,0 +1,58 @@
+use glam::Quat;
+use tiny_game_framework::{
+    glam::{vec2, vec3, vec4},
+    gl::{Clear, ClearColor, Enable, COLOR_BUFFER_BIT, DEPTH_BUFFER_BIT, DEPTH_TEST},
+    EventLoop, Light, NodeTarget, ProjectionType, Renderer, ShaderType, Sphere,
+};
+
+// a planet spinning around the sun and a moon spinning around the planet,
+// only the parents are rotated, the children follow through the scene graph
+fn main() {
+    let resolution = vec2(800., 800.);
+    let mut el = EventLoop::new(resolution.x as u32, resolution.y as u32);
+    let mut renderer = Renderer::new();
+    renderer.camera.set_projection(ProjectionType::Perspective);
+
+    el.window.set_cursor_mode(glfw::CursorMode::Disabled);
+
+    unsafe {
+        Enable(DEPTH_TEST);
+    }
+
+    let bodies = [
+        ("sun", 1.5, vec4(1.0, 0.8, 0.2, 1.0), vec3(0., 0., -12.)),
+        ("planet", 0.6, vec4(0.2, 0.4, 1.0, 1.0), vec3(6., 0., -12.)),
+        ("moon", 0.2, vec4(0.7, 0.7, 0.7, 1.0), vec3(7.5, 0., -12.)),
+    ];
+    for (name, radius, color, position) in bodies {
+        let mut s = Sphere::new(32, radius, color).mesh();
+        s.set_shader_type(&ShaderType::Light);
+        s.setup_mesh();
+        s.set_position(position);
+        renderer.add_mesh(name, s).unwrap();
+    }
+
+    // set_parent keeps the world positions given above
+    renderer.set_parent(&NodeTarget::mesh("planet"), Some(&NodeTarget::mesh("sun"))).unwrap();
+    renderer.set_parent(&NodeTarget::mesh("moon"), Some(&NodeTarget::mesh("planet"))).unwrap();
+
+    renderer.add_light("light", Light::new(vec3(0.0, 10.0, 0.0), vec3(1.0, 1.0, 1.0)));
+
+    while !el.window.should_close() {
+        el.update();
+        renderer.update(&el);
+
+        renderer.camera.mouse_callback(el.event_handler.mouse_pos.x, el.event_handler.mouse_pos.y, &el.window);
+        renderer.camera.input(&el.window, &el.window.glfw);
+        renderer.camera.update(renderer.camera.pos);
+
+        renderer.get_mesh_mut("sun").unwrap().set_rotation(Quat::from_rotation_y(el.time * 0.5));
+        renderer.get_mesh_mut("planet").unwrap().set_rotation(Quat::from_rotation_y(el.time * 2.0));
+
+        unsafe {
+            ClearColor(0.0, 0.0, 0.05, 1.0);
+            Clear(COLOR_BUFFER_BIT | DEPTH_BUFFER_BIT);
+            renderer.draw(&el);
+        }
+    }
+}
@@ -51,13 +51,16 @@ fn main() {
     t.scale(vec3(20.0, 20.0, 20.0));
     renderer.add_mesh("t", t).unwrap();
 
-    let mut suzanne = tiny_game_framework::Model::new("examples/assets/models/suzanne.obj");
-    suzanne.meshes[0].set_shader_type(&ShaderType::Full);
+    // gets Full materials from suzanne.mtl
+    let suzanne = tiny_game_framework::Model::new("examples/assets/models/suzanne.obj").unwrap();
+    for warning in &suzanne.warnings {
+        eprintln!("{}", warning);
+    }
     renderer.add_model("suzanne", suzanne);
 
-    renderer.add_light("light1", Light {position: vec3(100000.0, 100000.0, 100000.0), color: vec3(0.0, 0.0, 1.0)});
-    renderer.add_light("light2", Light {position: vec3(-100000.0, 100000.0, 100000.0), color: vec3(0.0, 1.0, 0.0)});
-    renderer.add_light("light3", Light {position: vec3(-100000.0, 100000.0, -100000.0), color: vec3(1.0, 0.0, 0.0)});
+    renderer.add_light("light1", Light::new(vec3(100000.0, 100000.0, 100000.0), vec3(0.0, 0.0, 1.0)));
+    renderer.add_light("light2", Light::new(vec3(-100000.0, 100000.0, 100000.0), vec3(0.0, 1.0, 0.0)));
+    renderer.add_light("light3", Light::new(vec3(-100000.0, 100000.0, -100000.0), vec3(1.0, 0.0, 0.0)));
 
     let mut font = unsafe {
         Font::init(800.0, 800.0, "examples/assets/fonts/comic.ttf")
@@ -87,7 +90,7 @@ fn main() {
         let cam_pos = renderer.camera.pos * resolution.x;
 
         let s = renderer.get_mesh_mut("s").unwrap();
-        s.rotation = Quat::from_euler(glam::EulerRot::XYZ, 0.0, el.time / 20.0, 0.0);
+        s.set_rotation(Quat::from_euler(glam::EulerRot::XYZ, 0.0, el.time / 20.0, 0.0));
 
         let t = renderer.get_mesh_mut("t").unwrap();
         // t.position = cam_pos;
@@ -133,23 +136,21 @@ fn main() {
         is_set_fullscreen(&mut el, &mut fullscreen);
 
         let l1 = renderer.get_light_mut("light1").unwrap();
-        l1.position = vec3(el.time.cos() * 10000.0, el.time.sin() * 10000.0, 10000.0);
+        l1.transform.set_position(vec3(el.time.cos() * 10000.0, el.time.sin() * 10000.0, 10000.0));
 
         let l2 = renderer.get_light_mut("light2").unwrap();
-        l2.position = vec3(10000.0, el.time.sin() * 10000.0, el.time.cos() * 10000.0);
+        l2.transform.set_position(vec3(10000.0, el.time.sin() * 10000.0, el.time.cos() * 10000.0));
 
         let l3 = renderer.get_light_mut("light3").unwrap();
-        l3.position = vec3(el.time.sin() * 10000.0, el.time.cos() * 10000.0, 10000.0);
+        l3.transform.set_position(vec3(el.time.sin() * 10000.0, el.time.cos() * 10000.0, 10000.0));
 
         let direction = renderer.camera.front;
         let right = direction.cross(vec3(0.0, 1.0, 0.0));
         let mut suzanne = renderer.get_model_mut("suzanne").unwrap();
         dist += el.event_handler.scroll.y;
-        let mut s = &mut suzanne.meshes[0];
         let goal = cam_pos + direction * dist * 10.0 + right * 200.0;
-        s.position.x = lerp(s.position.x, goal.x, 0.25);
-        s.position.y = lerp(s.position.y, goal.y, 0.25);
-        s.position.z = lerp(s.position.z, goal.z, 0.25);
+        let p = suzanne.transform.position();
+        suzanne.set_position(vec3(lerp(p.x, goal.x, 0.25), lerp(p.y, goal.y, 0.25), lerp(p.z, goal.z, 0.25)));
 
         unsafe { 
             if el.is_key_down(Key::F1) {
@@ -126,7 +126,7 @@ impl Particle {
 
         let circle = Circle::new(7, radius / 250.0, vec4(rand_betw(0.0, 1.0), rand_betw(0.0, 1.0), rand_betw(0.0, 1.0), 1.0));
         renderer.add_mesh(name, circle.mesh()).unwrap();
-        renderer.get_mesh_mut(name).unwrap().rotation = Quat::from_euler(EulerRot::XYZ, 0.0, 0.0, rand_betw(0.0, 2.0*std::f32::consts::PI));
+        renderer.get_mesh_mut(name).unwrap().set_rotation(Quat::from_euler(EulerRot::XYZ, 0.0, 0.0, rand_betw(0.0, 2.0*std::f32::consts::PI)));
 
         Self {
             p,
@@ -172,8 +172,8 @@ impl Particle {
 
     pub fn update_mesh(&self, renderer: &mut Renderer) {
         if let Some(mesh) = renderer.get_mesh_mut(&self.name) {
-            mesh.position.x = lerp(mesh.position.x, self.p.x, 0.5);
-            mesh.position.y = lerp(mesh.position.y, self.p.y, 0.5);
+            let p = mesh.transform.position();
+            mesh.set_position(vec3(lerp(p.x, self.p.x, 0.5), lerp(p.y, self.p.y, 0.5), p.z));
         }
     }
 }
@@ -0,0 +1,95 @@
+use std::{fmt, path::{Path, PathBuf}};
+
+use gl::types::GLenum;
+
+use crate::ColorFormat;
+
+/// What can go wrong loading models, textures and other assets, or creating GL objects
+#[derive(Debug)]
+pub enum Error {
+    /// the file isn't there or couldn't be read
+    MissingFile { path: PathBuf, reason: String },
+    /// the file was read but its contents are broken
+    Parse { path: PathBuf, reason: String },
+    /// a mesh lacks something there's no fallback for, like positions
+    MissingAttribute { path: PathBuf, attribute: &'static str },
+    /// a kind of file, or a feature of one, that isn't handled
+    UnsupportedFormat { path: PathBuf, format: String },
+    /// the driver rejected a framebuffer, `status` is what `glCheckFramebufferStatus` returned
+    IncompleteFramebuffer { width: u32, height: u32, format: ColorFormat, status: GLenum },
+}
+
+impl Error {
+    /// The file the error is about, `None` for errors that don't come from a file
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::MissingFile { path, .. }
+            | Error::Parse { path, .. }
+            | Error::MissingAttribute { path, .. }
+            | Error::UnsupportedFormat { path, .. } => Some(path),
+            Error::IncompleteFramebuffer { .. } => None,
+        }
+    }
+
+    pub(crate) fn from_image(path: &Path, error: image::ImageError) -> Self {
+        let path = path.to_owned();
+        match error {
+            image::ImageError::IoError(e) => Error::MissingFile { path, reason: e.to_string() },
+            image::ImageError::Unsupported(e) => Error::UnsupportedFormat { path, format: e.to_string() },
+            e => Error::Parse { path, reason: e.to_string() },
+        }
+    }
+
+    pub(crate) fn from_obj(path: &Path, error: tobj::LoadError) -> Self {
+        let path = path.to_owned();
+        match error {
+            tobj::LoadError::OpenFileFailed | tobj::LoadError::ReadError => {
+                Error::MissingFile { path, reason: error.to_string() }
+            }
+            e => Error::Parse { path, reason: e.to_string() },
+        }
+    }
+
+    // tobj doesn't say where the .mtl file was, so the error points at the OBJ
+    pub(crate) fn from_mtl(path: &Path, error: tobj::LoadError) -> Self {
+        let reason = format!("its .mtl file: {}", error);
+        match Self::from_obj(path, error) {
+            Error::MissingFile { path, .. } => Error::MissingFile { path, reason },
+            _ => Error::Parse { path: path.to_owned(), reason },
+        }
+    }
+
+    pub(crate) fn from_gltf(path: &Path, error: gltf::Error) -> Self {
+        let path = path.to_owned();
+        match error {
+            gltf::Error::Io(e) => Error::MissingFile { path, reason: e.to_string() },
+            e @ (gltf::Error::UnsupportedImageEncoding
+                | gltf::Error::UnsupportedImageFormat(_)
+                | gltf::Error::UnsupportedScheme) => Error::UnsupportedFormat { path, format: e.to_string() },
+            e => Error::Parse { path, reason: e.to_string() },
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MissingFile { path, reason } => write!(f, "couldn't read '{}': {}", path.display(), reason),
+            Error::Parse { path, reason } => write!(f, "couldn't parse '{}': {}", path.display(), reason),
+            Error::MissingAttribute { path, attribute } => write!(f, "'{}' has a mesh without {}", path.display(), attribute),
+            Error::UnsupportedFormat { path, format } => write!(f, "'{}' is unsupported: {}", path.display(), format),
+            Error::IncompleteFramebuffer { width, height, format, status } => {
+                write!(f, "{}x{} {:?} framebuffer is incomplete (status {:#x})", width, height, format, status)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// most of the framework reports errors as strings
+impl From<Error> for String {
+    fn from(error: Error) -> Self {
+        error.to_string()
+    }
+}
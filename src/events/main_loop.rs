@@ -23,13 +23,28 @@ pub struct EventLoop {
     pub time: f32,
 
     pub timescale: f32,
+
+    pub headless: bool,
 }
 
 impl EventLoop {
     pub fn new(w: u32, h: u32) -> Self {
+        Self::create(w, h, false)
+    }
+
+    /// Same as `new` but the window is never shown, so `Renderer::draw` can run on build
+    /// machines without a visible desktop. It's still a GLFW window, so a display
+    /// connection is needed: on machines without one run under an X server like `xvfb-run`
+    pub fn new_headless(w: u32, h: u32) -> Self {
+        Self::create(w, h, true)
+    }
+
+    fn create(w: u32, h: u32, headless: bool) -> Self {
         let mut glfw = glfw::init(fail_on_errors!()).unwrap();
-        
+
+        // the same context hints either way, so offscreen frames match on screen ones
         glfw.window_hint(glfw::WindowHint::TransparentFramebuffer(true));
+        glfw.window_hint(glfw::WindowHint::Visible(!headless));
 
         let (mut window, events) = glfw.create_window(w, h, "Hello this is window", glfw::WindowMode::Windowed)
         .expect("Failed to create GLFW window.");
@@ -51,6 +66,12 @@ impl EventLoop {
             BlendFunc(SRC_ALPHA, ONE_MINUS_SRC_ALPHA);
             Enable(BLEND | DEPTH_TEST);
         }
+
+        if headless {
+            // no FramebufferSize event ever arrives for a hidden window
+            let (fw, fh) = window.get_framebuffer_size();
+            unsafe { Viewport(0, 0, fw, fh) };
+        }
     
         let mut event_handler = EventHandler::new();
         event_handler.on_window_resize(w as i32, h as i32);
@@ -65,6 +86,7 @@ impl EventLoop {
             dt: 0.0,
             time: 0.0,
             timescale: 1.0,
+            headless,
         }
     }
 
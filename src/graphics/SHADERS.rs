@@ -31,14 +31,257 @@ in vec4 fColor;
 in vec2 TexCoord; // Receive texture coordinates
 
 uniform sampler2D texture1; // Texture sampler
+uniform int has_texture;
+uniform vec4 base_color;
 
 void main()
 {
-    vec4 texColor = texture(texture1, TexCoord); // Sample the texture
-    FragColor = texColor * fColor; // Combine texture color and vertex color
+    vec4 texColor = has_texture == 1 ? texture(texture1, TexCoord) : vec4(1.0); // Sample the texture
+    FragColor = texColor * fColor * base_color; // Combine texture color and vertex color
 }
 "#;
 
+// lights as fed by `Renderer::send_light_uniforms`, shared by every shader that gets lit.
+// each light is 4 texels of `lightData`, `lightIndices` starts with the lights that reach
+// every fragment and then holds one list per cluster, which `lightGrid` points into
+macro_rules! lights_glsl {
+    () => {
+r#"
+uniform vec3 viewPos;
+uniform mat4 view;
+
+uniform samplerBuffer lightData;
+uniform usamplerBuffer lightGrid; // (offset, count) into lightIndices for each cluster
+uniform usamplerBuffer lightIndices;
+
+uniform int num_global_lights;
+uniform int clustered;
+uniform ivec3 clusterDims;
+uniform vec2 clusterDepth; // near, far
+uniform vec4 clusterViewport;
+
+struct LightInfo {
+    vec3 position;
+    int type; // 0 directional, 1 point, 2 spot
+    vec3 color;
+    float intensity;
+    vec3 direction;
+    float range;
+    vec2 cone; // cos of the inner and outer angles
+};
+
+LightInfo fetch_light(int i)
+{
+    vec4 a = texelFetch(lightData, i * 4);
+    vec4 b = texelFetch(lightData, i * 4 + 1);
+    vec4 c = texelFetch(lightData, i * 4 + 2);
+    vec4 d = texelFetch(lightData, i * 4 + 3);
+
+    return LightInfo(a.xyz, int(a.w), b.rgb, b.a, c.xyz, c.w, d.xy);
+}
+
+// the (offset, count) of the cluster this fragment falls in
+uvec2 cluster_cell()
+{
+    if (clustered == 0) {
+        return uvec2(0u);
+    }
+
+    float depth = -(view * vec4(FragPos, 1.0)).z;
+    vec2 uv = (gl_FragCoord.xy - clusterViewport.xy) / clusterViewport.zw;
+
+    ivec3 cell;
+    cell.xy = clamp(ivec2(uv * vec2(clusterDims.xy)), ivec2(0), clusterDims.xy - 1);
+    float slice = log(max(depth, clusterDepth.x) / clusterDepth.x) / log(clusterDepth.y / clusterDepth.x);
+    cell.z = clamp(int(slice * float(clusterDims.z)), 0, clusterDims.z - 1);
+
+    int index = cell.x + cell.y * clusterDims.x + cell.z * clusterDims.x * clusterDims.y;
+    return texelFetch(lightGrid, index).rg;
+}
+
+// index of the n-th light reaching this fragment, counting the global ones first
+int light_index(uvec2 cell, int n)
+{
+    int entry = n < num_global_lights ? n : int(cell.x) + n - num_global_lights;
+    return int(texelFetch(lightIndices, entry).r);
+}
+
+// fills in the direction towards the light and returns how much of it reaches this fragment
+float light_falloff(LightInfo light, out vec3 lightDir)
+{
+    if (light.type == 0) {
+        lightDir = normalize(-light.direction);
+        return light.intensity;
+    }
+
+    vec3 toLight = light.position - FragPos;
+    float dist = length(toLight);
+    lightDir = toLight / max(dist, 0.0001);
+
+    float attenuation = 1.0;
+    if (light.range > 0.0) {
+        // roughly inverse square, windowed so it hits zero right at the range
+        float x = dist / light.range;
+        float window = clamp(1.0 - x * x * x * x, 0.0, 1.0);
+        attenuation = window * window / (1.0 + 25.0 * x * x);
+    }
+
+    if (light.type == 2) {
+        float theta = dot(lightDir, normalize(-light.direction));
+        float epsilon = max(light.cone.x - light.cone.y, 0.0001);
+        attenuation *= clamp((theta - light.cone.y) / epsilon, 0.0, 1.0);
+    }
+
+    return attenuation * light.intensity;
+}
+"#
+    };
+}
+
+// shadow lookups as fed by `Renderer::send_shadow_uniforms`, needs `lights_glsl!` first
+macro_rules! shadows_glsl {
+    () => {
+r#"
+uniform int shadows_enabled;
+uniform int receive_shadows;
+uniform float shadow_bias;
+uniform int pcf_radius;
+
+uniform sampler2DArray shadowMap;
+uniform int shadow_light;
+uniform int num_cascades;
+uniform mat4 lightSpace[4];
+uniform float cascadeSplits[4];
+
+uniform samplerCube pointShadowMap;
+uniform int point_shadow_light;
+uniform float point_shadow_far;
+
+const vec3 pointShadowOffsets[20] = vec3[](
+    vec3( 1,  1,  1), vec3( 1, -1,  1), vec3(-1, -1,  1), vec3(-1,  1,  1),
+    vec3( 1,  1, -1), vec3( 1, -1, -1), vec3(-1, -1, -1), vec3(-1,  1, -1),
+    vec3( 1,  1,  0), vec3( 1, -1,  0), vec3(-1, -1,  0), vec3(-1,  1,  0),
+    vec3( 1,  0,  1), vec3(-1,  0,  1), vec3( 1,  0, -1), vec3(-1,  0, -1),
+    vec3( 0,  1,  1), vec3( 0, -1,  1), vec3( 0, -1, -1), vec3( 0,  1, -1)
+);
+
+float directional_shadow(vec3 norm, vec3 lightDir)
+{
+    float depth = -(view * vec4(FragPos, 1.0)).z;
+
+    int cascade = num_cascades - 1;
+    for (int i = 0; i < num_cascades; ++i) {
+        if (depth < cascadeSplits[i]) {
+            cascade = i;
+            break;
+        }
+    }
+
+    vec4 lightSpacePos = lightSpace[cascade] * vec4(FragPos, 1.0);
+    vec3 projCoords = lightSpacePos.xyz / lightSpacePos.w * 0.5 + 0.5;
+    if (projCoords.z > 1.0) {
+        return 0.0;
+    }
+
+    float bias = max(shadow_bias * (1.0 - dot(norm, lightDir)), shadow_bias * 0.1);
+    vec2 texel = 1.0 / vec2(textureSize(shadowMap, 0).xy);
+
+    float shadow = 0.0;
+    for (int x = -pcf_radius; x <= pcf_radius; ++x) {
+        for (int y = -pcf_radius; y <= pcf_radius; ++y) {
+            float closest = texture(shadowMap, vec3(projCoords.xy + vec2(x, y) * texel, float(cascade))).r;
+            shadow += projCoords.z - bias > closest ? 1.0 : 0.0;
+        }
+    }
+
+    float samples = float((2 * pcf_radius + 1) * (2 * pcf_radius + 1));
+    return shadow / samples;
+}
+
+float point_shadow(vec3 lightPosition)
+{
+    vec3 toFrag = FragPos - lightPosition;
+    float current = length(toFrag);
+    if (current > point_shadow_far) {
+        return 0.0;
+    }
+
+    float bias = 0.05;
+    float radius = (1.0 + length(viewPos - FragPos) / point_shadow_far) / 50.0;
+
+    if (pcf_radius == 0) {
+        float closest = texture(pointShadowMap, toFrag).r * point_shadow_far;
+        return current - bias > closest ? 1.0 : 0.0;
+    }
+
+    float shadow = 0.0;
+    for (int i = 0; i < 20; ++i) {
+        float closest = texture(pointShadowMap, toFrag + pointShadowOffsets[i] * radius).r * point_shadow_far;
+        shadow += current - bias > closest ? 1.0 : 0.0;
+    }
+
+    return shadow / 20.0;
+}
+
+// how much of light i is blocked at this fragment
+float shadow_factor(int i, vec3 norm, vec3 lightDir, vec3 lightPosition)
+{
+    if (shadows_enabled == 0 || receive_shadows == 0) {
+        return 0.0;
+    }
+
+    if (i == shadow_light) {
+        return directional_shadow(norm, lightDir);
+    } else if (i == point_shadow_light) {
+        return point_shadow(lightPosition);
+    }
+
+    return 0.0;
+}
+"#
+    };
+}
+
+// `surface_normal()`, the interpolated normal bent by `normalMap` when `has_normal_map` is set.
+// uses the vertex tangents, or a frame built from screen space derivatives when a mesh has none
+macro_rules! normal_map_glsl {
+    () => {
+r#"
+uniform int has_normal_map;
+uniform sampler2D normalMap;
+
+vec3 surface_normal()
+{
+    vec3 N = normalize(Normal);
+    if (has_normal_map == 0) {
+        return N;
+    }
+
+    vec3 mapped = texture(normalMap, TexCoord).xyz * 2.0 - 1.0;
+
+    if (dot(Tangent.xyz, Tangent.xyz) > 0.0001) {
+        vec3 T = normalize(Tangent.xyz - N * dot(N, Tangent.xyz));
+        vec3 B = cross(N, T) * Tangent.w;
+        return normalize(mat3(T, B, N) * mapped);
+    }
+
+    vec3 dp1 = dFdx(FragPos);
+    vec3 dp2 = dFdy(FragPos);
+    vec2 duv1 = dFdx(TexCoord);
+    vec2 duv2 = dFdy(TexCoord);
+
+    vec3 dp2perp = cross(dp2, N);
+    vec3 dp1perp = cross(N, dp1);
+    vec3 T = dp2perp * duv1.x + dp1perp * duv2.x;
+    vec3 B = dp2perp * duv1.y + dp1perp * duv2.y;
+    float invmax = inversesqrt(max(max(dot(T, T), dot(B, B)), 1e-12));
+
+    return normalize(mat3(T * invmax, B * invmax, N) * mapped);
+}
+"#
+    };
+}
+
 pub static LIGHT_MESH_SHADER_VS: &str = r#"
 #version 330 core
 layout (location = 0) in vec3 aPos;
@@ -66,7 +309,7 @@ void main() {
 }
 "#;
 
-pub static LIGHT_MESH_SHADER_FS: &str = r#"
+pub static LIGHT_MESH_SHADER_FS: &str = concat!(r#"
 #version 330 core
 
 out vec4 FragColor;
@@ -74,111 +317,729 @@ out vec4 FragColor;
 in vec4 fColor;
 in vec3 Normal;
 in vec3 FragPos;  
-
-uniform vec3 lightColor;
-uniform vec3 lightPos;
-uniform vec3 viewPos;
+"#, lights_glsl!(), r#"
+uniform vec4 base_color;
+uniform float ambient_strength;
+uniform float specular_strength;
+uniform float shininess;
 
 void main()
 {
-    float ambientStrength = 0.1;
-    vec3 ambient = ambientStrength * lightColor;
-
     vec3 norm = normalize(Normal);
-    vec3 lightDir = normalize(lightPos - FragPos); 
+    vec3 viewDir = normalize(viewPos - FragPos);
 
-    float diff = max(dot(norm, lightDir), 0.0);
+    vec3 result = vec3(ambient_strength);
 
-    vec3 diffuse = diff * lightColor;
-    float specularStrength = 0.5;
+    uvec2 cell = cluster_cell();
+    int count = num_global_lights + int(cell.y);
+    for (int n = 0; n < count; ++n) {
+        LightInfo light = fetch_light(light_index(cell, n));
 
-    vec3 viewDir = normalize(viewPos - FragPos);
-    vec3 reflectDir = reflect(-lightDir, norm); 
+        vec3 lightDir;
+        float strength = light_falloff(light, lightDir);
+        if (strength <= 0.0) {
+            continue;
+        }
 
-    float spec = pow(max(dot(viewDir, reflectDir), 0.0), 32);
-    vec3 specular = specularStrength * spec * lightColor;  
-    
-    vec3 result = (ambient + diffuse + specular) * vec3(fColor);
-    FragColor = vec4(result, 1.0);
+        float diff = max(dot(norm, lightDir), 0.0);
+        vec3 diffuse = diff * light.color;
+
+        vec3 reflectDir = reflect(-lightDir, norm); 
+
+        float spec = pow(max(dot(viewDir, reflectDir), 0.0), shininess);
+        vec3 specular = specular_strength * spec * light.color;  
+
+        result += (diffuse + specular) * strength;
+    }
+
+    FragColor = vec4(result * vec3(fColor * base_color), 1.0);
 }
-"#;
+"#);
+
+// `apply_morphs()`, adds a mesh's weighted morph target deltas to a vertex. `morph_deltas`
+// holds a position and a normal delta per vertex per target, target after target
+macro_rules! morph_glsl {
+    () => {
+r#"
+uniform samplerBuffer morph_deltas;
+uniform int morph_count;
+uniform int morph_vertex_count;
+uniform float morph_weights[16];
+
+void apply_morphs(inout vec3 position, inout vec3 normal)
+{
+    // vertices added after the deltas were uploaded have none
+    if (gl_VertexID >= morph_vertex_count) return;
 
-pub static FULL_SHADER_VS: &str = r#"
+    for (int i = 0; i < morph_count; ++i) {
+        int texel = (i * morph_vertex_count + gl_VertexID) * 2;
+        position += morph_weights[i] * texelFetch(morph_deltas, texel).xyz;
+        normal += morph_weights[i] * texelFetch(morph_deltas, texel + 1).xyz;
+    }
+}
+"#
+    };
+}
+
+pub static FULL_SHADER_VS: &str = concat!(r#"
 #version 330 core
 layout (location = 0) in vec3 aPos;
 layout (location = 1) in vec4 aColor;
 layout (location = 2) in vec2 aTexCoord;
 layout (location = 3) in vec3 aNormal;
-
+layout (location = 4) in vec4 aTangent;
+"#, morph_glsl!(), r#"
 uniform mat4 model;
 uniform mat4 view;
 uniform mat4 proj;
 
 out vec4 fColor;
 out vec3 Normal;
+out vec4 Tangent;
 out vec3 FragPos;
 out vec2 TexCoord; // Pass texture coordinates to the fragment shader
 
 void main() {
-    gl_Position = proj * view * model * vec4(aPos, 1.0);
+    vec3 position = aPos;
+    vec3 normal = aNormal;
+    apply_morphs(position, normal);
+
+    gl_Position = proj * view * model * vec4(position, 1.0);
     fColor = aColor;
     TexCoord = aTexCoord; // Pass texture coordinates
-    FragPos = vec3(model * vec4(aPos, 1.0));
-    Normal = mat3(transpose(inverse(model))) * aNormal;  
+    FragPos = vec3(model * vec4(position, 1.0));
+    Normal = mat3(transpose(inverse(model))) * normal;
+    Tangent = vec4(mat3(model) * aTangent.xyz, aTangent.w);
 }
-"#;
+"#);
 
-pub static FULL_SHADER_FS: &str = r#"
+pub static FULL_SHADER_FS: &str = concat!(r#"
 #version 330 core
 out vec4 FragColor;
 
 in vec4 fColor;
 in vec2 TexCoord;
 in vec3 Normal;
+in vec4 Tangent;
 in vec3 FragPos;  
-
-uniform vec3 lightColor[5];
-uniform vec3 lightPos[5];
-uniform vec3 viewPos;
+"#, lights_glsl!(), shadows_glsl!(), r#"
+// material
+uniform vec4 base_color;
+uniform float ambient_strength;
+uniform vec3 ambient_color;
+uniform float specular_strength;
+uniform vec3 specular_color;
+uniform float shininess;
 
 uniform int has_texture;
-uniform int num_lights;
+uniform int has_specular_map;
+uniform int has_emissive_map;
 
 uniform sampler2D texture1;
-
+uniform sampler2D specularMap;
+uniform sampler2D emissiveMap;
+
+// mirror-like reflections of the skybox
+uniform float reflectivity;
+uniform int has_reflection_map;
+uniform samplerCube reflectionMap;
+"#, normal_map_glsl!(), r#"
 void main()
 {
-    // vec4 texColor = fColor;
+    vec4 texColor = fColor * base_color;
+    if (has_texture == 1) {
+        texColor *= texture(texture1, TexCoord);
+    }
+
+    float specularStrength = specular_strength;
+    if (has_specular_map == 1) {
+        specularStrength *= texture(specularMap, TexCoord).r;
+    }
 
-    //if (has_texture == 1) {
-        // }
-    vec4 texColor = texture(texture1, TexCoord) * fColor;
+    vec3 norm = surface_normal();
+    vec3 viewDir = normalize(viewPos - FragPos);
 
-    vec3 ambientStrength = vec3(0.1); 
-    vec3 specularStrength = vec3(0.5);
+    vec3 result = ambient_strength * ambient_color * texColor.rgb;
+    if (has_emissive_map == 1) {
+        result += texture(emissiveMap, TexCoord).rgb;
+    }
 
-    vec3 result = vec3(0.0);
+    uvec2 cell = cluster_cell();
+    int count = num_global_lights + int(cell.y);
+    for (int n = 0; n < count; ++n) {
+        int i = light_index(cell, n);
+        LightInfo light = fetch_light(i);
 
-    for (int i = 0; i < num_lights; ++i) {
-        vec3 lightDir = normalize(lightPos[i] - FragPos); 
-        vec3 norm = normalize(Normal);
+        vec3 lightDir;
+        float strength = light_falloff(light, lightDir);
+        if (strength <= 0.0) {
+            continue;
+        }
 
         float diff = max(dot(norm, lightDir), 0.0);
-        vec3 diffuse = diff * lightColor[i];
+        vec3 diffuse = diff * light.color;
 
-        vec3 viewDir = normalize(viewPos - FragPos);
         vec3 reflectDir = reflect(-lightDir, norm); 
 
-        float spec = pow(max(dot(viewDir, reflectDir), 0.0), 32);
-        vec3 specular = specularStrength * spec * lightColor[i];
+        float spec = pow(max(dot(viewDir, reflectDir), 0.0), shininess);
+        vec3 specular = specularStrength * spec * specular_color * light.color;
+
+        float shadow = shadow_factor(i, norm, lightDir, light.position);
+
+        result += (1.0 - shadow) * (diffuse + specular) * strength * texColor.rgb;
+    }
 
-        result += (ambientStrength + diffuse + specular) * texColor.rgb;
+    if (has_reflection_map == 1 && reflectivity > 0.0) {
+        vec3 reflected = texture(reflectionMap, reflect(-viewDir, norm)).rgb;
+        result = mix(result, reflected * texColor.rgb, reflectivity);
     }
 
     FragColor = vec4(result, texColor.a);
 }
+"#);
+
+// metallic/roughness pbr with the same vertex inputs as the full shader, lit by the
+// renderer's lights and its `Environment` when there is one
+pub static PBR_SHADER_FS: &str = concat!(r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec4 fColor;
+in vec2 TexCoord;
+in vec3 Normal;
+in vec4 Tangent;
+in vec3 FragPos;
+"#, lights_glsl!(), shadows_glsl!(), normal_map_glsl!(), r#"
+// material
+uniform vec4 base_color;
+uniform float metallic;
+uniform float roughness;
+uniform vec3 emissive_factor;
+uniform float occlusion_strength;
+uniform float ambient_strength;
+uniform int tonemap;
+
+uniform int has_texture;
+uniform int has_specular_map; // glTF layout, roughness in green and metallic in blue
+uniform int has_emissive_map;
+uniform int has_occlusion_map;
+
+uniform sampler2D texture1;
+uniform sampler2D specularMap;
+uniform sampler2D emissiveMap;
+uniform sampler2D occlusionMap;
+
+// image based lighting
+uniform int has_environment;
+uniform float environment_intensity;
+uniform float prefilter_mips;
+uniform samplerCube irradianceMap;
+uniform samplerCube prefilterMap;
+uniform sampler2D brdfLut;
+
+const float PI = 3.14159265359;
+
+float distribution_ggx(float NdotH, float roughness)
+{
+    float a2 = roughness * roughness * roughness * roughness;
+    float d = NdotH * NdotH * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+float geometry_smith(float NdotV, float NdotL, float roughness)
+{
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    float ggx_v = NdotV / (NdotV * (1.0 - k) + k);
+    float ggx_l = NdotL / (NdotL * (1.0 - k) + k);
+    return ggx_v * ggx_l;
+}
+
+vec3 fresnel_schlick(float cosTheta, vec3 F0)
+{
+    return F0 + (1.0 - F0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+vec3 fresnel_schlick_roughness(float cosTheta, vec3 F0, float roughness)
+{
+    return F0 + (max(vec3(1.0 - roughness), F0) - F0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+vec3 aces(vec3 x)
+{
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main()
+{
+    vec4 albedo = fColor * base_color;
+    if (has_texture == 1) {
+        vec4 texel = texture(texture1, TexCoord);
+        albedo *= vec4(pow(texel.rgb, vec3(2.2)), texel.a);
+    }
+
+    float metal = metallic;
+    float rough = roughness;
+    if (has_specular_map == 1) {
+        vec4 mr = texture(specularMap, TexCoord);
+        rough *= mr.g;
+        metal *= mr.b;
+    }
+    rough = clamp(rough, 0.04, 1.0);
+
+    float ao = 1.0;
+    if (has_occlusion_map == 1) {
+        ao = 1.0 + occlusion_strength * (texture(occlusionMap, TexCoord).r - 1.0);
+    }
+
+    vec3 N = surface_normal();
+    vec3 V = normalize(viewPos - FragPos);
+    float NdotV = max(dot(N, V), 0.0001);
+
+    vec3 F0 = mix(vec3(0.04), albedo.rgb, metal);
+    vec3 Lo = vec3(0.0);
+
+    uvec2 cell = cluster_cell();
+    int count = num_global_lights + int(cell.y);
+    for (int n = 0; n < count; ++n) {
+        int i = light_index(cell, n);
+        LightInfo light = fetch_light(i);
+
+        vec3 L;
+        float strength = light_falloff(light, L);
+        float NdotL = max(dot(N, L), 0.0);
+        if (strength <= 0.0 || NdotL <= 0.0) {
+            continue;
+        }
+
+        vec3 H = normalize(V + L);
+        float NdotH = max(dot(N, H), 0.0);
+
+        float D = distribution_ggx(NdotH, rough);
+        float G = geometry_smith(NdotV, NdotL, rough);
+        vec3 F = fresnel_schlick(max(dot(H, V), 0.0), F0);
+
+        vec3 specular = D * G * F / (4.0 * NdotV * NdotL + 0.0001);
+        vec3 kD = (1.0 - F) * (1.0 - metal);
+
+        float shadow = shadow_factor(i, N, L, light.position);
+        Lo += (1.0 - shadow) * (kD * albedo.rgb / PI + specular) * light.color * strength * NdotL;
+    }
+
+    vec3 ambient;
+    if (has_environment == 1) {
+        vec3 F = fresnel_schlick_roughness(NdotV, F0, rough);
+        vec3 kD = (1.0 - F) * (1.0 - metal);
+
+        vec3 diffuse = texture(irradianceMap, N).rgb * albedo.rgb;
+
+        vec3 R = reflect(-V, N);
+        vec3 prefiltered = textureLod(prefilterMap, R, rough * prefilter_mips).rgb;
+        vec2 brdf = texture(brdfLut, vec2(NdotV, rough)).rg;
+        vec3 specular = prefiltered * (F * brdf.x + brdf.y);
+
+        ambient = (kD * diffuse + specular) * ao * environment_intensity;
+    } else {
+        ambient = vec3(ambient_strength) * albedo.rgb * ao;
+    }
+
+    vec3 emissive = emissive_factor;
+    if (has_emissive_map == 1) {
+        emissive *= pow(texture(emissiveMap, TexCoord).rgb, vec3(2.2));
+    }
+
+    vec3 color = ambient + Lo + emissive;
+    if (tonemap == 1) {
+        color = pow(aces(color), vec3(1.0 / 2.2));
+    }
+
+    FragColor = vec4(color, albedo.a);
+}
+"#);
+
+// unit cube seen from its center, for rendering into cubemap faces
+pub static CUBEMAP_CAPTURE_VS: &str = r#"
+#version 330 core
+layout (location = 0) in vec3 aPos;
+
+uniform mat4 view;
+uniform mat4 proj;
+
+out vec3 WorldPos;
+
+void main() {
+    WorldPos = aPos;
+    gl_Position = proj * view * vec4(aPos, 1.0);
+}
+"#;
+
+pub static EQUIRECT_TO_CUBEMAP_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec3 WorldPos;
+
+uniform sampler2D equirect;
+
+const vec2 invAtan = vec2(0.1591, 0.3183);
+
+void main()
+{
+    vec3 v = normalize(WorldPos);
+    vec2 uv = vec2(atan(v.z, v.x), asin(v.y)) * invAtan + 0.5;
+    FragColor = vec4(texture(equirect, uv).rgb, 1.0);
+}
+"#;
+
+// cosine weighted convolution of the environment for diffuse lighting
+pub static IRRADIANCE_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec3 WorldPos;
+
+uniform samplerCube environment;
+
+const float PI = 3.14159265359;
+
+void main()
+{
+    vec3 N = normalize(WorldPos);
+    vec3 up = abs(N.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+    vec3 right = normalize(cross(up, N));
+    up = normalize(cross(N, right));
+
+    vec3 irradiance = vec3(0.0);
+    float samples = 0.0;
+    float delta = 0.025;
+    for (float phi = 0.0; phi < 2.0 * PI; phi += delta) {
+        for (float theta = 0.0; theta < 0.5 * PI; theta += delta) {
+            vec3 tangentSample = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 dir = tangentSample.x * right + tangentSample.y * up + tangentSample.z * N;
+
+            irradiance += texture(environment, dir).rgb * cos(theta) * sin(theta);
+            samples += 1.0;
+        }
+    }
+
+    FragColor = vec4(PI * irradiance / samples, 1.0);
+}
+"#;
+
+// ggx importance sampled environment, one roughness per mip level
+pub static PREFILTER_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec3 WorldPos;
+
+uniform samplerCube environment;
+uniform float roughness;
+uniform float resolution;
+
+const float PI = 3.14159265359;
+const uint SAMPLES = 512u;
+
+float radical_inverse(uint bits)
+{
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return float(bits) * 2.3283064365386963e-10;
+}
+
+vec3 importance_sample_ggx(vec2 Xi, vec3 N, float roughness)
+{
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * Xi.x;
+    float cosTheta = sqrt((1.0 - Xi.y) / (1.0 + (a * a - 1.0) * Xi.y));
+    float sinTheta = sqrt(1.0 - cosTheta * cosTheta);
+
+    vec3 H = vec3(cos(phi) * sinTheta, sin(phi) * sinTheta, cosTheta);
+    vec3 up = abs(N.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, N));
+    vec3 bitangent = cross(N, tangent);
+
+    return normalize(tangent * H.x + bitangent * H.y + N * H.z);
+}
+
+void main()
+{
+    vec3 N = normalize(WorldPos);
+    vec3 V = N;
+
+    vec3 color = vec3(0.0);
+    float weight = 0.0;
+    for (uint i = 0u; i < SAMPLES; ++i) {
+        vec2 Xi = vec2(float(i) / float(SAMPLES), radical_inverse(i));
+        vec3 H = importance_sample_ggx(Xi, N, roughness);
+        vec3 L = normalize(2.0 * dot(V, H) * H - V);
+
+        float NdotL = max(dot(N, L), 0.0);
+        if (NdotL > 0.0) {
+            // sample a blurrier mip where few samples cover a lot of the sphere
+            float NdotH = max(dot(N, H), 0.0);
+            float a2 = roughness * roughness * roughness * roughness;
+            float d = NdotH * NdotH * (a2 - 1.0) + 1.0;
+            float pdf = a2 / (PI * d * d) / 4.0 + 0.0001;
+            float saTexel = 4.0 * PI / (6.0 * resolution * resolution);
+            float saSample = 1.0 / (float(SAMPLES) * pdf + 0.0001);
+            float mip = roughness == 0.0 ? 0.0 : 0.5 * log2(saSample / saTexel);
+
+            color += textureLod(environment, L, mip).rgb * NdotL;
+            weight += NdotL;
+        }
+    }
+
+    FragColor = vec4(color / weight, 1.0);
+}
 "#;
 
+// split sum brdf lookup, drawn with POST_PROCESS_VS
+pub static BRDF_LUT_FS: &str = r#"
+#version 330 core
+out vec2 FragColor;
+
+in vec2 TexCoord;
+
+const float PI = 3.14159265359;
+const uint SAMPLES = 1024u;
+
+float radical_inverse(uint bits)
+{
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return float(bits) * 2.3283064365386963e-10;
+}
+
+float geometry_schlick(float NdotV, float roughness)
+{
+    float k = roughness * roughness / 2.0;
+    return NdotV / (NdotV * (1.0 - k) + k);
+}
+
+void main()
+{
+    float NdotV = max(TexCoord.x, 0.0001);
+    float roughness = TexCoord.y;
+
+    vec3 V = vec3(sqrt(1.0 - NdotV * NdotV), 0.0, NdotV);
+    vec3 N = vec3(0.0, 0.0, 1.0);
+
+    float A = 0.0;
+    float B = 0.0;
+    for (uint i = 0u; i < SAMPLES; ++i) {
+        vec2 Xi = vec2(float(i) / float(SAMPLES), radical_inverse(i));
+
+        float a = roughness * roughness;
+        float phi = 2.0 * PI * Xi.x;
+        float cosTheta = sqrt((1.0 - Xi.y) / (1.0 + (a * a - 1.0) * Xi.y));
+        float sinTheta = sqrt(1.0 - cosTheta * cosTheta);
+        vec3 H = vec3(cos(phi) * sinTheta, sin(phi) * sinTheta, cosTheta);
+        vec3 L = normalize(2.0 * dot(V, H) * H - V);
+
+        float NdotL = max(L.z, 0.0);
+        float NdotH = max(H.z, 0.0);
+        float VdotH = max(dot(V, H), 0.0);
+
+        if (NdotL > 0.0) {
+            float G = geometry_schlick(NdotV, roughness) * geometry_schlick(NdotL, roughness);
+            float G_Vis = (G * VdotH) / (NdotH * NdotV);
+            float Fc = pow(1.0 - VdotH, 5.0);
+
+            A += (1.0 - Fc) * G_Vis;
+            B += Fc * G_Vis;
+        }
+    }
+
+    FragColor = vec2(A, B) / float(SAMPLES);
+}
+"#;
+
+pub static SKYBOX_VS: &str = r#"
+#version 330 core
+layout (location = 0) in vec3 aPos;
+
+uniform mat4 view;
+uniform mat4 proj;
+
+out vec3 Direction;
+
+void main() {
+    Direction = aPos;
+    vec4 pos = proj * view * vec4(aPos, 1.0);
+    gl_Position = pos.xyww; // always at the far plane
+}
+"#;
+
+pub static SKYBOX_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec3 Direction;
+
+uniform samplerCube skybox;
+uniform float intensity;
+
+void main()
+{
+    FragColor = vec4(texture(skybox, Direction).rgb * intensity, 1.0);
+}
+"#;
+
+pub static SHADOW_DEPTH_VS: &str = concat!(r#"
+#version 330 core
+layout (location = 0) in vec3 aPos;
+"#, morph_glsl!(), r#"
+uniform mat4 lightSpace;
+uniform mat4 model;
+
+void main() {
+    vec3 position = aPos;
+    vec3 normal = vec3(0.0);
+    apply_morphs(position, normal);
+
+    gl_Position = lightSpace * model * vec4(position, 1.0);
+}
+"#);
+
+pub static SHADOW_DEPTH_FS: &str = r#"
+#version 330 core
+
+void main()
+{
+}
+"#;
+
+pub static POINT_SHADOW_DEPTH_VS: &str = concat!(r#"
+#version 330 core
+layout (location = 0) in vec3 aPos;
+"#, morph_glsl!(), r#"
+uniform mat4 lightSpace;
+uniform mat4 model;
+
+out vec3 FragPos;
+
+void main() {
+    vec3 position = aPos;
+    vec3 normal = vec3(0.0);
+    apply_morphs(position, normal);
+
+    FragPos = vec3(model * vec4(position, 1.0));
+    gl_Position = lightSpace * vec4(FragPos, 1.0);
+}
+"#);
+
+// stores the distance to the light (scaled into 0..1) instead of the projected depth
+pub static POINT_SHADOW_DEPTH_FS: &str = r#"
+#version 330 core
+in vec3 FragPos;
+
+uniform vec3 lightPos;
+uniform float far_plane;
+
+void main()
+{
+    gl_FragDepth = length(FragPos - lightPos) / far_plane;
+}
+"#;
+
+// `skin_matrix()`, the blend of the joints a vertex is bound to. `joint_matrices` are the
+// joints' model space matrices times their inverse bind matrices, at most `MAX_JOINTS` of them
+macro_rules! skinning_glsl {
+    () => {
+r#"
+layout (location = 5) in uvec4 aJoints;
+layout (location = 6) in vec4 aWeights;
+
+uniform mat4 joint_matrices[128];
+
+mat4 skin_matrix()
+{
+    return aWeights.x * joint_matrices[aJoints.x]
+         + aWeights.y * joint_matrices[aJoints.y]
+         + aWeights.z * joint_matrices[aJoints.z]
+         + aWeights.w * joint_matrices[aJoints.w];
+}
+"#
+    };
+}
+
+// the full shader's vertex stage with the vertices moved by their joints after morphing,
+// paired with `FULL_SHADER_FS` or `PBR_SHADER_FS`
+pub static SKINNED_SHADER_VS: &str = concat!(r#"
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec4 aColor;
+layout (location = 2) in vec2 aTexCoord;
+layout (location = 3) in vec3 aNormal;
+layout (location = 4) in vec4 aTangent;
+"#, skinning_glsl!(), morph_glsl!(), r#"
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 proj;
+
+out vec4 fColor;
+out vec3 Normal;
+out vec4 Tangent;
+out vec3 FragPos;
+out vec2 TexCoord;
+
+void main() {
+    vec3 position = aPos;
+    vec3 normal = aNormal;
+    apply_morphs(position, normal);
+
+    mat4 skinned = model * skin_matrix();
+
+    gl_Position = proj * view * skinned * vec4(position, 1.0);
+    fColor = aColor;
+    TexCoord = aTexCoord;
+    FragPos = vec3(skinned * vec4(position, 1.0));
+    Normal = mat3(transpose(inverse(skinned))) * normal;
+    Tangent = vec4(mat3(skinned) * aTangent.xyz, aTangent.w);
+}
+"#);
+
+pub static SKINNED_SHADOW_DEPTH_VS: &str = concat!(r#"
+#version 330 core
+layout (location = 0) in vec3 aPos;
+"#, skinning_glsl!(), morph_glsl!(), r#"
+uniform mat4 lightSpace;
+uniform mat4 model;
+
+void main() {
+    vec3 position = aPos;
+    vec3 normal = vec3(0.0);
+    apply_morphs(position, normal);
+
+    gl_Position = lightSpace * model * skin_matrix() * vec4(position, 1.0);
+}
+"#);
+
+pub static SKINNED_POINT_SHADOW_DEPTH_VS: &str = concat!(r#"
+#version 330 core
+layout (location = 0) in vec3 aPos;
+"#, skinning_glsl!(), morph_glsl!(), r#"
+uniform mat4 lightSpace;
+uniform mat4 model;
+
+out vec3 FragPos;
+
+void main() {
+    vec3 position = aPos;
+    vec3 normal = vec3(0.0);
+    apply_morphs(position, normal);
+
+    FragPos = vec3(model * skin_matrix() * vec4(position, 1.0));
+    gl_Position = lightSpace * vec4(FragPos, 1.0);
+}
+"#);
+
 pub static INSTANCE_MESH_SHADER_VS: &str = r#"
 #version 330 core
 layout (location = 0) in vec3 aPos;
@@ -249,3 +1110,324 @@ void main()
     FragColor = vec4(fColor);
 }
 "#;
+
+// full screen triangle generated from gl_VertexID, draw 3 vertices with an empty VAO
+pub static POST_PROCESS_VS: &str = r#"
+#version 330 core
+out vec2 TexCoord;
+
+void main() {
+    vec2 pos = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    TexCoord = pos;
+    gl_Position = vec4(pos * 2.0 - 1.0, 0.0, 1.0);
+}
+"#;
+
+pub static POST_COPY_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D screen;
+
+void main()
+{
+    FragColor = vec4(texture(screen, TexCoord).rgb, 1.0);
+}
+"#;
+
+pub static POST_TONEMAP_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D screen;
+uniform float exposure;
+uniform float gamma;
+
+// Narkowicz's ACES filmic curve fit
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main()
+{
+    vec3 hdr = texture(screen, TexCoord).rgb * exposure;
+    vec3 mapped = aces(hdr);
+    FragColor = vec4(pow(mapped, vec3(1.0 / gamma)), 1.0);
+}
+"#;
+
+pub static POST_FXAA_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D screen;
+uniform vec2 resolution;
+
+#define FXAA_REDUCE_MIN (1.0 / 128.0)
+#define FXAA_REDUCE_MUL (1.0 / 8.0)
+#define FXAA_SPAN_MAX 8.0
+
+void main()
+{
+    vec2 inv = 1.0 / resolution;
+    vec3 luma = vec3(0.299, 0.587, 0.114);
+
+    vec3 rgbNW = texture(screen, TexCoord + vec2(-1.0, -1.0) * inv).rgb;
+    vec3 rgbNE = texture(screen, TexCoord + vec2( 1.0, -1.0) * inv).rgb;
+    vec3 rgbSW = texture(screen, TexCoord + vec2(-1.0,  1.0) * inv).rgb;
+    vec3 rgbSE = texture(screen, TexCoord + vec2( 1.0,  1.0) * inv).rgb;
+    vec3 rgbM  = texture(screen, TexCoord).rgb;
+
+    float lumaNW = dot(rgbNW, luma);
+    float lumaNE = dot(rgbNE, luma);
+    float lumaSW = dot(rgbSW, luma);
+    float lumaSE = dot(rgbSE, luma);
+    float lumaM  = dot(rgbM, luma);
+
+    float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    vec2 dir;
+    dir.x = -((lumaNW + lumaNE) - (lumaSW + lumaSE));
+    dir.y =  ((lumaNW + lumaSW) - (lumaNE + lumaSE));
+
+    float dirReduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * (0.25 * FXAA_REDUCE_MUL), FXAA_REDUCE_MIN);
+    float rcpDirMin = 1.0 / (min(abs(dir.x), abs(dir.y)) + dirReduce);
+    dir = clamp(dir * rcpDirMin, vec2(-FXAA_SPAN_MAX), vec2(FXAA_SPAN_MAX)) * inv;
+
+    vec3 rgbA = 0.5 * (
+        texture(screen, TexCoord + dir * (1.0 / 3.0 - 0.5)).rgb +
+        texture(screen, TexCoord + dir * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 rgbB = rgbA * 0.5 + 0.25 * (
+        texture(screen, TexCoord + dir * -0.5).rgb +
+        texture(screen, TexCoord + dir * 0.5).rgb);
+
+    float lumaB = dot(rgbB, luma);
+    if (lumaB < lumaMin || lumaB > lumaMax) {
+        FragColor = vec4(rgbA, 1.0);
+    } else {
+        FragColor = vec4(rgbB, 1.0);
+    }
+}
+"#;
+
+pub static POST_BRIGHT_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D screen;
+uniform float threshold;
+
+void main()
+{
+    vec3 color = texture(screen, TexCoord).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    float contribution = max(brightness - threshold, 0.0) / max(brightness, 0.0001);
+    FragColor = vec4(color * contribution, 1.0);
+}
+"#;
+
+pub static POST_BLUR_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D screen;
+uniform vec2 resolution;
+uniform vec2 direction;
+
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main()
+{
+    vec2 texel = direction / resolution;
+    vec3 result = texture(screen, TexCoord).rgb * weights[0];
+
+    for (int i = 1; i < 5; ++i) {
+        result += texture(screen, TexCoord + texel * float(i)).rgb * weights[i];
+        result += texture(screen, TexCoord - texel * float(i)).rgb * weights[i];
+    }
+
+    FragColor = vec4(result, 1.0);
+}
+"#;
+
+pub static POST_BLOOM_COMPOSITE_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D screen;
+uniform sampler2D bloom;
+uniform float intensity;
+
+void main()
+{
+    vec3 color = texture(screen, TexCoord).rgb + texture(bloom, TexCoord).rgb * intensity;
+    FragColor = vec4(color, 1.0);
+}
+"#;
+
+pub static POST_VIGNETTE_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D screen;
+uniform float strength;
+uniform float radius;
+
+void main()
+{
+    vec3 color = texture(screen, TexCoord).rgb;
+    float d = distance(TexCoord, vec2(0.5));
+    color *= 1.0 - strength * smoothstep(radius, radius + 0.4, d);
+    FragColor = vec4(color, 1.0);
+}
+"#;
+
+// expects a strip lut (e.g. 256x16): `size` slices along x, one per blue value,
+// red grows along each slice and green from the top row down
+pub static POST_COLOR_GRADE_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D screen;
+uniform sampler2D lut;
+uniform float lut_size;
+
+vec3 sample_slice(vec3 c, float slice) {
+    float x = (slice * lut_size + c.r * (lut_size - 1.0) + 0.5) / (lut_size * lut_size);
+    float y = (c.g * (lut_size - 1.0) + 0.5) / lut_size;
+    return texture(lut, vec2(x, y)).rgb;
+}
+
+void main()
+{
+    vec3 color = clamp(texture(screen, TexCoord).rgb, 0.0, 1.0);
+
+    float blue = color.b * (lut_size - 1.0);
+    float slice0 = floor(blue);
+    float slice1 = min(slice0 + 1.0, lut_size - 1.0);
+
+    vec3 graded = mix(sample_slice(color, slice0), sample_slice(color, slice1), blue - slice0);
+    FragColor = vec4(graded, 1.0);
+}
+"#;
+
+pub static DEBUG_LINE_SHADER_VS: &str = r#"
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec4 aColor;
+
+uniform mat4 view;
+uniform mat4 proj;
+
+out vec4 fColor;
+
+void main()
+{
+    fColor = aColor;
+    gl_Position = proj * view * vec4(aPos, 1.0);
+}
+"#;
+
+pub static DEBUG_LINE_SHADER_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec4 fColor;
+
+void main()
+{
+    FragColor = fColor;
+}
+"#;
+
+// full screen triangle, each pixel's view ray as the points it crosses the near and far planes at
+pub static GRID_VS: &str = r#"
+#version 330 core
+uniform mat4 view;
+uniform mat4 proj;
+
+out vec3 NearPoint;
+out vec3 FarPoint;
+
+vec3 unproject(vec2 pos, float z) {
+    vec4 world = inverse(proj * view) * vec4(pos, z, 1.0);
+    return world.xyz / world.w;
+}
+
+void main() {
+    vec2 pos = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    NearPoint = unproject(pos, -1.0);
+    FarPoint = unproject(pos, 1.0);
+    gl_Position = vec4(pos, 0.0, 1.0);
+}
+"#;
+
+// the ray hits the y = height plane, lines are antialiased with the derivatives and the
+// depth is written so the scene covers the grid
+pub static GRID_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec3 NearPoint;
+in vec3 FarPoint;
+
+uniform mat4 view;
+uniform mat4 proj;
+uniform float height;
+uniform float cell_size;
+uniform float major_every;
+uniform float fade_distance;
+uniform vec4 line_color;
+uniform vec4 major_color;
+uniform int show_axes;
+
+// 1 on a line, 0 between them
+float lines(vec2 coord) {
+    vec2 width = fwidth(coord);
+    vec2 grid = abs(fract(coord - 0.5) - 0.5) / max(width, vec2(1e-5));
+    return 1.0 - min(min(grid.x, grid.y), 1.0);
+}
+
+void main()
+{
+    float t = (height - NearPoint.y) / (FarPoint.y - NearPoint.y);
+    if (!(t > 0.0)) discard;
+
+    vec3 pos = NearPoint + t * (FarPoint - NearPoint);
+    vec4 clip = proj * view * vec4(pos, 1.0);
+    gl_FragDepth = clip.z / clip.w * 0.5 + 0.5;
+
+    float minor = lines(pos.xz / cell_size);
+    float major = lines(pos.xz / (cell_size * major_every));
+    vec4 color = vec4(mix(line_color.rgb, major_color.rgb, major), max(line_color.a * minor, major_color.a * major));
+
+    if (show_axes == 1) {
+        vec2 width = fwidth(pos.xz);
+        if (abs(pos.z) < width.y) color = vec4(0.9, 0.2, 0.2, 1.0);
+        if (abs(pos.x) < width.x) color = vec4(0.2, 0.3, 0.9, 1.0);
+    }
+
+    vec3 eye = inverse(view)[3].xyz;
+    color.a *= 1.0 - smoothstep(fade_distance * 0.5, fade_distance, distance(pos.xz, eye.xz));
+    if (color.a <= 0.0) discard;
+
+    FragColor = color;
+}
+"#;
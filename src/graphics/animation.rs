@@ -0,0 +1,487 @@
+use std::ops::{Add, Mul};
+
+use glam::{Quat, Vec3};
+
+use crate::{Model, Transform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    /// Holds each key until the next one
+    Step,
+    /// Hermite spline through the keys, with the tangents stored next to each value
+    CubicSpline,
+}
+
+/// Keyframe values of a channel. `CubicSpline` channels have three per key:
+/// in tangent, value, out tangent
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelValues {
+    Translation(Vec<Vec3>),
+    Rotation(Vec<Quat>),
+    Scale(Vec<Vec3>),
+    /// Morph target weights of the node's meshes, one per target for each key
+    Weights(Vec<f32>),
+}
+
+/// Animates one property of one node
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    /// Index into `Model::nodes`
+    pub node: usize,
+    pub interpolation: Interpolation,
+    /// Key times in seconds, ascending
+    pub times: Vec<f32>,
+    pub values: ChannelValues,
+}
+
+impl Channel {
+    /// Sets the channel's property on `transform` to its value at `time`
+    pub fn apply(&self, time: f32, transform: &mut Transform) {
+        let (interpolation, times) = (self.interpolation, &self.times);
+
+        match &self.values {
+            ChannelValues::Translation(values) => {
+                if let Some(position) = sample(interpolation, times, values, time, Vec3::lerp) {
+                    transform.set_position(position);
+                }
+            }
+            ChannelValues::Rotation(values) => {
+                if let Some(rotation) = sample(interpolation, times, values, time, Quat::slerp) {
+                    transform.set_rotation(rotation);
+                }
+            }
+            ChannelValues::Scale(values) => {
+                if let Some(scale) = sample(interpolation, times, values, time, Vec3::lerp) {
+                    transform.set_scale(scale);
+                }
+            }
+            ChannelValues::Weights(_) => (),
+        }
+    }
+
+    /// Sets `weights` to the channel's morph weights at `time`, when it animates them
+    pub fn apply_weights(&self, time: f32, weights: &mut Vec<f32>) {
+        let ChannelValues::Weights(values) = &self.values else {
+            return;
+        };
+
+        let stride = if self.interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+        let count = values.len() / (self.times.len() * stride).max(1);
+        weights.resize(count, 0.0);
+
+        // each target's values on their own, laid out like any other channel's
+        for (i, weight) in weights.iter_mut().enumerate() {
+            let column: Vec<f32> = values.iter().skip(i).step_by(count).copied().collect();
+            if let Some(w) = sample(self.interpolation, &self.times, &column, time, |a, b, t| a + (b - a) * t) {
+                *weight = w;
+            }
+        }
+    }
+}
+
+// the value at `time`, holding the first and last keys outside of their range
+fn sample<T>(interpolation: Interpolation, times: &[f32], values: &[T], time: f32, lerp: impl Fn(T, T, f32) -> T) -> Option<T>
+where
+    T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+{
+    let stride = if interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+    let value = |key: usize| values.get(key * stride + stride / 2).copied();
+
+    let last = times.len().checked_sub(1)?;
+    if time <= times[0] {
+        return value(0);
+    }
+    if time >= times[last] {
+        return value(last);
+    }
+
+    let next = times.partition_point(|&t| t <= time);
+    let prev = next - 1;
+    let dt = times[next] - times[prev];
+    let t = if dt > 0.0 { (time - times[prev]) / dt } else { 0.0 };
+
+    match interpolation {
+        Interpolation::Step => value(prev),
+        Interpolation::Linear => Some(lerp(value(prev)?, value(next)?, t)),
+        Interpolation::CubicSpline => {
+            let (v0, out0) = (value(prev)?, *values.get(prev * 3 + 2)?);
+            let (v1, in1) = (value(next)?, *values.get(next * 3)?);
+            let (t2, t3) = (t * t, t * t * t);
+
+            Some(v0 * (2.0 * t3 - 3.0 * t2 + 1.0)
+                + out0 * (dt * (t3 - 2.0 * t2 + t))
+                + v1 * (-2.0 * t3 + 3.0 * t2)
+                + in1 * (dt * (t3 - t2)))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnimationClip {
+    pub name: String,
+    /// Seconds, the time of the last key of any channel
+    pub duration: f32,
+    pub channels: Vec<Channel>,
+}
+
+impl AnimationClip {
+    /// Poses `pose` (one transform per model node) at `time`, properties the clip
+    /// doesn't animate are left alone
+    pub fn sample(&self, time: f32, pose: &mut [Transform]) {
+        for channel in &self.channels {
+            if let Some(transform) = pose.get_mut(channel.node) {
+                channel.apply(time, transform);
+            }
+        }
+    }
+
+    /// Like `sample`, for the morph weights of each node
+    pub fn sample_weights(&self, time: f32, weights: &mut [Vec<f32>]) {
+        for channel in &self.channels {
+            if let Some(w) = weights.get_mut(channel.node) {
+                channel.apply_weights(time, w);
+            }
+        }
+    }
+}
+
+/// `a` moved `weight` of the way towards `b`
+pub fn blend_transforms(a: &Transform, b: &Transform, weight: f32) -> Transform {
+    Transform::new(
+        a.position().lerp(b.position(), weight),
+        a.rotation().slerp(b.rotation(), weight),
+        a.scale().lerp(b.scale(), weight),
+    )
+}
+
+// morph weights `a` moved `weight` of the way towards `b`, missing ones count as 0
+fn blend_weights(a: &[f32], b: &[f32], weight: f32) -> Vec<f32> {
+    (0..a.len().max(b.len()))
+        .map(|i| {
+            let (a, b) = (a.get(i).copied().unwrap_or(0.0), b.get(i).copied().unwrap_or(0.0));
+            a + (b - a) * weight
+        })
+        .collect()
+}
+
+/// A clip playing on a model
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationState {
+    /// Index into `Model::animations`
+    pub clip: usize,
+    pub time: f32,
+    pub speed: f32,
+    pub weight: f32,
+    pub looping: bool,
+    // weight gained per second, negative while fading out
+    fade: f32,
+}
+
+impl AnimationState {
+    fn new(clip: usize, weight: f32, looping: bool) -> Self {
+        Self { clip, time: 0.0, speed: 1.0, weight, looping, fade: 0.0 }
+    }
+}
+
+/// The clips playing on a model. Their poses are blended by weight, when the weights
+/// add up to less than 1 the rest comes from the pose the nodes had before playing
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Animator {
+    pub states: Vec<AnimationState>,
+    /// Node transforms from before the first clip played
+    pub rest_pose: Vec<Transform>,
+    /// Node morph weights from before the first clip played
+    pub rest_weights: Vec<Vec<f32>>,
+}
+
+impl Model {
+    pub fn find_animation(&self, name: &str) -> Option<usize> {
+        self.animations.iter().position(|a| a.name == name)
+    }
+
+    fn animation_index(&self, name: &str) -> Result<usize, String> {
+        self.find_animation(name).ok_or_else(|| format!("No animation found with name '{}'", name))
+    }
+
+    /// Plays only `name`, from the start
+    pub fn play(&mut self, name: &str, looping: bool) -> Result<(), String> {
+        let clip = self.animation_index(name)?;
+        self.capture_rest_pose();
+        self.animator.states = vec![AnimationState::new(clip, 1.0, looping)];
+        Ok(())
+    }
+
+    /// Fades `name` in over `duration` seconds while everything else playing fades out
+    pub fn cross_fade(&mut self, name: &str, duration: f32, looping: bool) -> Result<(), String> {
+        if duration <= 0.0 {
+            return self.play(name, looping);
+        }
+
+        let clip = self.animation_index(name)?;
+        self.capture_rest_pose();
+
+        let states = &mut self.animator.states;
+        if !states.iter().any(|s| s.clip == clip) {
+            states.push(AnimationState::new(clip, 0.0, looping));
+        }
+        for state in states.iter_mut() {
+            if state.clip == clip {
+                state.looping = looping;
+                state.fade = (1.0 - state.weight) / duration;
+            } else {
+                state.fade = -state.weight / duration;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Plays `name` alongside what's already playing, or changes its weight if it already is
+    pub fn blend(&mut self, name: &str, weight: f32, looping: bool) -> Result<(), String> {
+        let clip = self.animation_index(name)?;
+        self.capture_rest_pose();
+
+        let states = &mut self.animator.states;
+        match states.iter_mut().find(|s| s.clip == clip) {
+            Some(state) => {
+                state.weight = weight;
+                state.fade = 0.0;
+            }
+            None => states.push(AnimationState::new(clip, weight, looping)),
+        }
+
+        Ok(())
+    }
+
+    /// Stops every clip and puts the nodes back how they were before playing
+    pub fn stop(&mut self) {
+        self.animator.states.clear();
+        for (node, rest) in self.nodes.iter_mut().zip(&self.animator.rest_pose) {
+            node.transform = rest.clone();
+        }
+        for (node, rest) in self.nodes.iter_mut().zip(&self.animator.rest_weights) {
+            node.weights = rest.clone();
+        }
+        self.apply_node_weights();
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.animator.states.is_empty()
+    }
+
+    fn capture_rest_pose(&mut self) {
+        if self.animator.rest_pose.len() != self.nodes.len() {
+            self.animator.rest_pose = self.nodes.iter().map(|n| n.transform.clone()).collect();
+            self.animator.rest_weights = self.nodes.iter().map(|n| n.weights.clone()).collect();
+        }
+    }
+
+    // nodes' morph weights onto their meshes, for nodes that have any
+    fn apply_node_weights(&mut self) {
+        for node in &self.nodes {
+            if node.weights.is_empty() {
+                continue;
+            }
+            for &mesh in &node.meshes {
+                self.meshes[mesh].morph_weights = node.weights.clone();
+            }
+        }
+    }
+
+    /// Advances the playing clips by `dt` seconds and poses the nodes, `Renderer::update` calls this.
+    /// Nodes' morph weights are copied to their meshes, replacing what the meshes had
+    pub fn update(&mut self, dt: f32) {
+        if self.animator.states.is_empty() {
+            self.apply_node_weights();
+            return;
+        }
+
+        for state in &mut self.animator.states {
+            let duration = self.animations[state.clip].duration;
+            state.time += dt * state.speed;
+            state.time = if state.looping && duration > 0.0 {
+                state.time.rem_euclid(duration)
+            } else {
+                state.time.clamp(0.0, duration)
+            };
+
+            state.weight += state.fade * dt;
+            if state.fade > 0.0 && state.weight >= 1.0 {
+                state.weight = 1.0;
+                state.fade = 0.0;
+            }
+        }
+        self.animator.states.retain(|s| !(s.fade < 0.0 && s.weight <= 0.0));
+
+        let (rest, rest_weights) = (&self.animator.rest_pose, &self.animator.rest_weights);
+        let mut pose = rest.clone();
+        let mut weights = rest_weights.clone();
+        let mut total = 0.0;
+
+        for state in &self.animator.states {
+            if state.weight <= 0.0 {
+                continue;
+            }
+
+            let clip = &self.animations[state.clip];
+            let mut sampled = rest.clone();
+            let mut sampled_weights = rest_weights.clone();
+            clip.sample(state.time, &mut sampled);
+            clip.sample_weights(state.time, &mut sampled_weights);
+
+            total += state.weight;
+            let weight = state.weight / total;
+            for (p, s) in pose.iter_mut().zip(&sampled) {
+                *p = blend_transforms(p, s, weight);
+            }
+            for (w, s) in weights.iter_mut().zip(&sampled_weights) {
+                *w = blend_weights(w, s, weight);
+            }
+        }
+
+        for ((node, p), r) in self.nodes.iter_mut().zip(pose).zip(rest) {
+            node.transform = if total < 1.0 { blend_transforms(r, &p, total) } else { p };
+        }
+        for ((node, w), r) in self.nodes.iter_mut().zip(weights).zip(rest_weights) {
+            node.weights = if total < 1.0 { blend_weights(r, &w, total) } else { w };
+        }
+        self.apply_node_weights();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec3;
+
+    use super::*;
+    use crate::ModelNode;
+
+    fn lerp(a: f32, b: f32, t: f32) -> f32 {
+        a + (b - a) * t
+    }
+
+    fn translation_clip(name: &str, x: f32) -> AnimationClip {
+        AnimationClip {
+            name: name.to_owned(),
+            duration: 1.0,
+            channels: vec![Channel {
+                node: 0,
+                interpolation: Interpolation::Linear,
+                times: vec![0.0],
+                values: ChannelValues::Translation(vec![vec3(x, 0.0, 0.0)]),
+            }],
+        }
+    }
+
+    #[test]
+    fn step_holds_each_key() {
+        let (times, values) = ([0.0, 1.0, 2.0], [1.0, 3.0, 7.0]);
+
+        assert_eq!(sample(Interpolation::Step, &times, &values, 0.5, lerp), Some(1.0));
+        assert_eq!(sample(Interpolation::Step, &times, &values, 1.0, lerp), Some(3.0));
+        assert_eq!(sample(Interpolation::Step, &times, &values, 1.99, lerp), Some(3.0));
+    }
+
+    #[test]
+    fn linear_clamps_outside_the_keys() {
+        let (times, values) = ([1.0, 3.0], [2.0, 6.0]);
+
+        assert_eq!(sample(Interpolation::Linear, &times, &values, 2.0, lerp), Some(4.0));
+        assert_eq!(sample(Interpolation::Linear, &times, &values, 0.0, lerp), Some(2.0));
+        assert_eq!(sample(Interpolation::Linear, &times, &values, 5.0, lerp), Some(6.0));
+        assert_eq!(sample::<f32>(Interpolation::Linear, &[], &[], 0.0, lerp), None);
+    }
+
+    #[test]
+    fn cubic_uses_the_tangents() {
+        // in tangent, value, out tangent for each key
+        let times = [0.0, 2.0];
+        let flat = [0.0, 1.0, 0.0, 0.0, 3.0, 0.0];
+        assert_eq!(sample(Interpolation::CubicSpline, &times, &flat, 1.0, lerp), Some(2.0));
+        assert_eq!(sample(Interpolation::CubicSpline, &times, &flat, 2.0, lerp), Some(3.0));
+
+        // only the out tangent of the first key, scaled by the 2 second gap
+        let sloped = [0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+        let value = sample(Interpolation::CubicSpline, &times, &sloped, 1.0, lerp).unwrap();
+        assert!((value - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn clip_poses_only_its_nodes() {
+        let clip = AnimationClip {
+            channels: vec![
+                Channel {
+                    node: 1,
+                    interpolation: Interpolation::Linear,
+                    times: vec![0.0, 1.0],
+                    values: ChannelValues::Translation(vec![Vec3::ZERO, vec3(2.0, 0.0, 0.0)]),
+                },
+                Channel { node: 5, ..translation_clip("", 1.0).channels[0].clone() },
+            ],
+            ..Default::default()
+        };
+
+        let mut pose = vec![Transform::default(); 2];
+        clip.sample(0.5, &mut pose);
+
+        assert_eq!(pose[0], Transform::default());
+        assert_eq!(pose[1].position(), vec3(1.0, 0.0, 0.0));
+        assert_eq!(pose[1].scale(), Vec3::ONE);
+    }
+
+    #[test]
+    fn morph_weights_are_sampled_per_target() {
+        // two targets, the weights of both for each key
+        let mut channel = Channel {
+            node: 0,
+            interpolation: Interpolation::Linear,
+            times: vec![0.0, 1.0],
+            values: ChannelValues::Weights(vec![0.0, 1.0, 1.0, 0.0]),
+        };
+        let mut weights = Vec::new();
+        channel.apply_weights(0.25, &mut weights);
+        assert_eq!(weights, vec![0.25, 0.75]);
+
+        // each target's in tangents, values then out tangents
+        channel.interpolation = Interpolation::CubicSpline;
+        channel.values = ChannelValues::Weights(vec![0.0, 0.0, 1.0, 2.0, 0.0, 0.0, 0.0, 0.0, 3.0, 4.0, 0.0, 0.0]);
+        channel.apply_weights(0.5, &mut weights);
+        assert_eq!(weights, vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn cross_fade_moves_the_weight_over() {
+        let mut model = Model {
+            nodes: vec![ModelNode::default()],
+            animations: vec![translation_clip("a", 1.0), translation_clip("b", 3.0)],
+            ..Default::default()
+        };
+
+        model.play("a", true).unwrap();
+        model.update(0.0);
+        assert_eq!(model.nodes[0].transform.position().x, 1.0);
+
+        model.cross_fade("b", 1.0, true).unwrap();
+        model.update(0.5);
+        let weights: Vec<f32> = model.animator.states.iter().map(|s| s.weight).collect();
+        assert_eq!(weights, vec![0.5, 0.5]);
+        assert!((model.nodes[0].transform.position().x - 2.0).abs() < 1e-5);
+
+        model.update(0.5);
+        assert_eq!(model.animator.states.len(), 1);
+        assert_eq!(model.animator.states[0].clip, 1);
+        assert!((model.nodes[0].transform.position().x - 3.0).abs() < 1e-5);
+
+        model.stop();
+        assert_eq!(model.nodes[0].transform, Transform::default());
+    }
+
+    #[test]
+    fn missing_animation_is_an_error() {
+        let mut model = Model::default();
+        assert!(model.play("walk", true).is_err());
+        assert!(model.cross_fade("walk", 1.0, true).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use glam::{Mat4, Vec3};
+
+/// Axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// The smallest box around `points`, None when there are none
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+
+        Some(points.fold(Self::new(first, first), |b, p| Self::new(b.min.min(p), b.max.max(p))))
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vec3::new(a.x, a.y, a.z), Vec3::new(b.x, a.y, a.z),
+            Vec3::new(a.x, b.y, a.z), Vec3::new(b.x, b.y, a.z),
+            Vec3::new(a.x, a.y, b.z), Vec3::new(b.x, a.y, b.z),
+            Vec3::new(a.x, b.y, b.z), Vec3::new(b.x, b.y, b.z),
+        ]
+    }
+
+    /// How far along the ray it enters the box, 0 when it starts inside. `direction` doesn't need to be normalized
+    pub fn ray_intersection(&self, origin: Vec3, direction: Vec3) -> Option<f32> {
+        let inverse = direction.recip();
+        let (t0, t1) = ((self.min - origin) * inverse, (self.max - origin) * inverse);
+        let (near, far) = (t0.min(t1).max_element().max(0.0), t0.max(t1).min_element());
+
+        (near <= far).then_some(near)
+    }
+
+    /// The box around this one after `matrix`, which is usually a bit bigger than the transformed shape
+    pub fn transformed(&self, matrix: Mat4) -> Aabb {
+        Self::from_points(self.corners().map(|c| matrix.transform_point3(c))).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec3;
+
+    use super::*;
+
+    fn unit() -> Aabb {
+        Aabb::new(Vec3::ZERO, Vec3::ONE)
+    }
+
+    #[test]
+    fn ray_enters_where_it_hits() {
+        let t = unit().ray_intersection(vec3(0.5, 0.5, -2.0), Vec3::Z);
+        assert_eq!(t, Some(2.0));
+
+        // not normalized, so the distance is in multiples of the direction
+        let t = unit().ray_intersection(vec3(-1.0, 0.5, 0.5), vec3(4.0, 0.0, 0.0));
+        assert_eq!(t, Some(0.25));
+    }
+
+    #[test]
+    fn ray_from_inside_hits_at_zero() {
+        assert_eq!(unit().ray_intersection(Vec3::splat(0.5), -Vec3::Y), Some(0.0));
+    }
+
+    #[test]
+    fn ray_misses() {
+        // pointing away
+        assert_eq!(unit().ray_intersection(vec3(0.5, 0.5, -2.0), -Vec3::Z), None);
+        // parallel to the box, off to the side
+        assert_eq!(unit().ray_intersection(vec3(2.0, 0.5, -2.0), Vec3::Z), None);
+        // passing by a corner
+        assert_eq!(unit().ray_intersection(vec3(-1.0, 3.5, 0.5), vec3(1.0, -1.0, 0.0)), None);
+    }
+
+    #[test]
+    fn diagonal_ray_hits() {
+        let t = unit().ray_intersection(vec3(-1.0, -1.0, -1.0), Vec3::ONE).unwrap();
+        assert!((t - 1.0).abs() < 1e-6);
+    }
+}
@@ -1,6 +1,6 @@
 use glfw::{self, Action, Key};
 use crate::{cstr, graphics::shader::Shader};
-use crate::glam::{vec3, Vec3, Mat4};
+use crate::glam::{vec3, Vec2, Vec3, Mat4};
 use std::ffi::CString;
 
 const UP: Vec3 = Vec3::Y;
@@ -176,6 +176,26 @@ impl Camera {
         );
     }
 
+    /// Near and far planes pulled out of a `perspective_rh_gl` projection,
+    /// None when the camera is orthographic
+    pub fn clip_planes(&self) -> Option<(f32, f32)> {
+        if self.proj.w_axis.w != 0.0 {
+            return None;
+        }
+
+        let (a, b) = (self.proj.z_axis.z, self.proj.w_axis.z);
+        Some((b / (a - 1.0), b / (a + 1.0)))
+    }
+
+    /// The ray through `ndc` (-1 to 1, y up) as an origin on the near plane and a unit direction
+    pub fn ray_from_ndc(&self, ndc: Vec2) -> (Vec3, Vec3) {
+        let inverse = (self.proj * self.view).inverse();
+        let near = inverse.project_point3(ndc.extend(-1.0));
+        let far = inverse.project_point3(ndc.extend(1.0));
+
+        (near, (far - near).normalize_or_zero())
+    }
+
     pub fn set_projection(
         &mut self, 
         projection_type: ProjectionType,
@@ -0,0 +1,298 @@
+use std::{ffi::CString, rc::Rc};
+
+use gl::types::{GLint, GLsizei, GLuint, GLvoid};
+use glam::{Mat4, Vec3};
+use once_cell::sync::Lazy;
+
+use crate::{cstr, Camera, GpuHandle, GpuResourceKind, Renderer, Shader, CUBEMAP_CAPTURE_VS, EQUIRECT_TO_CUBEMAP_FS, SKYBOX_FS, SKYBOX_VS};
+
+pub const SKYBOX_UNIT: u32 = 13;
+
+pub static EQUIRECT_TO_CUBEMAP_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(CUBEMAP_CAPTURE_VS, EQUIRECT_TO_CUBEMAP_FS)
+});
+
+pub static SKYBOX_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(SKYBOX_VS, SKYBOX_FS)
+});
+
+/// A cube map texture, faces in the order GL expects them: +x, -x, +y, -y, +z, -z
+#[derive(Debug)]
+pub struct Cubemap {
+    texture: GpuHandle,
+    pub size: u32,
+}
+
+impl Cubemap {
+    /// Six square images of the same size (right, left, top, bottom, front, back)
+    pub fn from_faces(faces: [&str; 6]) -> Result<Self, String> {
+        let mut images = Vec::with_capacity(6);
+        for path in faces {
+            let img = image::open(path)
+                .map_err(|e| format!("Failed to load cubemap face '{}': {}", path, e))?
+                .to_rgba8();
+            images.push(img);
+        }
+
+        let size = images[0].width();
+        for (img, path) in images.iter().zip(faces) {
+            if img.width() != size || img.height() != size {
+                return Err(format!("Cubemap face '{}' should be {}x{}, got {}x{}", path, size, size, img.width(), img.height()));
+            }
+        }
+
+        unsafe {
+            let texture = GpuHandle::new(GpuResourceKind::Texture);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, texture.id());
+
+            for (face, img) in images.iter().enumerate() {
+                gl::TexImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                    0,
+                    gl::RGBA8 as GLint,
+                    size as GLsizei,
+                    size as GLsizei,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    img.as_raw().as_ptr() as *const GLvoid,
+                );
+            }
+
+            Self::set_parameters(false);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+
+            Ok(Self { texture, size })
+        }
+    }
+
+    /// Projects an equirectangular image (an `.hdr` or any other format) onto
+    /// a cubemap with `size` wide faces
+    pub fn from_equirectangular(path: &str, size: u32) -> Result<Self, String> {
+        let img = image::open(path)
+            .map_err(|e| format!("Failed to load environment '{}': {}", path, e))?
+            .flipv()
+            .to_rgb32f();
+
+        let (width, height) = img.dimensions();
+
+        unsafe {
+            let equirect = GpuHandle::new(GpuResourceKind::Texture);
+            gl::BindTexture(gl::TEXTURE_2D, equirect.id());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGB16F as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                gl::RGB,
+                gl::FLOAT,
+                img.as_raw().as_ptr() as *const GLvoid,
+            );
+
+            let texture = Self::alloc(size, false);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, equirect.id());
+            render_cubemap(&EQUIRECT_TO_CUBEMAP_SHADER, texture.id(), size, 0, |s| {
+                s.uniform_1i(cstr!("equirect"), 0);
+            });
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+            Ok(Self { texture, size })
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.texture.id()
+    }
+
+    /// Builds the mip chain and switches to trilinear filtering
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn generate_mipmaps(&self) {
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id());
+        gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+        gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as GLint);
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+    }
+
+    // an empty rgb16f cubemap, all faces `size` wide
+    pub(crate) unsafe fn alloc(size: u32, mipmapped: bool) -> GpuHandle {
+        let cubemap = GpuHandle::new(GpuResourceKind::Texture);
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, cubemap.id());
+
+        for face in 0..6 {
+            gl::TexImage2D(
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                0,
+                gl::RGB16F as GLint,
+                size as GLsizei,
+                size as GLsizei,
+                0,
+                gl::RGB,
+                gl::FLOAT,
+                std::ptr::null(),
+            );
+        }
+
+        Self::set_parameters(mipmapped);
+
+        cubemap
+    }
+
+    // clamped, linear filtered parameters for the bound cubemap
+    unsafe fn set_parameters(mipmapped: bool) {
+        let min_filter = if mipmapped { gl::LINEAR_MIPMAP_LINEAR } else { gl::LINEAR };
+        gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+        gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+        gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as GLint);
+        gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, min_filter as GLint);
+        gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+        gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+    }
+}
+
+/// A cubemap drawn behind everything else, the cubemap can be shared with an
+/// `Environment` so pbr materials reflect the same sky
+#[derive(Debug)]
+pub struct Skybox {
+    pub cubemap: Rc<Cubemap>,
+    pub intensity: f32,
+
+    VAO: GpuHandle,
+    // never bound again, only held so the vao's buffer lives as long as it does
+    #[allow(dead_code)]
+    VBO: GpuHandle,
+}
+
+impl Skybox {
+    pub fn new(cubemap: Rc<Cubemap>) -> Self {
+        let (vao, vbo) = unsafe { unit_cube() };
+
+        Self {
+            cubemap,
+            intensity: 1.0,
+            VAO: vao,
+            VBO: vbo,
+        }
+    }
+
+    // drawn at the far plane with depth writes off, so it only fills what geometry didn't cover
+    pub(crate) unsafe fn draw(&self, camera: &Camera) {
+        if camera.clip_planes().is_none() {
+            return;
+        }
+
+        let mut depth_func = 0;
+        gl::GetIntegerv(gl::DEPTH_FUNC, &mut depth_func);
+        let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+        gl::Enable(gl::DEPTH_TEST);
+        gl::DepthFunc(gl::LEQUAL);
+        gl::DepthMask(gl::FALSE);
+
+        // rotation only, the sky never gets closer
+        let view = Mat4::from_mat3(glam::Mat3::from_mat4(camera.view));
+
+        SKYBOX_SHADER.use_shader();
+        SKYBOX_SHADER.uniform_mat4fv(cstr!("view"), &view.to_cols_array());
+        SKYBOX_SHADER.uniform_mat4fv(cstr!("proj"), &camera.proj.to_cols_array());
+        SKYBOX_SHADER.uniform_1i(cstr!("skybox"), 0);
+        SKYBOX_SHADER.uniform_1f(cstr!("intensity"), self.intensity);
+
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.cubemap.id());
+
+        gl::BindVertexArray(self.VAO.id());
+        gl::DrawArrays(gl::TRIANGLES, 0, 36);
+        gl::BindVertexArray(0);
+
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        gl::UseProgram(0);
+
+        gl::DepthMask(gl::TRUE);
+        gl::DepthFunc(depth_func as u32);
+        if !depth_test { gl::Disable(gl::DEPTH_TEST) }
+    }
+}
+
+// draws a unit cube from the inside into each face of `cubemap` at `mip`,
+// whatever the shader samples has to be bound before calling this
+pub(crate) unsafe fn render_cubemap(shader: &Shader, cubemap: GLuint, size: u32, mip: GLint, setup: impl FnOnce(&Shader)) {
+    let mut last_fbo = 0;
+    let mut last_viewport = [0; 4];
+    gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut last_fbo);
+    gl::GetIntegerv(gl::VIEWPORT, last_viewport.as_mut_ptr());
+    let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+    gl::Disable(gl::DEPTH_TEST);
+
+    let fbo = GpuHandle::new(GpuResourceKind::Framebuffer);
+    gl::BindFramebuffer(gl::FRAMEBUFFER, fbo.id());
+    gl::Viewport(0, 0, size.max(1) as GLsizei, size.max(1) as GLsizei);
+
+    // the vbo has to outlive the draws, both go at the end of the scope
+    let (vao, _vbo) = unit_cube();
+
+    shader.use_shader();
+    setup(shader);
+    let proj = Mat4::perspective_rh_gl(90.0f32.to_radians(), 1.0, 0.1, 10.0);
+    shader.uniform_mat4fv(cstr!("proj"), &proj.to_cols_array());
+
+    for (face, view) in crate::cube_face_views(Vec3::ZERO).iter().enumerate() {
+        gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32, cubemap, mip);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+
+        shader.uniform_mat4fv(cstr!("view"), &view.to_cols_array());
+        gl::BindVertexArray(vao.id());
+        gl::DrawArrays(gl::TRIANGLES, 0, 36);
+    }
+
+    gl::BindVertexArray(0);
+    gl::UseProgram(0);
+
+    gl::BindFramebuffer(gl::FRAMEBUFFER, last_fbo as u32);
+    gl::Viewport(last_viewport[0], last_viewport[1], last_viewport[2], last_viewport[3]);
+    if depth_test { gl::Enable(gl::DEPTH_TEST) }
+}
+
+// 36 positions of a cube from -1 to 1, only attribute 0
+pub(crate) unsafe fn unit_cube() -> (GpuHandle, GpuHandle) {
+    let positions: [f32; 108] = [
+        -1.0,  1.0, -1.0,  -1.0, -1.0, -1.0,   1.0, -1.0, -1.0,   1.0, -1.0, -1.0,   1.0,  1.0, -1.0,  -1.0,  1.0, -1.0,
+        -1.0, -1.0,  1.0,  -1.0, -1.0, -1.0,  -1.0,  1.0, -1.0,  -1.0,  1.0, -1.0,  -1.0,  1.0,  1.0,  -1.0, -1.0,  1.0,
+         1.0, -1.0, -1.0,   1.0, -1.0,  1.0,   1.0,  1.0,  1.0,   1.0,  1.0,  1.0,   1.0,  1.0, -1.0,   1.0, -1.0, -1.0,
+        -1.0, -1.0,  1.0,  -1.0,  1.0,  1.0,   1.0,  1.0,  1.0,   1.0,  1.0,  1.0,   1.0, -1.0,  1.0,  -1.0, -1.0,  1.0,
+        -1.0,  1.0, -1.0,   1.0,  1.0, -1.0,   1.0,  1.0,  1.0,   1.0,  1.0,  1.0,  -1.0,  1.0,  1.0,  -1.0,  1.0, -1.0,
+        -1.0, -1.0, -1.0,  -1.0, -1.0,  1.0,   1.0, -1.0, -1.0,   1.0, -1.0, -1.0,  -1.0, -1.0,  1.0,   1.0, -1.0,  1.0,
+    ];
+
+    let (vao, vbo) = (GpuHandle::new(GpuResourceKind::VertexArray), GpuHandle::new(GpuResourceKind::Buffer));
+
+    gl::BindVertexArray(vao.id());
+    gl::BindBuffer(gl::ARRAY_BUFFER, vbo.id());
+    gl::BufferData(
+        gl::ARRAY_BUFFER,
+        std::mem::size_of_val(&positions) as isize,
+        positions.as_ptr() as *const GLvoid,
+        gl::STATIC_DRAW,
+    );
+    gl::EnableVertexAttribArray(0);
+    gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, 3 * std::mem::size_of::<f32>() as GLsizei, std::ptr::null());
+    gl::BindVertexArray(0);
+
+    (vao, vbo)
+}
+
+
+impl Renderer {
+    /// Draws `skybox` behind the scene, it's also what `Full` materials with a
+    /// `reflectivity` reflect
+    pub fn set_skybox(&mut self, skybox: Option<Skybox>) {
+        self.skybox = skybox;
+    }
+}
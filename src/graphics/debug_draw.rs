@@ -0,0 +1,375 @@
+use std::{cell::{Cell, OnceCell}, ffi::c_void, mem::{offset_of, size_of}, ptr};
+
+use gl::*;
+use glam::{vec2, vec3, Mat4, Vec2, Vec3, Vec4};
+use once_cell::sync::Lazy;
+
+use crate::{Aabb, AttributeType, Camera, GpuHandle, GpuResourceKind, Shader, VertexAttribute, VertexLayout, DEBUG_LINE_SHADER_FS, DEBUG_LINE_SHADER_VS};
+
+pub static DEBUG_LINE_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(DEBUG_LINE_SHADER_VS, DEBUG_LINE_SHADER_FS)
+});
+
+const CIRCLE_SEGMENTS: usize = 32;
+
+// corners of a box indexed by bits, x is bit 0, y bit 1 and z bit 2 (like `Aabb::corners`)
+const BOX_EDGES: [(usize, usize); 12] = [
+    (0, 1), (2, 3), (4, 5), (6, 7),
+    (0, 2), (1, 3), (4, 6), (5, 7),
+    (0, 4), (1, 5), (2, 6), (3, 7),
+];
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DebugVertex {
+    position: Vec3,
+    color: Vec4,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum DebugShape {
+    Line(Vec3, Vec3),
+    // turned into lines facing the camera when drawn
+    Text { position: Vec3, text: String, size: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DebugItem {
+    shape: DebugShape,
+    color: Vec4,
+    depth_test: bool,
+    // seconds left, it's drawn at least once
+    remaining: f32,
+}
+
+/// Lines for visualizing things like physics, raycasts and paths without making meshes.
+/// Everything added goes out in one batched line draw at the end of `Renderer::draw`,
+/// `Renderer::update` drops what has been drawn for long enough. `depth_test` and
+/// `duration` apply to whatever is added after they're set
+#[derive(Debug)]
+pub struct DebugDraw {
+    pub enabled: bool,
+    /// Whether the shapes get hidden behind the scene, or draw on top of it
+    pub depth_test: bool,
+    /// Seconds the shapes stay around, 0 for a single frame
+    pub duration: f32,
+
+    items: Vec<DebugItem>,
+    // how many of `items` the last draw got to, the rest were added after it
+    drawn: Cell<usize>,
+
+    // vao and vbo, made by the first draw so this can be created without a context
+    buffers: OnceCell<(GpuHandle, GpuHandle)>,
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            depth_test: true,
+            duration: 0.0,
+            items: Vec::new(),
+            drawn: Cell::new(0),
+            buffers: OnceCell::new(),
+        }
+    }
+
+    unsafe fn buffers(&self) -> &(GpuHandle, GpuHandle) {
+        self.buffers.get_or_init(|| {
+            let vao = GpuHandle::new(GpuResourceKind::VertexArray);
+            let vbo = GpuHandle::new(GpuResourceKind::Buffer);
+
+            BindVertexArray(vao.id());
+            BindBuffer(ARRAY_BUFFER, vbo.id());
+            VertexLayout::new()
+                .with_at(VertexAttribute::new("position", 0, 3, AttributeType::Float), offset_of!(DebugVertex, position))
+                .with_at(VertexAttribute::new("color", 1, 4, AttributeType::Float), offset_of!(DebugVertex, color))
+                .with_stride(size_of::<DebugVertex>())
+                .apply();
+            BindVertexArray(0);
+            BindBuffer(ARRAY_BUFFER, 0);
+
+            (vao, vbo)
+        })
+    }
+
+    pub fn set_depth_test(&mut self, depth_test: bool) {
+        self.depth_test = depth_test;
+    }
+
+    pub fn set_duration(&mut self, seconds: f32) {
+        self.duration = seconds;
+    }
+
+    fn push(&mut self, shape: DebugShape, color: Vec4) {
+        self.items.push(DebugItem { shape, color, depth_test: self.depth_test, remaining: self.duration });
+    }
+
+    pub fn line(&mut self, a: Vec3, b: Vec3, color: Vec4) {
+        self.push(DebugShape::Line(a, b), color);
+    }
+
+    /// From `origin` to `origin + direction`, with the length of `direction`
+    pub fn ray(&mut self, origin: Vec3, direction: Vec3, color: Vec4) {
+        self.line(origin, origin + direction, color);
+    }
+
+    /// Lines through `points` in order
+    pub fn path(&mut self, points: &[Vec3], color: Vec4) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], color);
+        }
+    }
+
+    pub fn aabb(&mut self, bounds: &Aabb, color: Vec4) {
+        let corners = bounds.corners();
+        for (a, b) in BOX_EDGES {
+            self.line(corners[a], corners[b], color);
+        }
+    }
+
+    pub fn circle(&mut self, center: Vec3, normal: Vec3, radius: f32, color: Vec4) {
+        let (u, v) = normal.normalize_or_zero().any_orthonormal_pair();
+        let point = |i: usize| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + (u * angle.cos() + v * angle.sin()) * radius
+        };
+
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), color);
+        }
+    }
+
+    /// A circle around each axis
+    pub fn sphere_wire(&mut self, center: Vec3, radius: f32, color: Vec4) {
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+            self.circle(center, axis, radius, color);
+        }
+    }
+
+    /// The x, y and z axes of `matrix` in red, green and blue, `size` long
+    pub fn axes(&mut self, matrix: Mat4, size: f32) {
+        let origin = matrix.transform_point3(Vec3::ZERO);
+        let colors = [Vec4::new(1.0, 0.0, 0.0, 1.0), Vec4::new(0.0, 1.0, 0.0, 1.0), Vec4::new(0.0, 0.0, 1.0, 1.0)];
+
+        for (axis, color) in [Vec3::X, Vec3::Y, Vec3::Z].into_iter().zip(colors) {
+            let direction = matrix.transform_vector3(axis).normalize_or_zero();
+            self.ray(origin, direction * size, color);
+        }
+    }
+
+    /// A grid on the xz plane around `center`, `size` wide with `divisions` cells per side
+    pub fn grid(&mut self, center: Vec3, size: f32, divisions: u32, color: Vec4) {
+        let divisions = divisions.max(1);
+        let half = size * 0.5;
+
+        for i in 0..=divisions {
+            let offset = i as f32 / divisions as f32 * size - half;
+            self.line(center + vec3(offset, 0.0, -half), center + vec3(offset, 0.0, half), color);
+            self.line(center + vec3(-half, 0.0, offset), center + vec3(half, 0.0, offset), color);
+        }
+    }
+
+    /// The volume `view_proj` sees, `camera.proj * camera.view` for a camera's
+    pub fn frustum(&mut self, view_proj: Mat4, color: Vec4) {
+        let inverse = view_proj.inverse();
+        let corners: [Vec3; 8] = std::array::from_fn(|i| {
+            let ndc = vec3(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { -1.0 } else { 1.0 },
+            );
+            inverse.project_point3(ndc)
+        });
+
+        for (a, b) in BOX_EDGES {
+            self.line(corners[a], corners[b], color);
+        }
+    }
+
+    /// `text` centered on `position` and facing the camera, `size` tall in world units.
+    /// Drawn with line segments, so only letters, digits and a few symbols show up
+    pub fn text_3d(&mut self, position: Vec3, text: &str, size: f32, color: Vec4) {
+        self.push(DebugShape::Text { position, text: text.to_owned(), size }, color);
+    }
+
+    /// Drops everything, including shapes that had time left
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.drawn.set(0);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Counts down the shapes that have been drawn and drops the ones whose time is up
+    pub fn update(&mut self, dt: f32) {
+        let drawn = self.drawn.replace(0);
+        let mut index = 0;
+
+        self.items.retain_mut(|item| {
+            let keep = index >= drawn || {
+                item.remaining -= dt;
+                item.remaining > 0.0
+            };
+            index += 1;
+            keep
+        });
+    }
+
+    // everything as seen by `camera`, depth tested lines first
+    pub(crate) unsafe fn draw(&self, camera: &Camera) {
+        self.drawn.set(self.items.len());
+        if !self.enabled || self.items.is_empty() {
+            return;
+        }
+
+        let inverse_view = camera.view.inverse();
+        let (right, up) = (inverse_view.x_axis.truncate(), inverse_view.y_axis.truncate());
+
+        let mut tested = Vec::new();
+        let mut on_top = Vec::new();
+        for item in &self.items {
+            let vertices = if item.depth_test { &mut tested } else { &mut on_top };
+            match &item.shape {
+                DebugShape::Line(a, b) => {
+                    vertices.push(DebugVertex { position: *a, color: item.color });
+                    vertices.push(DebugVertex { position: *b, color: item.color });
+                }
+                DebugShape::Text { position, text, size } => {
+                    for (a, b) in text_lines(text, *position, *size, right, up) {
+                        vertices.push(DebugVertex { position: a, color: item.color });
+                        vertices.push(DebugVertex { position: b, color: item.color });
+                    }
+                }
+            }
+        }
+
+        let count = tested.len();
+        tested.extend(on_top);
+        let size = (tested.len() * size_of::<DebugVertex>()) as isize;
+        let (vao, vbo) = self.buffers();
+
+        BindBuffer(ARRAY_BUFFER, vbo.id());
+        BufferData(ARRAY_BUFFER, size, ptr::null(), STREAM_DRAW);
+        BufferSubData(ARRAY_BUFFER, 0, size, tested.as_ptr() as *const c_void);
+        BindBuffer(ARRAY_BUFFER, 0);
+
+        DEBUG_LINE_SHADER.use_shader();
+        camera.send_uniforms(&DEBUG_LINE_SHADER);
+        BindVertexArray(vao.id());
+
+        let depth_test = IsEnabled(DEPTH_TEST) == TRUE;
+        Enable(DEPTH_TEST);
+        DrawArrays(LINES, 0, count as i32);
+        Disable(DEPTH_TEST);
+        DrawArrays(LINES, count as i32, (tested.len() - count) as i32);
+        if depth_test { Enable(DEPTH_TEST) }
+
+        BindVertexArray(0);
+        UseProgram(0);
+    }
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the segments glyphs are made of, on a cell 1 wide and 1 tall
+fn segment(name: char) -> Option<(Vec2, Vec2)> {
+    let (a, b) = match name {
+        'a' => ((0.0, 1.0), (0.5, 1.0)),
+        'b' => ((0.5, 1.0), (1.0, 1.0)),
+        'c' => ((1.0, 1.0), (1.0, 0.5)),
+        'd' => ((1.0, 0.5), (1.0, 0.0)),
+        'e' => ((1.0, 0.0), (0.5, 0.0)),
+        'f' => ((0.5, 0.0), (0.0, 0.0)),
+        'g' => ((0.0, 0.0), (0.0, 0.5)),
+        'h' => ((0.0, 0.5), (0.0, 1.0)),
+        'i' => ((0.5, 1.0), (0.5, 0.5)),
+        'k' => ((0.0, 0.5), (0.5, 0.5)),
+        'l' => ((0.5, 0.5), (0.5, 0.0)),
+        'm' => ((0.5, 0.5), (1.0, 0.5)),
+        'n' => ((0.0, 1.0), (0.5, 0.5)),
+        'o' => ((1.0, 1.0), (0.5, 0.5)),
+        'p' => ((0.0, 0.0), (0.5, 0.5)),
+        'q' => ((1.0, 0.0), (0.5, 0.5)),
+        'r' => ((0.0, 0.5), (0.5, 0.0)),
+        's' => ((1.0, 0.5), (0.5, 0.0)),
+        't' => ((0.5, 0.0), (0.5, 0.1)),
+        _ => return None,
+    };
+    Some((vec2(a.0, a.1), vec2(b.0, b.1)))
+}
+
+// segment names for each glyph, lowercase letters use the uppercase ones
+fn glyph(c: char) -> &'static str {
+    match c.to_ascii_uppercase() {
+        '0' => "abcdefghop",
+        '1' => "cd",
+        '2' => "abcmkgef",
+        '3' => "abcdefm",
+        '4' => "hkmcd",
+        '5' => "abhkmdef",
+        '6' => "abhgefdkm",
+        '7' => "abcd",
+        '8' => "abcdefghkm",
+        '9' => "abchkmdef",
+        'A' => "abcdghkm",
+        'B' => "abcdefilm",
+        'C' => "abghef",
+        'D' => "abcdefil",
+        'E' => "abghefk",
+        'F' => "abghk",
+        'G' => "abghefdm",
+        'H' => "ghcdkm",
+        'I' => "abefil",
+        'J' => "cdefg",
+        'K' => "ghkoq",
+        'L' => "ghef",
+        'M' => "ghcdno",
+        'N' => "ghcdnq",
+        'O' => "abcdefgh",
+        'P' => "abchgkm",
+        'Q' => "abcdefghq",
+        'R' => "abchgkmq",
+        'S' => "abhkmdef",
+        'T' => "abil",
+        'U' => "ghcdef",
+        'V' => "hcrs",
+        'W' => "ghcdpq",
+        'X' => "nopq",
+        'Y' => "nol",
+        'Z' => "abefop",
+        '-' => "km",
+        '+' => "kmil",
+        '=' => "kmef",
+        '_' => "ef",
+        '/' => "op",
+        '.' | ',' => "t",
+        _ => "",
+    }
+}
+
+// `text` as line segments, centered on `position` in the plane of `right` and `up`
+fn text_lines(text: &str, position: Vec3, size: f32, right: Vec3, up: Vec3) -> Vec<(Vec3, Vec3)> {
+    let (width, advance) = (size * 0.6, size * 0.8);
+    let count = text.chars().count() as f32;
+    let total = (count * advance - (advance - width)).max(0.0);
+    let origin = position - right * total * 0.5 - up * size * 0.5;
+
+    let mut lines = Vec::new();
+    for (i, c) in text.chars().enumerate() {
+        let corner = origin + right * i as f32 * advance;
+        let to_world = |p: Vec2| corner + right * p.x * width + up * p.y * size;
+
+        for (a, b) in glyph(c).chars().filter_map(segment) {
+            lines.push((to_world(a), to_world(b)));
+        }
+    }
+
+    lines
+}
@@ -0,0 +1,167 @@
+use std::{ffi::CString, rc::Rc};
+
+use gl::types::{GLint, GLsizei};
+use once_cell::sync::Lazy;
+
+use crate::{cstr, render_cubemap, Cubemap, GpuHandle, GpuResourceKind, Renderer, Shader, BRDF_LUT_FS, CUBEMAP_CAPTURE_VS, IRRADIANCE_FS, POST_PROCESS_VS, PREFILTER_FS};
+
+pub const IRRADIANCE_UNIT: u32 = 10;
+pub const PREFILTER_UNIT: u32 = 11;
+pub const BRDF_LUT_UNIT: u32 = 12;
+
+const ENVIRONMENT_SIZE: u32 = 512;
+const IRRADIANCE_SIZE: u32 = 32;
+const PREFILTER_SIZE: u32 = 128;
+const PREFILTER_MIPS: u32 = 5;
+const BRDF_LUT_SIZE: u32 = 512;
+
+pub static IRRADIANCE_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(CUBEMAP_CAPTURE_VS, IRRADIANCE_FS)
+});
+
+pub static PREFILTER_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(CUBEMAP_CAPTURE_VS, PREFILTER_FS)
+});
+
+pub static BRDF_LUT_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(POST_PROCESS_VS, BRDF_LUT_FS)
+});
+
+/// Image based lighting for the pbr shader, everything is precomputed on creation:
+/// the environment as a cubemap, its diffuse irradiance, a prefiltered specular
+/// cubemap with one roughness level per mip and the split sum brdf lookup
+#[derive(Debug)]
+pub struct Environment {
+    pub cubemap: Rc<Cubemap>,
+    pub irradiance: GpuHandle,
+    pub prefilter: GpuHandle,
+    pub brdf_lut: GpuHandle,
+
+    pub intensity: f32,
+}
+
+impl Environment {
+    /// Loads an equirectangular (usually `.hdr`) image
+    pub fn from_hdr(path: &str) -> Result<Self, String> {
+        let cubemap = Cubemap::from_equirectangular(path, ENVIRONMENT_SIZE)?;
+        Ok(Self::from_cubemap(Rc::new(cubemap)))
+    }
+
+    /// Convolves `cubemap` into the ibl maps, share it with a `Skybox` so
+    /// reflections match the sky
+    pub fn from_cubemap(cubemap: Rc<Cubemap>) -> Self {
+        unsafe {
+            cubemap.generate_mipmaps();
+
+            let irradiance = Cubemap::alloc(IRRADIANCE_SIZE, false);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, cubemap.id());
+            render_cubemap(&IRRADIANCE_SHADER, irradiance.id(), IRRADIANCE_SIZE, 0, |s| {
+                s.uniform_1i(cstr!("environment"), 0);
+            });
+
+            let prefilter = Cubemap::alloc(PREFILTER_SIZE, true);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, prefilter.id());
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAX_LEVEL, (PREFILTER_MIPS - 1) as GLint);
+            gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, cubemap.id());
+            for mip in 0..PREFILTER_MIPS {
+                let roughness = mip as f32 / (PREFILTER_MIPS - 1) as f32;
+                render_cubemap(&PREFILTER_SHADER, prefilter.id(), PREFILTER_SIZE >> mip, mip as GLint, |s| {
+                    s.uniform_1i(cstr!("environment"), 0);
+                    s.uniform_1f(cstr!("roughness"), roughness);
+                    s.uniform_1f(cstr!("resolution"), cubemap.size as f32);
+                });
+            }
+
+            let brdf_lut = brdf_lut();
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+
+            Self {
+                cubemap,
+                irradiance,
+                prefilter,
+                brdf_lut,
+                intensity: 1.0,
+            }
+        }
+    }
+
+    pub(crate) unsafe fn send_uniforms(shader: &Shader, environment: Option<&Environment>) {
+        shader.use_shader();
+        shader.uniform_1i(cstr!("irradianceMap"), IRRADIANCE_UNIT as i32);
+        shader.uniform_1i(cstr!("prefilterMap"), PREFILTER_UNIT as i32);
+        shader.uniform_1i(cstr!("brdfLut"), BRDF_LUT_UNIT as i32);
+
+        let Some(env) = environment else {
+            shader.uniform_1i(cstr!("has_environment"), 0);
+            return;
+        };
+
+        shader.uniform_1i(cstr!("has_environment"), 1);
+        shader.uniform_1f(cstr!("environment_intensity"), env.intensity);
+        shader.uniform_1f(cstr!("prefilter_mips"), (PREFILTER_MIPS - 1) as f32);
+
+        gl::ActiveTexture(gl::TEXTURE0 + IRRADIANCE_UNIT);
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, env.irradiance.id());
+        gl::ActiveTexture(gl::TEXTURE0 + PREFILTER_UNIT);
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, env.prefilter.id());
+        gl::ActiveTexture(gl::TEXTURE0 + BRDF_LUT_UNIT);
+        gl::BindTexture(gl::TEXTURE_2D, env.brdf_lut.id());
+        gl::ActiveTexture(gl::TEXTURE0);
+    }
+}
+
+unsafe fn brdf_lut() -> GpuHandle {
+    let lut = GpuHandle::new(GpuResourceKind::Texture);
+    gl::BindTexture(gl::TEXTURE_2D, lut.id());
+    gl::TexImage2D(
+        gl::TEXTURE_2D,
+        0,
+        gl::RG16F as GLint,
+        BRDF_LUT_SIZE as GLsizei,
+        BRDF_LUT_SIZE as GLsizei,
+        0,
+        gl::RG,
+        gl::FLOAT,
+        std::ptr::null(),
+    );
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+
+    let mut last_fbo = 0;
+    let mut last_viewport = [0; 4];
+    gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut last_fbo);
+    gl::GetIntegerv(gl::VIEWPORT, last_viewport.as_mut_ptr());
+    let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+    gl::Disable(gl::DEPTH_TEST);
+
+    let fbo = GpuHandle::new(GpuResourceKind::Framebuffer);
+    let vao = GpuHandle::new(GpuResourceKind::VertexArray);
+
+    gl::BindFramebuffer(gl::FRAMEBUFFER, fbo.id());
+    gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, lut.id(), 0);
+    gl::Viewport(0, 0, BRDF_LUT_SIZE as GLsizei, BRDF_LUT_SIZE as GLsizei);
+
+    BRDF_LUT_SHADER.use_shader();
+    gl::BindVertexArray(vao.id());
+    gl::DrawArrays(gl::TRIANGLES, 0, 3);
+    gl::BindVertexArray(0);
+    gl::UseProgram(0);
+
+    gl::BindFramebuffer(gl::FRAMEBUFFER, last_fbo as u32);
+    gl::Viewport(last_viewport[0], last_viewport[1], last_viewport[2], last_viewport[3]);
+    if depth_test { gl::Enable(gl::DEPTH_TEST) }
+
+    lut
+}
+
+impl Renderer {
+    /// Lights pbr materials with `environment`, None goes back to a flat ambient term
+    pub fn set_environment(&mut self, environment: Option<Environment>) {
+        self.environment = environment;
+    }
+}
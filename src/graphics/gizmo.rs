@@ -0,0 +1,320 @@
+use glam::{vec2, Mat4, Quat, Vec3, Vec4};
+
+use crate::{Aabb, Camera, EventLoop, NodeTarget, Renderer, Transform};
+
+// scaling stops here instead of reaching 0 or flipping, which would make the matrices singular
+const MIN_SCALE: f32 = 1e-3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+/// What the gizmo and the inspector edit, the same mesh or model names scene nodes use
+pub type Selection = NodeTarget;
+
+#[derive(Debug, Clone, PartialEq)]
+struct GizmoDrag {
+    axis: usize,
+    // the selection's local transform and what it's relative to, when the drag started
+    start: Transform,
+    parent: Mat4,
+    center: Vec3,
+    direction: Vec3,
+    // where the handle was grabbed, on the axis or on the ring's plane
+    grab: Vec3,
+}
+
+/// Handles for moving, rotating and scaling the selected mesh or model with the mouse.
+/// They're drawn with `Renderer::debug` on top of the scene, translate and rotate along
+/// the world axes and scale along the selection's own
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gizmo {
+    pub enabled: bool,
+    pub mode: GizmoMode,
+    /// Handle length as a fraction of the distance to the camera, keeping the size on screen the same
+    pub size: f32,
+    /// Clicking away from the handles selects whatever is under the cursor
+    pub click_select: bool,
+
+    hovered: Option<usize>,
+    drag: Option<GizmoDrag>,
+    was_down: bool,
+}
+
+impl Gizmo {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            mode: GizmoMode::Translate,
+            size: 0.15,
+            click_select: true,
+            hovered: None,
+            drag: None,
+            was_down: false,
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: GizmoMode) {
+        self.mode = mode;
+        self.drag = None;
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    fn handle_length(&self, camera: &Camera, center: Vec3) -> f32 {
+        if camera.clip_planes().is_none() {
+            // orthographic, a fraction of the view's width
+            return self.size * 2.0 / camera.proj.x_axis.x;
+        }
+
+        let eye = camera.view.inverse().w_axis.truncate();
+        eye.distance(center) * self.size
+    }
+
+    fn axes(&self, world: Mat4) -> [Vec3; 3] {
+        match self.mode {
+            GizmoMode::Scale => [world.x_axis, world.y_axis, world.z_axis].map(|a| a.truncate().normalize_or_zero()),
+            _ => [Vec3::X, Vec3::Y, Vec3::Z],
+        }
+    }
+
+    // where the ray meets the handle being dragged: the closest point on the axis, or
+    // the hit on the ring's plane
+    fn drag_point(&self, origin: Vec3, ray: Vec3, center: Vec3, axis: Vec3) -> Option<Vec3> {
+        match self.mode {
+            GizmoMode::Rotate => {
+                let t = ray_plane(origin, ray, center, axis)?;
+                Some(origin + ray * t)
+            }
+            _ => Some(center + axis * closest_on_axis(origin, ray, center, axis)?),
+        }
+    }
+
+    // the closest handle under the ray
+    fn hit(&self, origin: Vec3, ray: Vec3, center: Vec3, axes: [Vec3; 3], length: f32) -> Option<usize> {
+        let threshold = length * 0.08;
+
+        let errors = axes.map(|axis| match self.mode {
+            GizmoMode::Rotate => ray_plane(origin, ray, center, axis)
+                .map(|t| ((origin + ray * t).distance(center) - length).abs()),
+            _ => closest_on_axis(origin, ray, center, axis).map(|s| {
+                let point = center + axis * s.clamp(0.0, length);
+                let to_point = point - origin;
+                (to_point - ray * to_point.dot(ray)).length()
+            }),
+        });
+
+        (0..3)
+            .filter_map(|i| Some((i, errors[i]?)))
+            .filter(|&(_, error)| error < threshold)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    // the selection's local transform with the drag up to `point` applied
+    fn dragged(&self, drag: &GizmoDrag, point: Vec3) -> Transform {
+        let mut transform = drag.start.clone();
+
+        match self.mode {
+            GizmoMode::Translate => {
+                let offset = drag.parent.inverse().transform_vector3(point - drag.grab);
+                transform.set_position(drag.start.position() + offset);
+            }
+            GizmoMode::Rotate => {
+                let (from, to) = ((drag.grab - drag.center).normalize_or_zero(), (point - drag.center).normalize_or_zero());
+                let angle = drag.direction.dot(from.cross(to)).atan2(from.dot(to));
+
+                // the world rotation brought into the parent's space
+                let (_, parent_rotation, _) = drag.parent.to_scale_rotation_translation();
+                let rotation = parent_rotation.inverse() * Quat::from_axis_angle(drag.direction, angle) * parent_rotation;
+                transform.set_rotation((rotation * drag.start.rotation()).normalize());
+            }
+            GizmoMode::Scale => {
+                let from = (drag.grab - drag.center).dot(drag.direction);
+                let to = (point - drag.center).dot(drag.direction);
+                if from.abs() > f32::EPSILON {
+                    let mut scale = drag.start.scale();
+                    let scaled = scale[drag.axis] * (to / from).max(0.0);
+                    scale[drag.axis] = scaled.abs().max(MIN_SCALE).copysign(scale[drag.axis]);
+                    transform.set_scale(scale);
+                }
+            }
+        }
+
+        transform
+    }
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// distance along the axis to its closest point to the ray, None when they're parallel
+fn closest_on_axis(origin: Vec3, ray: Vec3, center: Vec3, axis: Vec3) -> Option<f32> {
+    let w = center - origin;
+    let (b, d, e) = (axis.dot(ray), axis.dot(w), ray.dot(w));
+    let denom = 1.0 - b * b;
+
+    (denom > 1e-6).then(|| (b * e - d) / denom)
+}
+
+// how far along the ray it hits the plane through `center`, None when it misses
+fn ray_plane(origin: Vec3, ray: Vec3, center: Vec3, normal: Vec3) -> Option<f32> {
+    let denom = ray.dot(normal);
+    if denom.abs() < 1e-6 {
+        return None;
+    }
+
+    let t = (center - origin).dot(normal) / denom;
+    (t >= 0.0).then_some(t)
+}
+
+impl Renderer {
+    pub fn select(&mut self, selection: Option<Selection>) {
+        self.selection = selection;
+        self.gizmo.drag = None;
+    }
+
+    /// The closest mesh or model whose bounds the ray goes through
+    pub fn pick(&self, origin: Vec3, direction: Vec3) -> Option<Selection> {
+        let worlds = self.world_matrices();
+
+        let meshes = self.meshes.iter().filter_map(|(name, mesh)| {
+            let bounds = mesh.bounds()?.transformed(worlds.meshes[name.as_str()]);
+            Some((bounds, Selection::Mesh(name.clone())))
+        });
+        let models = self.models.iter().filter_map(|(name, model)| {
+            let bounds = model.local_bounds()?.transformed(worlds.models[name.as_str()]);
+            Some((bounds, Selection::Model(name.clone())))
+        });
+
+        meshes.chain(models)
+            .filter_map(|(bounds, selection): (Aabb, Selection)| Some((bounds.ray_intersection(origin, direction)?, selection)))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, selection)| selection)
+    }
+
+    // the selection's local transform and the world matrix of what it's relative to
+    fn selected_frame(&self) -> Option<(Transform, Mat4)> {
+        let selection = self.selection.as_ref()?;
+        let transform = match selection {
+            Selection::Mesh(name) => self.meshes.get(name)?.transform.clone(),
+            Selection::Model(name) => self.models.get(name)?.transform.clone(),
+        };
+        Some((transform, self.parent_world_matrix(selection)))
+    }
+
+    fn set_selected_transform(&mut self, transform: Transform) {
+        let Some(selection) = self.selection.clone() else {
+            return;
+        };
+        if let Some(selected) = self.target_transform_mut(&selection) {
+            *selected = transform;
+        }
+    }
+
+    // picks and drags with the left mouse button using `camera`, then draws the handles.
+    // clicks imgui wants are left alone
+    pub(crate) fn update_gizmo(&mut self, el: &EventLoop) {
+        let down = el.event_handler.lmb;
+        let pressed = down && !self.gizmo.was_down;
+        self.gizmo.was_down = down;
+        if !down {
+            self.gizmo.drag = None;
+        }
+        if !self.gizmo.enabled {
+            return;
+        }
+
+        let (x, y) = el.window.get_cursor_pos();
+        let (w, h) = el.window.get_size();
+        let ndc = vec2(x as f32 / w.max(1) as f32 * 2.0 - 1.0, 1.0 - y as f32 / h.max(1) as f32 * 2.0);
+        let (origin, ray) = self.camera.ray_from_ndc(ndc);
+        let ui_has_mouse = el.ui.ctx.io().want_capture_mouse;
+
+        let Some((transform, parent)) = self.selected_frame() else {
+            self.gizmo.hovered = None;
+            if pressed && !ui_has_mouse && self.gizmo.click_select {
+                self.select(self.pick(origin, ray));
+            }
+            return;
+        };
+
+        let world = parent * transform.matrix();
+        let center = world.transform_point3(Vec3::ZERO);
+        let length = self.gizmo.handle_length(&self.camera, center);
+        let axes = self.gizmo.axes(world);
+
+        if let Some(drag) = self.gizmo.drag.clone() {
+            if let Some(point) = self.gizmo.drag_point(origin, ray, drag.center, drag.direction) {
+                let dragged = self.gizmo.dragged(&drag, point);
+                self.set_selected_transform(dragged);
+            }
+        } else if !ui_has_mouse {
+            self.gizmo.hovered = self.gizmo.hit(origin, ray, center, axes, length);
+
+            if pressed {
+                match self.gizmo.hovered {
+                    Some(axis) => {
+                        self.gizmo.drag = self.gizmo.drag_point(origin, ray, center, axes[axis]).map(|grab| GizmoDrag {
+                            axis,
+                            start: transform,
+                            parent,
+                            center,
+                            direction: axes[axis],
+                            grab,
+                        });
+                    }
+                    None if self.gizmo.click_select => self.select(self.pick(origin, ray)),
+                    None => (),
+                }
+            }
+        }
+
+        self.draw_gizmo(center, axes, length);
+    }
+
+    fn draw_gizmo(&mut self, center: Vec3, axes: [Vec3; 3], length: f32) {
+        let active = self.gizmo.drag.as_ref().map(|d| d.axis).or(self.gizmo.hovered);
+        let colors = [Vec4::new(0.9, 0.2, 0.2, 1.0), Vec4::new(0.2, 0.9, 0.2, 1.0), Vec4::new(0.2, 0.3, 0.9, 1.0)];
+        let mode = self.gizmo.mode;
+
+        let debug = &mut self.debug;
+        let (depth_test, duration) = (debug.depth_test, debug.duration);
+        debug.set_depth_test(false);
+        debug.set_duration(0.0);
+
+        for (i, &axis) in axes.iter().enumerate() {
+            let color = if active == Some(i) { Vec4::new(1.0, 0.9, 0.1, 1.0) } else { colors[i] };
+            let tip = center + axis * length;
+
+            match mode {
+                GizmoMode::Translate => {
+                    debug.line(center, tip, color);
+                    let (u, v) = axis.any_orthonormal_pair();
+                    let back = tip - axis * length * 0.15;
+                    for side in [u, -u, v, -v] {
+                        debug.line(tip, back + side * length * 0.05, color);
+                    }
+                }
+                GizmoMode::Rotate => debug.circle(center, axis, length, color),
+                GizmoMode::Scale => {
+                    debug.line(center, tip, color);
+                    let half = Vec3::splat(length * 0.04);
+                    debug.aabb(&Aabb::new(tip - half, tip + half), color);
+                }
+            }
+        }
+
+        debug.set_depth_test(depth_test);
+        debug.set_duration(duration);
+    }
+}
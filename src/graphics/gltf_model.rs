@@ -0,0 +1,322 @@
+use std::{collections::{hash_map::Entry, HashMap}, path::Path};
+
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use gltf::{animation::util::ReadOutputs, image::Format, mesh::Mode};
+
+use crate::{create_texture, skinned_shader, AnimationClip, Channel, ChannelValues, Error, GpuHandle, Interpolation, Material, Mesh, Model, ModelNode, MorphTarget, PrimitiveMode, ShaderType, SharedMaterial, Skin, SkinVertex, Texture, TextureSlot, Transform, Vertex, MAX_JOINTS};
+
+impl Model {
+    /// Loads a `.gltf` (with its external buffers and images) or `.glb` file.
+    /// Every primitive becomes a mesh with a pbr material, the default scene's
+    /// node tree ends up in `nodes`, along with its skins, morph targets and animations. Missing
+    /// normals and uvs are generated
+    pub fn load_gltf(&mut self, path: &str) -> Result<(), Error> {
+        let path = Path::new(path);
+        let (document, buffers, images) = gltf::import(path).map_err(|e| Error::from_gltf(path, e))?;
+
+        let textures: Vec<GpuHandle> = images.iter().map(|image| unsafe {
+            create_texture(image.width, image.height, &to_rgba8(image))
+        }).collect();
+
+        // by material index and whether it's for skinned meshes
+        let mut materials: HashMap<(usize, bool), SharedMaterial> = HashMap::new();
+        // meshes made for each glTF mesh, nodes using the same one share its buffers.
+        // skinned and unskinned uses of a mesh get different copies
+        let mut loaded: HashMap<(usize, bool), Vec<Mesh>> = HashMap::new();
+        // glTF node index to index in `self.nodes`
+        let mut node_map: HashMap<usize, usize> = HashMap::new();
+        let skin_base = self.skins.len();
+
+        let Some(scene) = document.default_scene().or_else(|| document.scenes().next()) else {
+            return Ok(());
+        };
+
+        let mut stack: Vec<(gltf::Node, Option<usize>)> = scene.nodes().map(|n| (n, None)).collect();
+        stack.reverse();
+        while let Some((node, parent)) = stack.pop() {
+            let (translation, rotation, scale) = node.transform().decomposed();
+            let index = self.nodes.len();
+
+            let mut model_node = ModelNode {
+                name: node.name().map_or_else(|| format!("node{}", node.index()), str::to_owned),
+                transform: Transform::new(Vec3::from(translation), Quat::from_array(rotation), Vec3::from(scale)),
+                parent,
+                children: Vec::new(),
+                meshes: Vec::new(),
+                weights: node.weights().or_else(|| node.mesh().and_then(|m| m.weights()))
+                    .map_or_else(Vec::new, <[f32]>::to_vec),
+            };
+
+            if let Some(mesh) = node.mesh() {
+                let key = (mesh.index(), node.skin().is_some());
+                if let Entry::Vacant(entry) = loaded.entry(key) {
+                    entry.insert(load_mesh(&mesh, key.1, &buffers, &textures, &mut materials)
+                        .ok_or_else(|| Error::MissingAttribute { path: path.to_owned(), attribute: "positions" })?);
+                }
+
+                for mesh in &loaded[&key] {
+                    let mut mesh = mesh.clone();
+                    if !mesh.skin_vertices.is_empty() {
+                        mesh.skin = node.skin().map(|s| skin_base + s.index());
+                    }
+
+                    model_node.meshes.push(self.meshes.len());
+                    self.meshes.push(mesh);
+                }
+            }
+            node_map.insert(node.index(), index);
+
+            if let Some(parent) = parent {
+                self.nodes[parent].children.push(index);
+            }
+            self.nodes.push(model_node);
+
+            stack.extend(node.children().collect::<Vec<_>>().into_iter().rev().map(|c| (c, Some(index))));
+        }
+
+        for skin in document.skins() {
+            self.skins.push(load_skin(path, &skin, &buffers, &node_map)?);
+        }
+        for animation in document.animations() {
+            self.animations.push(load_animation(&animation, &buffers, &node_map));
+        }
+
+        self.loaded_textures.extend(textures.into_iter().map(Texture::Loaded));
+
+        Ok(())
+    }
+}
+
+fn load_skin(path: &Path, skin: &gltf::Skin, buffers: &[gltf::buffer::Data], node_map: &HashMap<usize, usize>) -> Result<Skin, Error> {
+    let joints: Vec<usize> = skin.joints()
+        .map(|j| node_map.get(&j.index()).copied())
+        .collect::<Option<_>>()
+        .ok_or_else(|| Error::Parse { path: path.to_owned(), reason: "a skin has joints outside the scene".to_owned() })?;
+
+    if joints.len() > MAX_JOINTS {
+        return Err(Error::UnsupportedFormat {
+            path: path.to_owned(),
+            format: format!("skins with more than {} joints", MAX_JOINTS),
+        });
+    }
+
+    // missing inverse bind matrices are identities
+    let inverse_bind_matrices = skin.reader(|b| Some(&buffers[b.index()]))
+        .read_inverse_bind_matrices()
+        .map_or_else(|| vec![Mat4::IDENTITY; joints.len()], |m| m.map(|m| Mat4::from_cols_array_2d(&m)).collect());
+
+    Ok(Skin {
+        name: skin.name().map_or_else(|| format!("skin{}", skin.index()), str::to_owned),
+        joints,
+        inverse_bind_matrices,
+    })
+}
+
+// channels aimed at nodes that weren't loaded are left out
+fn load_animation(animation: &gltf::Animation, buffers: &[gltf::buffer::Data], node_map: &HashMap<usize, usize>) -> AnimationClip {
+    let mut clip = AnimationClip {
+        name: animation.name().map_or_else(|| format!("animation{}", animation.index()), str::to_owned),
+        duration: 0.0,
+        channels: Vec::new(),
+    };
+
+    for channel in animation.channels() {
+        let Some(&node) = node_map.get(&channel.target().node().index()) else {
+            continue;
+        };
+
+        let reader = channel.reader(|b| Some(&buffers[b.index()]));
+        let (Some(inputs), Some(outputs)) = (reader.read_inputs(), reader.read_outputs()) else {
+            continue;
+        };
+
+        let values = match outputs {
+            ReadOutputs::Translations(t) => ChannelValues::Translation(t.map(Vec3::from).collect()),
+            ReadOutputs::Rotations(r) => ChannelValues::Rotation(r.into_f32().map(Quat::from_array).collect()),
+            ReadOutputs::Scales(s) => ChannelValues::Scale(s.map(Vec3::from).collect()),
+            ReadOutputs::MorphTargetWeights(w) => ChannelValues::Weights(w.into_f32().collect()),
+        };
+        let interpolation = match channel.sampler().interpolation() {
+            gltf::animation::Interpolation::Linear => Interpolation::Linear,
+            gltf::animation::Interpolation::Step => Interpolation::Step,
+            gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+        };
+
+        let times: Vec<f32> = inputs.collect();
+        clip.duration = clip.duration.max(times.last().copied().unwrap_or(0.0));
+        clip.channels.push(Channel { node, interpolation, times, values });
+    }
+
+    clip
+}
+
+// `skinned` meshes get their joints and weights read, when they have them
+fn load_mesh(
+    mesh: &gltf::Mesh,
+    skinned: bool,
+    buffers: &[gltf::buffer::Data],
+    textures: &[GpuHandle],
+    materials: &mut HashMap<(usize, bool), SharedMaterial>,
+) -> Option<Vec<Mesh>> {
+    let mut meshes = Vec::new();
+
+    // the default morph weights, nodes can override them
+    let mesh_weights = mesh.weights();
+
+    for primitive in mesh.primitives() {
+        let reader = primitive.reader(|b| Some(&buffers[b.index()]));
+        let positions: Vec<Vec3> = reader.read_positions()?.map(Vec3::from).collect();
+
+        let normals: Vec<Vec3> = reader.read_normals()
+            .map_or_else(Vec::new, |n| n.map(Vec3::from).collect());
+        let tex_coords: Vec<Vec2> = reader.read_tex_coords(0)
+            .map_or_else(Vec::new, |t| t.into_f32().map(Vec2::from).collect());
+        let colors: Vec<Vec4> = reader.read_colors(0)
+            .map_or_else(Vec::new, |c| c.into_rgba_f32().map(Vec4::from).collect());
+        let tangents: Vec<Vec4> = reader.read_tangents()
+            .map_or_else(Vec::new, |t| t.map(Vec4::from).collect());
+
+        let vertices: Vec<Vertex> = positions.iter().enumerate().map(|(i, &position)| {
+            let mut vertex = Vertex::new(
+                position,
+                colors.get(i).copied().unwrap_or(Vec4::ONE),
+                tex_coords.get(i).copied().unwrap_or(Vec2::ZERO),
+                normals.get(i).copied().unwrap_or(Vec3::ZERO),
+            );
+            vertex.tangent = tangents.get(i).copied().unwrap_or(Vec4::ZERO);
+            vertex
+        }).collect();
+
+        let indices: Vec<u32> = reader.read_indices()
+            .map_or_else(|| (0..vertices.len() as u32).collect(), |i| i.into_u32().collect());
+
+        let skin_vertices: Vec<SkinVertex> = match (skinned, reader.read_joints(0), reader.read_weights(0)) {
+            (true, Some(joints), Some(weights)) => joints.into_u16().zip(weights.into_f32()).map(|(j, w)| {
+                let weights = Vec4::from(w);
+                let total = weights.element_sum();
+                SkinVertex {
+                    joints: j.map(u32::from),
+                    weights: if total > 0.0 { weights / total } else { Vec4::X },
+                }
+            }).collect(),
+            _ => Vec::new(),
+        };
+        let skinned = skin_vertices.len() == vertices.len();
+
+        let material = match primitive.material().index() {
+            Some(index) => materials
+                .entry((index, skinned))
+                .or_insert_with(|| {
+                    let mut material = load_material(&primitive.material(), textures);
+                    if skinned {
+                        material.shader = skinned_shader(material.shader);
+                    }
+                    material.shared()
+                })
+                .clone(),
+            None => {
+                let mut material = Material::from_shader_type(&ShaderType::Pbr);
+                if skinned {
+                    material.shader = skinned_shader(material.shader);
+                }
+                material.shared()
+            }
+        };
+
+        let mut mesh = Mesh::new(&vertices, &indices);
+        mesh.set_primitive_mode(primitive_mode(primitive.mode()));
+        if skinned {
+            mesh.skin_vertices = skin_vertices;
+        }
+        for (i, (positions, normals, _)) in reader.read_morph_targets().enumerate() {
+            mesh.add_morph_target(MorphTarget::new(
+                &format!("target{}", i),
+                positions.map_or_else(Vec::new, |p| p.map(Vec3::from).collect()),
+                normals.map_or_else(Vec::new, |n| n.map(Vec3::from).collect()),
+            ));
+        }
+        if let Some(weights) = mesh_weights {
+            for (i, &weight) in weights.iter().enumerate() {
+                mesh.set_morph_weight(i, weight);
+            }
+        }
+        if normals.len() < positions.len() {
+            mesh.compute_normals();
+        }
+        if tex_coords.len() < positions.len() {
+            mesh.generate_uvs();
+        }
+        mesh.set_material(material);
+        mesh.setup_mesh();
+        meshes.push(mesh);
+    }
+
+    Some(meshes)
+}
+
+fn primitive_mode(mode: Mode) -> PrimitiveMode {
+    match mode {
+        Mode::Points => PrimitiveMode::Points,
+        Mode::Lines => PrimitiveMode::Lines,
+        Mode::LineLoop => PrimitiveMode::LineLoop,
+        Mode::LineStrip => PrimitiveMode::LineStrip,
+        Mode::Triangles => PrimitiveMode::Triangles,
+        Mode::TriangleStrip => PrimitiveMode::TriangleStrip,
+        Mode::TriangleFan => PrimitiveMode::TriangleFan,
+    }
+}
+
+fn load_material(material: &gltf::Material, textures: &[GpuHandle]) -> Material {
+    let pbr = material.pbr_metallic_roughness();
+    let mut out = Material::from_shader_type(&ShaderType::Pbr);
+
+    out.set_uniform("base_color", Vec4::from(pbr.base_color_factor()));
+    out.set_uniform("metallic", pbr.metallic_factor());
+    out.set_uniform("roughness", pbr.roughness_factor());
+    out.set_uniform("emissive_factor", Vec3::from(material.emissive_factor()));
+
+    let texture = |t: gltf::Texture| textures[t.source().index()].clone();
+
+    if let Some(info) = pbr.base_color_texture() {
+        out.set_texture(TextureSlot::Albedo, texture(info.texture()));
+    }
+    if let Some(info) = pbr.metallic_roughness_texture() {
+        out.set_texture(TextureSlot::Specular, texture(info.texture()));
+    }
+    if let Some(info) = material.normal_texture() {
+        out.set_texture(TextureSlot::Normal, texture(info.texture()));
+    }
+    if let Some(info) = material.emissive_texture() {
+        out.set_texture(TextureSlot::Emissive, texture(info.texture()));
+    }
+    if let Some(info) = material.occlusion_texture() {
+        out.set_uniform("occlusion_strength", info.strength());
+        out.set_texture(TextureSlot::Occlusion, texture(info.texture()));
+    }
+
+    out
+}
+
+// glTF images come decoded in whatever layout the file had
+fn to_rgba8(image: &gltf::image::Data) -> Vec<u8> {
+    let pixels = &image.pixels;
+    match image.format {
+        Format::R8G8B8A8 => pixels.clone(),
+        Format::R8G8B8 => pixels.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        Format::R8G8 => pixels.chunks_exact(2).flat_map(|p| [p[0], p[1], 0, 255]).collect(),
+        Format::R8 => pixels.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+        // 16 bit channels (little endian), keep the high byte
+        Format::R16G16B16A16 => pixels.chunks_exact(8).flat_map(|p| [p[1], p[3], p[5], p[7]]).collect(),
+        Format::R16G16B16 => pixels.chunks_exact(6).flat_map(|p| [p[1], p[3], p[5], 255]).collect(),
+        Format::R16G16 => pixels.chunks_exact(4).flat_map(|p| [p[1], p[3], 0, 255]).collect(),
+        Format::R16 => pixels.chunks_exact(2).flat_map(|p| [p[1], p[1], p[1], 255]).collect(),
+        Format::R32G32B32FLOAT => pixels.chunks_exact(12).flat_map(|p| {
+            let c = |i: usize| (f32::from_le_bytes([p[i], p[i + 1], p[i + 2], p[i + 3]]).clamp(0.0, 1.0) * 255.0) as u8;
+            [c(0), c(4), c(8), 255]
+        }).collect(),
+        Format::R32G32B32A32FLOAT => pixels.chunks_exact(16).flat_map(|p| {
+            let c = |i: usize| (f32::from_le_bytes([p[i], p[i + 1], p[i + 2], p[i + 3]]).clamp(0.0, 1.0) * 255.0) as u8;
+            [c(0), c(4), c(8), c(12)]
+        }).collect(),
+    }
+}
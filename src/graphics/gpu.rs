@@ -0,0 +1,121 @@
+use std::rc::Rc;
+
+use gl::types::{GLenum, GLuint};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuResourceKind {
+    Buffer,
+    VertexArray,
+    Texture,
+    Framebuffer,
+}
+
+#[derive(Debug)]
+struct GpuResource {
+    id: GLuint,
+    kind: GpuResourceKind,
+    owned: bool,
+}
+
+impl Drop for GpuResource {
+    fn drop(&mut self) {
+        if !self.owned || self.id == 0 {
+            return;
+        }
+
+        unsafe {
+            match self.kind {
+                GpuResourceKind::Buffer => gl::DeleteBuffers(1, &self.id),
+                GpuResourceKind::VertexArray => gl::DeleteVertexArrays(1, &self.id),
+                GpuResourceKind::Texture => gl::DeleteTextures(1, &self.id),
+                GpuResourceKind::Framebuffer => gl::DeleteFramebuffers(1, &self.id),
+            }
+        }
+    }
+}
+
+/// A reference counted GL object. Cloning is cheap and the object gets deleted
+/// exactly once, when the last clone is dropped
+#[derive(Debug, Clone)]
+pub struct GpuHandle(Rc<GpuResource>);
+
+impl GpuHandle {
+    /// Generates a new object of `kind`
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread, and the handle has to be dropped while that
+    /// context is still alive
+    pub unsafe fn new(kind: GpuResourceKind) -> Self {
+        let mut id = 0;
+        match kind {
+            GpuResourceKind::Buffer => gl::GenBuffers(1, &mut id),
+            GpuResourceKind::VertexArray => gl::GenVertexArrays(1, &mut id),
+            GpuResourceKind::Texture => gl::GenTextures(1, &mut id),
+            GpuResourceKind::Framebuffer => gl::GenFramebuffers(1, &mut id),
+        }
+
+        Self::from_raw(id, kind)
+    }
+
+    /// Takes ownership of an object created elsewhere
+    pub fn from_raw(id: GLuint, kind: GpuResourceKind) -> Self {
+        Self(Rc::new(GpuResource { id, kind, owned: true }))
+    }
+
+    /// Refers to an object someone else deletes, like a `RenderTarget`'s depth texture
+    pub fn borrowed(id: GLuint, kind: GpuResourceKind) -> Self {
+        Self(Rc::new(GpuResource { id, kind, owned: false }))
+    }
+
+    /// The 0 object, binding it unbinds
+    pub fn null(kind: GpuResourceKind) -> Self {
+        Self::borrowed(0, kind)
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.0.id
+    }
+
+    pub fn kind(&self) -> GpuResourceKind {
+        self.0.kind
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.0.id == 0
+    }
+
+    /// How many handles share the object
+    pub fn handle_count(&self) -> usize {
+        Rc::strong_count(&self.0)
+    }
+}
+
+impl PartialEq for GpuHandle {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id() && self.kind() == other.kind()
+    }
+}
+
+impl Eq for GpuHandle {}
+
+/// How often a buffer's contents are expected to change, a hint for where the driver keeps it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BufferUsage {
+    /// Set once, drawn many times
+    #[default]
+    Static,
+    /// Changed now and then
+    Dynamic,
+    /// Changed about every frame
+    Stream,
+}
+
+impl BufferUsage {
+    pub fn gl_enum(&self) -> GLenum {
+        match self {
+            BufferUsage::Static => gl::STATIC_DRAW,
+            BufferUsage::Dynamic => gl::DYNAMIC_DRAW,
+            BufferUsage::Stream => gl::STREAM_DRAW,
+        }
+    }
+}
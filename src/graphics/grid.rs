@@ -0,0 +1,90 @@
+use std::{cell::OnceCell, ffi::CString};
+
+use glam::{vec4, Vec4};
+use once_cell::sync::Lazy;
+
+use crate::{cstr, Camera, GpuHandle, GpuResourceKind, Shader, GRID_FS, GRID_VS};
+
+pub static GRID_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(GRID_VS, GRID_FS)
+});
+
+/// A ground grid that goes on forever and fades out with distance, set `Renderer::grid` to show it
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditorGrid {
+    /// Where the grid lies on the y axis
+    pub height: f32,
+    pub cell_size: f32,
+    /// Every how many cells a major line is drawn
+    pub major_every: f32,
+    /// Distance from the camera at which the grid is gone, it starts fading at half of it
+    pub fade_distance: f32,
+    pub color: Vec4,
+    pub major_color: Vec4,
+    /// Draws the x and z axes in red and blue
+    pub show_axes: bool,
+
+    // made by the first draw
+    VAO: OnceCell<GpuHandle>,
+}
+
+impl EditorGrid {
+    pub fn new() -> Self {
+        Self {
+            height: 0.0,
+            cell_size: 1.0,
+            major_every: 10.0,
+            fade_distance: 100.0,
+            color: vec4(0.5, 0.5, 0.5, 0.35),
+            major_color: vec4(0.7, 0.7, 0.7, 0.6),
+            show_axes: true,
+            VAO: OnceCell::new(),
+        }
+    }
+
+    pub fn with_cell_size(mut self, cell_size: f32) -> Self {
+        self.cell_size = cell_size;
+        self
+    }
+
+    pub fn with_fade_distance(mut self, fade_distance: f32) -> Self {
+        self.fade_distance = fade_distance;
+        self
+    }
+
+    // after the opaque geometry, blended over it with depth writes off
+    pub(crate) unsafe fn draw(&self, camera: &Camera) {
+        let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+        let blend = gl::IsEnabled(gl::BLEND) == gl::TRUE;
+        gl::Enable(gl::DEPTH_TEST);
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        gl::DepthMask(gl::FALSE);
+
+        GRID_SHADER.use_shader();
+        camera.send_uniforms(&GRID_SHADER);
+        GRID_SHADER.uniform_1f(cstr!("height"), self.height);
+        GRID_SHADER.uniform_1f(cstr!("cell_size"), self.cell_size.max(f32::EPSILON));
+        GRID_SHADER.uniform_1f(cstr!("major_every"), self.major_every.max(1.0));
+        GRID_SHADER.uniform_1f(cstr!("fade_distance"), self.fade_distance);
+        GRID_SHADER.uniform_vec4f(cstr!("line_color"), &self.color);
+        GRID_SHADER.uniform_vec4f(cstr!("major_color"), &self.major_color);
+        GRID_SHADER.uniform_1i(cstr!("show_axes"), self.show_axes as i32);
+
+        let vao = self.VAO.get_or_init(|| GpuHandle::new(GpuResourceKind::VertexArray));
+        gl::BindVertexArray(vao.id());
+        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        gl::BindVertexArray(0);
+        gl::UseProgram(0);
+
+        gl::DepthMask(gl::TRUE);
+        if !blend { gl::Disable(gl::BLEND) }
+        if !depth_test { gl::Disable(gl::DEPTH_TEST) }
+    }
+}
+
+impl Default for EditorGrid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,6 +1,6 @@
 use std::{ffi::c_void, mem::{offset_of, size_of}, ptr};
 
-use crate::{bind_buffer, cstr, events::EventLoop, gen_attrib_pointers, Camera, INSTANCE_MESH_SHADER_FS, INSTANCE_MESH_SHADER_VS};
+use crate::{bind_buffer, cstr, events::EventLoop, gen_attrib_pointers, Camera, GpuHandle, GpuResourceKind, Transform, INSTANCE_MESH_SHADER_FS, INSTANCE_MESH_SHADER_VS};
 use std::ffi::CString;
 
 use super::{Renderer, Shader, Vertex, DEFAULT_MESH_SHADER_FS, DEFAULT_MESH_SHADER_VS};
@@ -26,45 +26,26 @@ pub struct InstanceData {
 
 impl InstanceData {
     pub fn from_position(pos: Vec3) -> Self {
-        let model = Mat4::from_translation(pos);
-
-        Self {
-            x_axis: model.x_axis,
-            y_axis: model.y_axis,
-            z_axis: model.z_axis,
-            w_axis: model.w_axis,
-        }
+        Self::from_matrix(Mat4::from_translation(pos))
     }
 
     pub fn from_rotation(rot: Quat) -> Self {
-        let model = Mat4::from_quat(rot);
-
-        Self {
-            x_axis: model.x_axis,
-            y_axis: model.y_axis,
-            z_axis: model.z_axis,
-            w_axis: model.w_axis,
-        }
-
+        Self::from_matrix(Mat4::from_quat(rot))
     }
 
     pub fn from_scale(sca: Vec3) -> Self {
-        let model = Mat4::from_scale(sca);
-
-        Self {
-            x_axis: model.x_axis,
-            y_axis: model.y_axis,
-            z_axis: model.z_axis,
-            w_axis: model.w_axis,
-        }
+        Self::from_matrix(Mat4::from_scale(sca))
     }
 
     pub fn new(pos: Vec3, rot: Quat, sca: Vec3) -> Self {
-        let model = 
-            Mat4::from_translation(pos) *
-            Mat4::from_quat(rot) *
-            Mat4::from_scale(sca);
+        Self::from_transform(&Transform::new(pos, rot, sca))
+    }
+
+    pub fn from_transform(transform: &Transform) -> Self {
+        Self::from_matrix(transform.matrix())
+    }
 
+    pub fn from_matrix(model: Mat4) -> Self {
         Self {
             x_axis: model.x_axis,
             y_axis: model.y_axis,
@@ -80,11 +61,11 @@ pub struct InstanceMesh {
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
 
-    pub VAO: u32,
-    EBO: u32,
-    VBO: u32,
+    pub VAO: GpuHandle,
+    EBO: GpuHandle,
+    VBO: GpuHandle,
 
-    pub instance_buffer: u32,
+    pub instance_buffer: GpuHandle,
 
     pub n: usize,
 
@@ -97,8 +78,10 @@ impl InstanceMesh {
     pub fn new(vertices: &Vec<Vertex>, indices: &Vec<u32>, n: usize) -> Self {
         let mut mesh = InstanceMesh {
             vertices: vertices.to_vec(), indices: indices.to_vec(),
-            VAO: 0, VBO: 0, EBO: 0, 
-            instance_buffer: 0,
+            VAO: GpuHandle::null(GpuResourceKind::VertexArray),
+            VBO: GpuHandle::null(GpuResourceKind::Buffer),
+            EBO: GpuHandle::null(GpuResourceKind::Buffer),
+            instance_buffer: GpuHandle::null(GpuResourceKind::Buffer),
             n,
             instance_data: vec![],
             shader: *INSTANCE_SHADER
@@ -110,18 +93,18 @@ impl InstanceMesh {
     }
 
     pub unsafe fn setup_mesh(&mut self) {
-        GenVertexArrays(1, &mut self.VAO);
-        GenBuffers(1, &mut self.VBO);
-        GenBuffers(1, &mut self.EBO);
+        self.VAO = GpuHandle::new(GpuResourceKind::VertexArray);
+        self.VBO = GpuHandle::new(GpuResourceKind::Buffer);
+        self.EBO = GpuHandle::new(GpuResourceKind::Buffer);
         
-        BindVertexArray(self.VAO);
+        BindVertexArray(self.VAO.id());
         
-        bind_buffer!(ARRAY_BUFFER, self.VBO, self.vertices);
-        bind_buffer!(ELEMENT_ARRAY_BUFFER, self.EBO, self.indices);
+        bind_buffer!(ARRAY_BUFFER, self.VBO.id(), self.vertices);
+        bind_buffer!(ELEMENT_ARRAY_BUFFER, self.EBO.id(), self.indices);
         gen_attrib_pointers!(Vertex, 0 => position: 3, 1 => color: 4);
 
-        GenBuffers(1, &mut self.instance_buffer);
-        bind_buffer!(ARRAY_BUFFER, self.instance_buffer, self.instance_data);
+        self.instance_buffer = GpuHandle::new(GpuResourceKind::Buffer);
+        bind_buffer!(ARRAY_BUFFER, self.instance_buffer.id(), self.instance_data);
         
         gen_attrib_pointers!(InstanceData, 2 => x_axis: 4);
         gen_attrib_pointers!(InstanceData, 3 => y_axis: 4);
@@ -138,7 +121,7 @@ impl InstanceMesh {
     pub unsafe fn draw(&self, el: &EventLoop) {
         let (w, h) = el.window.get_framebuffer_size();
 
-        BindVertexArray(self.VAO);
+        BindVertexArray(self.VAO.id());
         self.shader.use_shader();
         DrawElementsInstanced(TRIANGLES, self.indices.len() as i32, UNSIGNED_INT, ptr::null(), self.n as i32);
         BindVertexArray(0);
@@ -185,13 +168,3 @@ impl Renderer {
     }
     */
 }
-
-impl Drop for InstanceMesh {
-    fn drop(&mut self) {
-        unsafe {
-            DeleteVertexArrays(1, &self.VAO);
-            DeleteBuffers(1, &self.EBO);
-            DeleteBuffers(1, &self.VBO);
-        }
-    }
-}
\ No newline at end of file
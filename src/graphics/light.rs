@@ -1,28 +1,139 @@
 use gl::UseProgram;
-use glam::{vec3, Vec3};
+use glam::{vec2, vec4, Vec3};
 
 use std::ffi::CString;
 
-use crate::{cstr, Renderer, Shader};
+use crate::{cstr, look_rotation, Camera, LightFrame, Transform, Renderer, Shader, CLUSTER_DIMS, LIGHT_DATA_UNIT, LIGHT_GRID_UNIT, LIGHT_INDEX_UNIT};
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LightKind {
+    /// infinitely far away, only the direction it shines towards matters
+    Directional,
+    /// fades out smoothly until it reaches zero at `range`, a range of 0 never fades
+    Point { range: f32 },
+    /// a point light limited to a cone, fully lit inside `inner_angle` and
+    /// fading out towards `outer_angle` (both in radians, from the center of the cone)
+    Spot { range: f32, inner_angle: f32, outer_angle: f32 },
+}
+
+impl LightKind {
+    pub(crate) fn id(&self) -> i32 {
+        match self {
+            LightKind::Directional => 0,
+            LightKind::Point { .. } => 1,
+            LightKind::Spot { .. } => 2,
+        }
+    }
+}
+
+/// `position` used to be a field of its own, it now lives in `transform` next to the rotation
+/// that aims directional and spot lights. Struct literals can fill in the rest with
+/// `..Default::default()`, which is a white point light at the origin that never fades
+#[derive(Debug, Clone, PartialEq)]
 pub struct Light {
-    pub position: Vec3,
+    /// Directional and spot lights shine along its forward, scale is ignored
+    pub transform: Transform,
     pub color: Vec3,
+    pub intensity: f32,
+    pub kind: LightKind,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self::new(Vec3::ZERO, Vec3::ONE)
+    }
+}
+
+impl Light {
+    /// A point light that never fades, how every light used to behave
+    pub fn new(position: Vec3, color: Vec3) -> Self {
+        Self::point(position, color, 0.0)
+    }
+
+    pub fn point(position: Vec3, color: Vec3, range: f32) -> Self {
+        Self {
+            transform: Transform::from_position(position),
+            color,
+            intensity: 1.0,
+            kind: LightKind::Point { range },
+        }
+    }
+
+    pub fn directional(direction: Vec3, color: Vec3) -> Self {
+        Self {
+            transform: Transform::new(Vec3::ZERO, look_rotation(direction, Vec3::Y).unwrap_or_default(), Vec3::ONE),
+            color,
+            intensity: 1.0,
+            kind: LightKind::Directional,
+        }
+    }
+
+    pub fn spot(position: Vec3, direction: Vec3, color: Vec3, range: f32, inner_angle: f32, outer_angle: f32) -> Self {
+        Self {
+            transform: Transform::new(position, look_rotation(direction, Vec3::Y).unwrap_or_default(), Vec3::ONE),
+            color,
+            intensity: 1.0,
+            kind: LightKind::Spot {
+                range,
+                inner_angle,
+                outer_angle: outer_angle.max(inner_angle),
+            },
+        }
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Where the light shines towards, None for point lights
+    pub fn direction(&self) -> Option<Vec3> {
+        match self.kind {
+            LightKind::Directional | LightKind::Spot { .. } => Some(self.transform.forward()),
+            LightKind::Point { .. } => None,
+        }
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.transform.position()
+    }
+
+    /// How far the light reaches, infinite for directional lights and lights that never fade
+    pub fn range(&self) -> f32 {
+        match self.kind {
+            LightKind::Directional => f32::INFINITY,
+            LightKind::Point { range } | LightKind::Spot { range, .. } => {
+                if range > 0.0 { range } else { f32::INFINITY }
+            }
+        }
+    }
 }
 
 impl Renderer {
     pub unsafe fn send_light_uniforms(&self, shader: &Shader) {
+        let frame = self.upload_lights(&self.camera);
+        self.send_light_frame(shader, &frame);
+    }
+
+    pub(crate) unsafe fn upload_lights(&self, camera: &Camera) -> LightFrame {
+        self.light_buffer.upload(self.lights.values(), camera)
+    }
+
+    pub(crate) unsafe fn send_light_frame(&self, shader: &Shader, frame: &LightFrame) {
+        self.light_buffer.bind();
+
         shader.use_shader();
-        shader.uniform_vec3f(cstr!("viewPos"), &self.camera.pos);
-        shader.uniform_1i(cstr!("num_lights"), self.lights.len() as i32);
-        let mut i = 0;
-        for light in self.lights.values() {
-            shader.uniform_vec3f(cstr!(format!("lightColor[{}]", i)), &light.color);
-            shader.uniform_vec3f(cstr!(format!("lightPos[{}]", i)), &light.position);
-
-            i+=1;
-        }
+        shader.uniform_vec3f(cstr!("viewPos"), &frame.view_pos);
+        shader.uniform_1i(cstr!("lightData"), LIGHT_DATA_UNIT as i32);
+        shader.uniform_1i(cstr!("lightGrid"), LIGHT_GRID_UNIT as i32);
+        shader.uniform_1i(cstr!("lightIndices"), LIGHT_INDEX_UNIT as i32);
+
+        shader.uniform_1i(cstr!("num_global_lights"), frame.global_lights);
+        shader.uniform_1i(cstr!("clustered"), frame.clustered as i32);
+        shader.uniform_3i(cstr!("clusterDims"), CLUSTER_DIMS[0] as i32, CLUSTER_DIMS[1] as i32, CLUSTER_DIMS[2] as i32);
+        shader.uniform_vec2f(cstr!("clusterDepth"), &vec2(frame.near, frame.far));
+        let [x, y, w, h] = frame.viewport;
+        shader.uniform_vec4f(cstr!("clusterViewport"), &vec4(x as f32, y as f32, w.max(1) as f32, h.max(1) as f32));
         UseProgram(0);
     }
 
@@ -30,6 +141,14 @@ impl Renderer {
         self.lights.insert(name.to_string(), light);
     }
 
+    pub fn remove_light(&mut self, name: &str) -> Result<(), String> {
+        if self.lights.remove(name).is_some() {
+            Ok(())
+        } else {
+            Err(format!("No light found with name '{}'", name))
+        }
+    }
+
     pub fn get_light(&self, name: &str) -> Option<&Light> {
         self.lights.get(name)
     }
@@ -37,4 +156,4 @@ impl Renderer {
     pub fn get_light_mut(&mut self, name: &str) -> Option<&mut Light> {
         self.lights.get_mut(name)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,318 @@
+use std::cell::OnceCell;
+
+use gl::types::{GLint, GLsizeiptr, GLuint, GLvoid};
+use glam::{Mat4, Vec3, Vec4Swizzles};
+
+use crate::{Camera, GpuHandle, GpuResourceKind, Light, LightKind};
+
+pub const LIGHT_DATA_UNIT: u32 = 7;
+pub const LIGHT_GRID_UNIT: u32 = 8;
+pub const LIGHT_INDEX_UNIT: u32 = 9;
+
+/// How the view frustum gets split up for light culling, x and y are screen tiles
+/// and z slices are spaced exponentially between the camera's near and far planes
+pub const CLUSTER_DIMS: [u32; 3] = [16, 9, 24];
+
+// one texture buffer backed by a buffer object, re-filled every frame
+struct TextureBuffer {
+    buffer: GpuHandle,
+    texture: GpuHandle,
+}
+
+impl TextureBuffer {
+    unsafe fn new(format: GLuint) -> Self {
+        let buffer = GpuHandle::new(GpuResourceKind::Buffer);
+        let texture = GpuHandle::new(GpuResourceKind::Texture);
+
+        gl::BindBuffer(gl::TEXTURE_BUFFER, buffer.id());
+        gl::BufferData(gl::TEXTURE_BUFFER, 16, std::ptr::null(), gl::STREAM_DRAW);
+
+        gl::BindTexture(gl::TEXTURE_BUFFER, texture.id());
+        gl::TexBuffer(gl::TEXTURE_BUFFER, format, buffer.id());
+
+        gl::BindTexture(gl::TEXTURE_BUFFER, 0);
+        gl::BindBuffer(gl::TEXTURE_BUFFER, 0);
+
+        Self { buffer, texture }
+    }
+
+    unsafe fn upload<T>(&self, data: &[T]) {
+        // empty buffers aren't allowed, keep at least a few bytes around
+        let size = (std::mem::size_of_val(data)).max(16);
+
+        gl::BindBuffer(gl::TEXTURE_BUFFER, self.buffer.id());
+        gl::BufferData(gl::TEXTURE_BUFFER, size as GLsizeiptr, std::ptr::null(), gl::STREAM_DRAW);
+        if !data.is_empty() {
+            gl::BufferSubData(gl::TEXTURE_BUFFER, 0, std::mem::size_of_val(data) as GLsizeiptr, data.as_ptr() as *const GLvoid);
+        }
+        gl::BindBuffer(gl::TEXTURE_BUFFER, 0);
+    }
+
+    unsafe fn bind(&self, unit: u32) {
+        gl::ActiveTexture(gl::TEXTURE0 + unit);
+        gl::BindTexture(gl::TEXTURE_BUFFER, self.texture.id());
+        gl::ActiveTexture(gl::TEXTURE0);
+    }
+}
+
+/// The gpu side of the renderer's lights: every light packed into a texture buffer
+/// plus the per cluster light lists, so any number of lights can be drawn
+pub struct LightBuffer {
+    // made by the first upload, so `Renderer::new` doesn't need a context
+    textures: OnceCell<LightTextures>,
+}
+
+struct LightTextures {
+    data: TextureBuffer,
+    grid: TextureBuffer,
+    indices: TextureBuffer,
+
+    max_texels: usize,
+}
+
+/// What a frame's light upload produced, sent to every lit shader
+pub(crate) struct LightFrame {
+    pub view_pos: Vec3,
+    pub global_lights: i32,
+    pub clustered: bool,
+    pub near: f32,
+    pub far: f32,
+    pub viewport: [GLint; 4],
+}
+
+impl LightBuffer {
+    pub fn new() -> Self {
+        Self { textures: OnceCell::new() }
+    }
+
+    unsafe fn textures(&self) -> &LightTextures {
+        self.textures.get_or_init(|| {
+            let mut max_texels = 0;
+            gl::GetIntegerv(gl::MAX_TEXTURE_BUFFER_SIZE, &mut max_texels);
+
+            LightTextures {
+                data: TextureBuffer::new(gl::RGBA32F),
+                grid: TextureBuffer::new(gl::RG32UI),
+                indices: TextureBuffer::new(gl::R32UI),
+                max_texels: max_texels.max(65536) as usize,
+            }
+        })
+    }
+
+    /// Packs `lights` and bins them into clusters for `camera`, light i in the
+    /// shaders is the i-th light of the iterator
+    pub(crate) unsafe fn upload<'a>(&self, lights: impl Iterator<Item = &'a Light>, camera: &Camera) -> LightFrame {
+        let lights: Vec<&Light> = lights.collect();
+
+        let mut viewport = [0; 4];
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+
+        let mut data: Vec<f32> = Vec::with_capacity(lights.len() * 16);
+        for light in &lights {
+            let (range, cone) = match light.kind {
+                LightKind::Directional => (0.0, [-1.0, -1.0]),
+                LightKind::Point { range } => (range, [-1.0, -1.0]),
+                LightKind::Spot { range, inner_angle, outer_angle, .. } => (range, [inner_angle.cos(), outer_angle.cos()]),
+            };
+            let direction = light.direction().unwrap_or(Vec3::ZERO);
+
+            let position = light.position();
+
+            data.extend_from_slice(&[position.x, position.y, position.z, light.kind.id() as f32]);
+            data.extend_from_slice(&[light.color.x, light.color.y, light.color.z, light.intensity]);
+            data.extend_from_slice(&[direction.x, direction.y, direction.z, range]);
+            data.extend_from_slice(&[cone[0], cone[1], 0.0, 0.0]);
+        }
+
+        let planes = camera.clip_planes();
+        let (global, clusters) = match planes {
+            Some((near, far)) => bin_lights(&lights, &camera.view, &camera.proj, near, far),
+            None => ((0..lights.len() as u32).collect(), Vec::new()),
+        };
+
+        let textures = self.textures();
+        let mut indices = global.clone();
+        let mut grid: Vec<u32> = Vec::with_capacity(clusters.len() * 2);
+        for cluster in &clusters {
+            // past the texture buffer limit the remaining clusters just go dark
+            let room = textures.max_texels.saturating_sub(indices.len());
+            let count = cluster.len().min(room);
+            grid.push(indices.len() as u32);
+            grid.push(count as u32);
+            indices.extend_from_slice(&cluster[..count]);
+        }
+
+        textures.data.upload(&data);
+        textures.grid.upload(&grid);
+        textures.indices.upload(&indices);
+
+        let (near, far) = planes.unwrap_or((0.0, 1.0));
+        LightFrame {
+            view_pos: camera.pos,
+            global_lights: global.len() as i32,
+            clustered: planes.is_some(),
+            near,
+            far,
+            viewport,
+        }
+    }
+
+    pub(crate) unsafe fn bind(&self) {
+        let textures = self.textures();
+        textures.data.bind(LIGHT_DATA_UNIT);
+        textures.grid.bind(LIGHT_GRID_UNIT);
+        textures.indices.bind(LIGHT_INDEX_UNIT);
+    }
+}
+
+impl Default for LightBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// lights without a range reach everything, so they skip the clusters and come back
+// first. the rest go in every cluster their sphere touches
+fn bin_lights(lights: &[&Light], view: &Mat4, proj: &Mat4, near: f32, far: f32) -> (Vec<u32>, Vec<Vec<u32>>) {
+    let mut global: Vec<u32> = Vec::new();
+
+    let [cx, cy, cz] = CLUSTER_DIMS;
+    let mut clusters = vec![Vec::new(); (cx * cy * cz) as usize];
+    let bounds = cluster_bounds(proj, near, far);
+
+    for (i, light) in lights.iter().enumerate() {
+        let range = light.range();
+        if !range.is_finite() {
+            global.push(i as u32);
+            continue;
+        }
+
+        let center = (*view * light.position().extend(1.0)).xyz();
+        let depth = -center.z;
+        if depth + range < near || depth - range > far {
+            continue;
+        }
+
+        let first = depth_slice(depth - range, near, far);
+        let last = depth_slice(depth + range, near, far);
+        for z in first..=last {
+            for xy in 0..(cx * cy) as usize {
+                let cluster = z * (cx * cy) as usize + xy;
+                let (min, max) = bounds[cluster];
+                let closest = center.clamp(min, max);
+                if closest.distance_squared(center) <= range * range {
+                    clusters[cluster].push(i as u32);
+                }
+            }
+        }
+    }
+
+    (global, clusters)
+}
+
+fn depth_slice(depth: f32, near: f32, far: f32) -> usize {
+    let slices = CLUSTER_DIMS[2];
+    let slice = (depth.max(near) / near).ln() / (far / near).ln() * slices as f32;
+    (slice.max(0.0) as usize).min(slices as usize - 1)
+}
+
+// view space bounding boxes of every cluster, in the order the shader indexes them
+fn cluster_bounds(proj: &Mat4, near: f32, far: f32) -> Vec<(Vec3, Vec3)> {
+    let [cx, cy, cz] = CLUSTER_DIMS;
+    let mut bounds = Vec::with_capacity((cx * cy * cz) as usize);
+
+    for z in 0..cz {
+        let slice_near = near * (far / near).powf(z as f32 / cz as f32);
+        let slice_far = near * (far / near).powf((z + 1) as f32 / cz as f32);
+
+        for y in 0..cy {
+            for x in 0..cx {
+                let x0 = -1.0 + 2.0 * x as f32 / cx as f32;
+                let x1 = -1.0 + 2.0 * (x + 1) as f32 / cx as f32;
+                let y0 = -1.0 + 2.0 * y as f32 / cy as f32;
+                let y1 = -1.0 + 2.0 * (y + 1) as f32 / cy as f32;
+
+                let mut min = Vec3::splat(f32::INFINITY);
+                let mut max = Vec3::splat(f32::NEG_INFINITY);
+                for depth in [slice_near, slice_far] {
+                    for (nx, ny) in [(x0, y0), (x1, y0), (x0, y1), (x1, y1)] {
+                        let corner = Vec3::new(nx * depth / proj.x_axis.x, ny * depth / proj.y_axis.y, -depth);
+                        min = min.min(corner);
+                        max = max.max(corner);
+                    }
+                }
+
+                bounds.push((min, max));
+            }
+        }
+    }
+
+    bounds
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec3;
+
+    use super::*;
+
+    const NEAR: f32 = 0.1;
+    const FAR: f32 = 100.0;
+
+    fn proj() -> Mat4 {
+        Mat4::perspective_rh_gl(90f32.to_radians(), 16.0 / 9.0, NEAR, FAR)
+    }
+
+    #[test]
+    fn depth_slices_are_exponential_and_clamped() {
+        let last = CLUSTER_DIMS[2] as usize - 1;
+
+        assert_eq!(depth_slice(NEAR, NEAR, FAR), 0);
+        assert_eq!(depth_slice(0.0, NEAR, FAR), 0);
+        assert_eq!(depth_slice(FAR, NEAR, FAR), last);
+        assert_eq!(depth_slice(FAR * 10.0, NEAR, FAR), last);
+        // halfway in log space is halfway through the slices
+        assert_eq!(depth_slice((NEAR * FAR).sqrt() * 1.01, NEAR, FAR), CLUSTER_DIMS[2] as usize / 2);
+    }
+
+    #[test]
+    fn cluster_bounds_tile_the_frustum() {
+        let bounds = cluster_bounds(&proj(), NEAR, FAR);
+        let [cx, cy, cz] = CLUSTER_DIMS;
+        assert_eq!(bounds.len(), (cx * cy * cz) as usize);
+
+        // the first slice starts at the near plane and the last one ends at the far plane
+        let (_, first_max) = bounds[0];
+        let (last_min, _) = bounds[bounds.len() - 1];
+        assert!((first_max.z + NEAR).abs() < 1e-5);
+        assert!((last_min.z + FAR).abs() < 1e-3);
+        for (min, max) in bounds {
+            assert!(min.cmple(max).all());
+        }
+    }
+
+    #[test]
+    fn lights_go_in_the_clusters_they_touch() {
+        let lights = [
+            Light::directional(-Vec3::Y, Vec3::ONE),
+            Light::point(vec3(0.0, 0.0, -10.0), Vec3::ONE, 0.5),
+            // behind the camera
+            Light::point(vec3(0.0, 0.0, 10.0), Vec3::ONE, 0.5),
+            Light::new(Vec3::ZERO, Vec3::ONE),
+        ];
+        let lights: Vec<&Light> = lights.iter().collect();
+        let (global, clusters) = bin_lights(&lights, &Mat4::IDENTITY, &proj(), NEAR, FAR);
+
+        assert_eq!(global, vec![0, 3]);
+
+        let [cx, cy, _] = CLUSTER_DIMS;
+        let touched: Vec<usize> = (0..clusters.len()).filter(|&c| clusters[c].contains(&1)).collect();
+        assert!(!touched.is_empty());
+        for c in touched {
+            let (x, y, z) = (c % cx as usize, c / cx as usize % cy as usize, c / (cx * cy) as usize);
+            assert!((7..=8).contains(&x) && y == 4, "cluster {} {} is off center", x, y);
+            assert!((depth_slice(9.5, NEAR, FAR)..=depth_slice(10.5, NEAR, FAR)).contains(&z));
+        }
+        assert!(clusters.iter().all(|c| !c.contains(&2)));
+    }
+}
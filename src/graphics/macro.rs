@@ -13,11 +13,14 @@ macro_rules! gen_attrib_pointers {
 
 #[macro_export]
 macro_rules! bind_buffer {
-    ($buffer_type:expr, $buffer:expr, $data:expr) => {{
+    ($buffer_type:expr, $buffer:expr, $data:expr) => {
+        $crate::bind_buffer!($buffer_type, $buffer, $data, STATIC_DRAW)
+    };
+    ($buffer_type:expr, $buffer:expr, $data:expr, $usage:expr) => {{
         BindBuffer($buffer_type, $buffer);
         let size = ($data.len() * std::mem::size_of_val(&$data[0])) as isize;
         let data_ptr = &$data[0] as *const _ as *const std::ffi::c_void;
-        BufferData($buffer_type, size, data_ptr, STATIC_DRAW);
+        BufferData($buffer_type, size, data_ptr, $usage);
     }};
 }
 
@@ -0,0 +1,216 @@
+use std::{cell::RefCell, collections::HashMap, ffi::CString, rc::Rc};
+
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+use crate::{cstr, GpuHandle, Shader, ShaderType, DEFAULT_SHADER, FULL_SHADER, LIGHT_SHADER, PBR_SHADER};
+
+/// A material shared between meshes, editing it through one mesh changes all of them
+pub type SharedMaterial = Rc<RefCell<Material>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureSlot {
+    Albedo,
+    Normal,
+    /// specular strength for the phong shaders, metallic/roughness for the pbr one
+    /// (roughness in green, metallic in blue, like glTF)
+    Specular,
+    Emissive,
+    /// ambient occlusion in red, only used by the pbr shader
+    Occlusion,
+}
+
+impl TextureSlot {
+    pub const ALL: [TextureSlot; 5] = [TextureSlot::Albedo, TextureSlot::Normal, TextureSlot::Specular, TextureSlot::Emissive, TextureSlot::Occlusion];
+
+    /// The texture unit the slot gets bound to
+    pub fn unit(&self) -> u32 {
+        match self {
+            TextureSlot::Albedo => 0,
+            TextureSlot::Normal => 1,
+            TextureSlot::Specular => 2,
+            TextureSlot::Emissive => 3,
+            TextureSlot::Occlusion => 4,
+        }
+    }
+
+    // sampler and "is it bound" flag names in the shaders
+    fn uniform_names(&self) -> (&'static str, &'static str) {
+        match self {
+            TextureSlot::Albedo => ("texture1", "has_texture"),
+            TextureSlot::Normal => ("normalMap", "has_normal_map"),
+            TextureSlot::Specular => ("specularMap", "has_specular_map"),
+            TextureSlot::Emissive => ("emissiveMap", "has_emissive_map"),
+            TextureSlot::Occlusion => ("occlusionMap", "has_occlusion_map"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UniformValue {
+    Int(i32),
+    Float(f32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+    Mat4(Mat4),
+}
+
+impl From<i32> for UniformValue {
+    fn from(v: i32) -> Self { UniformValue::Int(v) }
+}
+
+impl From<f32> for UniformValue {
+    fn from(v: f32) -> Self { UniformValue::Float(v) }
+}
+
+impl From<Vec2> for UniformValue {
+    fn from(v: Vec2) -> Self { UniformValue::Vec2(v) }
+}
+
+impl From<Vec3> for UniformValue {
+    fn from(v: Vec3) -> Self { UniformValue::Vec3(v) }
+}
+
+impl From<Vec4> for UniformValue {
+    fn from(v: Vec4) -> Self { UniformValue::Vec4(v) }
+}
+
+impl From<Mat4> for UniformValue {
+    fn from(v: Mat4) -> Self { UniformValue::Mat4(v) }
+}
+
+/// A shader plus everything it needs to draw a mesh: named uniform values and textures.
+/// Uniforms stay set on the program between draws, so every material using a shader
+/// should give values for the same uniforms
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub shader: Shader,
+    pub uniforms: HashMap<String, UniformValue>,
+    pub textures: HashMap<TextureSlot, GpuHandle>,
+}
+
+impl Material {
+    /// Starts with a white `base_color`, the built in mesh shaders multiply by it
+    pub fn new(shader: Shader) -> Self {
+        let mut material = Self {
+            shader,
+            uniforms: HashMap::new(),
+            textures: HashMap::new(),
+        };
+        material.set_uniform("base_color", Vec4::ONE);
+        material
+    }
+
+    /// One of the built in shaders, with its uniforms at their defaults.
+    /// The pbr one tonemaps on its own, set `tonemap` to 0 when a `PostEffect::Tonemap`
+    /// runs after it, and `emissive_factor` multiplies the emissive texture
+    pub fn from_shader_type(shader_type: &ShaderType) -> Self {
+        let mut material = match shader_type {
+            ShaderType::Default => Self::new(*DEFAULT_SHADER),
+            ShaderType::Light => Self::new(*LIGHT_SHADER),
+            ShaderType::Full => Self::new(*FULL_SHADER),
+            ShaderType::Pbr => Self::new(*PBR_SHADER),
+        };
+
+        match shader_type {
+            ShaderType::Default => (),
+            ShaderType::Light => {
+                material.set_uniform("ambient_strength", 0.1);
+                material.set_uniform("specular_strength", 0.5);
+                material.set_uniform("shininess", 32.0);
+            }
+            ShaderType::Full => {
+                material.set_uniform("ambient_strength", 0.1);
+                material.set_uniform("ambient_color", Vec3::ONE);
+                material.set_uniform("specular_strength", 0.5);
+                material.set_uniform("specular_color", Vec3::ONE);
+                material.set_uniform("shininess", 32.0);
+                material.set_uniform("reflectivity", 0.0);
+            }
+            ShaderType::Pbr => {
+                material.set_uniform("metallic", 0.0);
+                material.set_uniform("roughness", 0.5);
+                material.set_uniform("emissive_factor", Vec3::ZERO);
+                material.set_uniform("occlusion_strength", 1.0);
+                material.set_uniform("ambient_strength", 0.03);
+                material.set_uniform("tonemap", 1);
+            }
+        }
+
+        material
+    }
+
+    pub fn shared(self) -> SharedMaterial {
+        Rc::new(RefCell::new(self))
+    }
+
+    pub fn set_uniform(&mut self, name: &str, value: impl Into<UniformValue>) {
+        self.uniforms.insert(name.to_owned(), value.into());
+    }
+
+    pub fn get_uniform(&self, name: &str) -> Option<UniformValue> {
+        self.uniforms.get(name).copied()
+    }
+
+    pub fn set_texture(&mut self, slot: TextureSlot, texture: GpuHandle) {
+        self.textures.insert(slot, texture);
+    }
+
+    pub fn remove_texture(&mut self, slot: TextureSlot) {
+        self.textures.remove(&slot);
+    }
+
+    pub fn texture(&self, slot: TextureSlot) -> Option<&GpuHandle> {
+        self.textures.get(&slot)
+    }
+
+    /// Binds the shader, sends the uniforms and binds the textures to their slots' units
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn apply(&self) {
+        let shader = &self.shader;
+        shader.use_shader();
+
+        for (name, value) in &self.uniforms {
+            let name = name.as_str();
+            match value {
+                UniformValue::Int(v) => shader.uniform_1i(cstr!(name), *v),
+                UniformValue::Float(v) => shader.uniform_1f(cstr!(name), *v),
+                UniformValue::Vec2(v) => shader.uniform_vec2f(cstr!(name), v),
+                UniformValue::Vec3(v) => shader.uniform_vec3f(cstr!(name), v),
+                UniformValue::Vec4(v) => shader.uniform_vec4f(cstr!(name), v),
+                UniformValue::Mat4(v) => shader.uniform_mat4fv(cstr!(name), &v.to_cols_array()),
+            }
+        }
+
+        for slot in TextureSlot::ALL {
+            let (sampler, flag) = slot.uniform_names();
+            let texture = self.texture(slot);
+
+            shader.uniform_1i(cstr!(sampler), slot.unit() as i32);
+            shader.uniform_1i(cstr!(flag), texture.is_some() as i32);
+
+            gl::ActiveTexture(gl::TEXTURE0 + slot.unit());
+            gl::BindTexture(gl::TEXTURE_2D, texture.map_or(0, GpuHandle::id));
+        }
+        gl::ActiveTexture(gl::TEXTURE0);
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self::from_shader_type(&ShaderType::Default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_materials_are_white() {
+        let material = Material::new(Shader { id: 0 });
+        assert_eq!(material.get_uniform("base_color"), Some(UniformValue::Vec4(Vec4::ONE)));
+    }
+}
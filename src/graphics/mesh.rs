@@ -1,11 +1,11 @@
-use std::ptr;
+use std::{cell::{Cell, RefMut}, ptr, rc::Rc};
 
-use crate::{bind_buffer, cstr, events::EventLoop, gen_attrib_pointers, InstanceData, InstanceMesh, ShaderType, FULL_SHADER_FS, FULL_SHADER_VS, LIGHT_MESH_SHADER_FS, LIGHT_MESH_SHADER_VS};
+use crate::{bind_buffer, cstr, events::EventLoop, Aabb, BufferUsage, GpuHandle, GpuResourceKind, InstanceData, InstanceMesh, Material, NodeTarget, ShaderType, SharedMaterial, skinned_shader, MorphTarget, SkinVertex, VertexLayout, VertexStream, TextureSlot, Transform, FULL_SHADER_FS, FULL_SHADER_VS, LIGHT_MESH_SHADER_FS, LIGHT_MESH_SHADER_VS, PBR_SHADER_FS};
 use std::ffi::CString;
 
-use super::{Renderer, Shader, Vertex, DEFAULT_MESH_SHADER_FS, DEFAULT_MESH_SHADER_VS};
+use super::{mesh_buffers::UploadState, Renderer, Shader, Vertex, DEFAULT_MESH_SHADER_FS, DEFAULT_MESH_SHADER_VS};
 
-use gl::{*, types::GLsizei};
+use gl::{*, types::GLenum};
 use glam::{Mat4, Quat, Vec3, Vec4};
 use once_cell::sync::Lazy;
 
@@ -21,65 +21,164 @@ pub static FULL_SHADER: Lazy<Shader> = Lazy::new(|| {
     Shader::new_pipeline(FULL_SHADER_VS, FULL_SHADER_FS)
 });
 
+pub static PBR_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(FULL_SHADER_VS, PBR_SHADER_FS)
+});
+
+/// How the indices are put together into shapes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PrimitiveMode {
+    #[default]
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+    Lines,
+    LineStrip,
+    LineLoop,
+    Points,
+}
+
+impl PrimitiveMode {
+    pub fn gl_enum(&self) -> GLenum {
+        match self {
+            PrimitiveMode::Triangles => TRIANGLES,
+            PrimitiveMode::TriangleStrip => TRIANGLE_STRIP,
+            PrimitiveMode::TriangleFan => TRIANGLE_FAN,
+            PrimitiveMode::Lines => LINES,
+            PrimitiveMode::LineStrip => LINE_STRIP,
+            PrimitiveMode::LineLoop => LINE_LOOP,
+            PrimitiveMode::Points => POINTS,
+        }
+    }
+
+    /// Whether the indices make faces, the modes normals and tangents can be computed for
+    pub fn is_triangles(&self) -> bool {
+        matches!(self, PrimitiveMode::Triangles | PrimitiveMode::TriangleStrip | PrimitiveMode::TriangleFan)
+    }
+}
+
+/// Size of the indices on the gpu, `indices` are always `u32` on the cpu side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IndexFormat {
+    /// `u16` when every index fits, `u32` otherwise
+    #[default]
+    Auto,
+    /// Always `u16`. An index past 65535 can't be stored, so those indices go up as
+    /// `u32` and a warning is printed
+    U16,
+    U32,
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct Mesh {
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
 
-    pub VAO: u32,
-    EBO: u32,
-    VBO: u32,
-
-    pub position: Vec3,
-    pub rotation: Quat,
-    pub scale: Vec3,
-
-    pub texture: u32,
-    shader: Shader,
-    pub parent: Option<Box<Mesh>>,
-    pub children: Vec<Box<Mesh>>,
+    /// Joints and weights for each vertex, empty unless the mesh is skinned
+    pub skin_vertices: Vec<SkinVertex>,
+    /// Index into `Model::skins`, skinned meshes ignore their node and follow the joints instead
+    pub skin: Option<usize>,
+    /// Blend shapes, evaluated on the gpu by the full, pbr and shadow shaders
+    pub morph_targets: Vec<MorphTarget>,
+    /// One for each of `morph_targets`
+    pub morph_weights: Vec<f32>,
+    /// Extra vertex buffers with their own layouts, see `VertexStream`
+    pub vertex_streams: Vec<VertexStream>,
+
+    pub primitive_mode: PrimitiveMode,
+    pub index_format: IndexFormat,
+    /// Picked when the buffers are made, `Dynamic` or `Stream` for meshes that change often
+    pub usage: BufferUsage,
+    // vertex range and whether the indices changed since the last upload
+    pub(crate) dirty_vertices: Cell<Option<(usize, usize)>>,
+    pub(crate) dirty_indices: Cell<bool>,
+    // what the buffers hold, shared with them by clones
+    pub(crate) uploaded: Rc<UploadState>,
+
+    pub VAO: GpuHandle,
+    pub(crate) EBO: GpuHandle,
+    pub(crate) VBO: GpuHandle,
+    pub(crate) SKIN_VBO: GpuHandle,
+    pub(crate) MORPH_BUFFER: GpuHandle,
+    pub(crate) MORPH_TEXTURE: GpuHandle,
+    // vertices and targets the morph buffer was laid out for by `setup_mesh`
+    pub(crate) morph_layout: (usize, usize),
+
+    /// Relative to the parent in `Renderer::scene`
+    pub transform: Transform,
+
+    pub material: SharedMaterial,
+
+    pub cast_shadows: bool,
+    pub receive_shadows: bool,
 }
 
 impl Mesh {
     pub fn new(vertices: &Vec<Vertex>, indices: &Vec<u32>) -> Self {
+        Self::with_material(vertices, indices, Material::default().shared())
+    }
+
+    /// Like `new` with `material` instead of the default one, which needs a GL context for its shader
+    pub fn with_material(vertices: &[Vertex], indices: &[u32], material: SharedMaterial) -> Self {
         let mesh = Mesh {
             vertices: vertices.to_vec(), indices: indices.to_vec(),
-            VAO: 0, VBO: 0, EBO: 0,
-            position: Vec3::ZERO,
-            rotation: Quat::from_euler(glam::EulerRot::XYZ, 0.0, 0.0, 0.0),
-            scale: Vec3::ONE,
-            texture: 0,
-            shader: *DEFAULT_SHADER,
-            parent: None,
-            children: Vec::new(),
+            skin_vertices: Vec::new(),
+            skin: None,
+            morph_targets: Vec::new(),
+            morph_weights: Vec::new(),
+            vertex_streams: Vec::new(),
+            primitive_mode: PrimitiveMode::Triangles,
+            index_format: IndexFormat::Auto,
+            usage: BufferUsage::Static,
+            dirty_vertices: Cell::new(None),
+            dirty_indices: Cell::new(false),
+            uploaded: Rc::new(UploadState::new()),
+            VAO: GpuHandle::null(GpuResourceKind::VertexArray),
+            VBO: GpuHandle::null(GpuResourceKind::Buffer),
+            EBO: GpuHandle::null(GpuResourceKind::Buffer),
+            SKIN_VBO: GpuHandle::null(GpuResourceKind::Buffer),
+            MORPH_BUFFER: GpuHandle::null(GpuResourceKind::Buffer),
+            MORPH_TEXTURE: GpuHandle::null(GpuResourceKind::Texture),
+            morph_layout: (0, 0),
+            transform: Transform::default(),
+            material,
+            cast_shadows: true,
+            receive_shadows: true,
         };
 
         mesh
     }
 
+    /// Switches to one of the built in shaders, keeping the textures. A material shared
+    /// with other meshes gets copied first so they're left alone. Skinned meshes get
+    /// the skinned version of the shader
     pub fn set_shader_type(&mut self, shader_type: &ShaderType) {
-        let shader: Shader;
-
-        match shader_type{
-            ShaderType::Default => {
-                shader = *DEFAULT_SHADER;
-            }
-            ShaderType::Light => {
-                shader = *LIGHT_SHADER;
-            }
-            ShaderType::Full => {
-                shader = *FULL_SHADER;
-            }
+        let mut material = Material::from_shader_type(shader_type);
+        if self.skin.is_some() {
+            material.shader = skinned_shader(material.shader);
         }
+        material.textures = self.material.borrow().textures.clone();
 
-        self.shader = shader;
+        self.material = material.shared();
     }
 
     pub fn set_texture(&mut self, texture_name: &str, renderer: &Renderer) {
-        self.texture = renderer.get_texture(texture_name.to_owned());
-        unsafe {
-            self.shader.uniform_1i(cstr!("has_texture"), 1);
+        let texture = renderer.get_texture(texture_name.to_owned());
+        self.material_mut().set_texture(TextureSlot::Albedo, texture);
+    }
+
+    pub fn set_material(&mut self, material: SharedMaterial) {
+        self.material = material;
+    }
+
+    /// Mutable access to this mesh's own material, copying it first if it's shared
+    pub fn material_mut(&mut self) -> RefMut<'_, Material> {
+        if Rc::strong_count(&self.material) > 1 {
+            let material = self.material.borrow().clone();
+            self.material = material.shared();
         }
+
+        self.material.borrow_mut()
     }
 
     pub fn to_instance(&mut self, data: Vec<InstanceData>, n: usize) -> InstanceMesh {
@@ -94,102 +193,135 @@ impl Mesh {
         new_mesh
     }
 
-    pub fn set_parent(&mut self, parent: Mesh){
-        self.parent = Some(Box::new(parent));
-    }
-
-    pub fn add_child(&mut self, mut child: Mesh){
-        child.set_parent(self.clone());
-        self.children.push(Box::new(child));
+    pub fn set_primitive_mode(&mut self, mode: PrimitiveMode) {
+        self.primitive_mode = mode;
     }
 
     pub fn set_color(&mut self, color: Vec4){
         for vert in self.vertices.iter_mut(){
             vert.color = color;
         }
+        self.mark_vertices_dirty(0..self.vertices.len());
     }
 
     pub fn set_position(&mut self, position: Vec3){
-        self.position = position;
-        for child in self.children.as_mut_slice(){
-            child.set_position(position + child.position)
-        }
+        self.transform.set_position(position);
     }
 
     pub fn add_position(&mut self, position: Vec3){
-        self.position += position;
-        for child in self.children.as_mut_slice(){
-            child.add_position(position)
-        }
+        self.transform.translate(position);
     }
 
     pub fn set_scale(&mut self, scale: Vec3){
-        self.scale = scale;
-        for child in self.children.as_mut_slice(){
-            child.set_scale(scale);
-        }
+        self.transform.set_scale(scale);
     }
 
     pub fn scale(&mut self, scale: Vec3){
-        self.scale *= scale;
-        for child in self.children.as_mut_slice(){
-            child.scale(scale);
-        }
+        self.transform.scale_by(scale);
     }
 
     pub fn set_rotation(&mut self, rotation: Quat){
-        self.rotation = rotation;
-        for child in self.children.as_mut_slice(){
-            child.set_rotation(rotation);
-        }
+        self.transform.set_rotation(rotation);
     }
 
     pub fn rotate(&mut self, rotation: Quat){
-        self.rotation = self.rotation + rotation;
-        for child in self.children.as_mut_slice(){
-            child.rotate(rotation);
-        }
+        self.transform.rotate(rotation);
     }
 
     pub fn setup_mesh(&mut self) {
+        if self.vertices.iter().all(|v| v.tangent == Vec4::ZERO) {
+            self.compute_tangents();
+        }
+
         unsafe {
-            GenVertexArrays(1, &mut self.VAO);
-            GenBuffers(1, &mut self.VBO);
-            GenBuffers(1, &mut self.EBO);
-    
-            BindVertexArray(self.VAO);
+            // fresh objects, clones of this mesh keep drawing the old ones
+            self.VAO = GpuHandle::new(GpuResourceKind::VertexArray);
+            self.EBO = GpuHandle::new(GpuResourceKind::Buffer);
+            self.uploaded = Rc::new(UploadState::new());
     
-            bind_buffer!(ARRAY_BUFFER, self.VBO, self.vertices);
-            bind_buffer!(ELEMENT_ARRAY_BUFFER, self.EBO, self.indices);
-            gen_attrib_pointers!(Vertex, 0 => position: 3, 1 => color: 4, 2 => tex_coords: 2, 3 => normal: 3);
+            BindVertexArray(self.VAO.id());
     
-            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            if self.has_custom_positions() {
+                self.VBO = GpuHandle::null(GpuResourceKind::Buffer);
+            } else {
+                self.VBO = GpuHandle::new(GpuResourceKind::Buffer);
+                bind_buffer!(ARRAY_BUFFER, self.VBO.id(), self.vertices, self.usage.gl_enum());
+                VertexLayout::of_vertex().apply();
+            }
+            BindBuffer(ELEMENT_ARRAY_BUFFER, self.EBO.id());
+            self.write_index_buffer();
+            self.setup_skin();
+            self.setup_morphs();
+
+            let usage = self.usage;
+            for stream in &mut self.vertex_streams {
+                stream.setup(usage);
+            }
 
             BindVertexArray(0);
         }
+
+        self.uploaded.counts.set((self.vertices.len(), self.indices.len()));
+        self.dirty_vertices.set(None);
+        self.dirty_indices.set(false);
     }
     
+    /// Bounds of the vertices before the transform, None when there are none
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().map(|v| v.position))
+    }
+
+    pub fn model_matrix(&self) -> Mat4 {
+        self.transform.matrix()
+    }
+
     pub unsafe fn draw(&self) {
-        let model_matrix = 
-            Mat4::from_translation(self.position) *
-            Mat4::from_quat(self.rotation) *
-            Mat4::from_scale(self.scale);
+        self.draw_with_matrix(self.model_matrix());
+    }
 
-        BindVertexArray(self.VAO);
-        self.shader.use_shader();
+    /// Draws with `model_matrix` in place of the mesh's own transform
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread and `setup_mesh` to have been called
+    pub unsafe fn draw_with_matrix(&self, model_matrix: Mat4) {
+        self.upload_changes();
 
+        BindVertexArray(self.VAO.id());
+        let material = self.material.borrow();
+        material.apply();
 
         // Set uniforms and draw
-        self.shader.uniform_mat4fv(cstr!("model"), &model_matrix.to_cols_array());
-        self.shader.uniform_vec3f(cstr!("pos"), &self.position);
-        
-        BindTexture(TEXTURE_2D, self.texture);
+        material.shader.uniform_mat4fv(cstr!("model"), &model_matrix.to_cols_array());
+        material.shader.uniform_vec3f(cstr!("pos"), &model_matrix.w_axis.truncate());
+        material.shader.uniform_1i(cstr!("receive_shadows"), self.receive_shadows as i32);
+        self.send_morph_uniforms(&material.shader);
 
-        DrawElements(TRIANGLES, self.indices.len() as i32, UNSIGNED_INT, ptr::null());
+        self.draw_elements();
 
         BindVertexArray(0);
         UseProgram(0);
     }
+
+    /// Geometry only, for depth passes where `shader` is already bound
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread and `setup_mesh` to have been called
+    pub unsafe fn draw_depth(&self, shader: &Shader, model_matrix: Mat4) {
+        self.upload_changes();
+        shader.uniform_mat4fv(cstr!("model"), &model_matrix.to_cols_array());
+        self.send_morph_uniforms(shader);
+
+        BindVertexArray(self.VAO.id());
+        self.draw_elements();
+        BindVertexArray(0);
+    }
+
+    // with the vertex array already bound. the count and type come from the buffer,
+    // which a clone may have rewritten since this mesh's indices changed
+    unsafe fn draw_elements(&self) {
+        let (_, count) = self.uploaded.counts.get();
+        DrawElements(self.primitive_mode.gl_enum(), count as i32, self.uploaded.index_type.get(), ptr::null());
+    }
 }
 
 impl Renderer {
@@ -201,6 +333,7 @@ impl Renderer {
         let mesh = Mesh::new(&vertices, &indices);
         
         self.meshes.insert(name.to_owned(), mesh);
+        self.scene.insert(NodeTarget::mesh(name));
         Ok(())
     }
 
@@ -210,6 +343,7 @@ impl Renderer {
         }
 
         self.meshes.insert(name.to_owned(), mesh);
+        self.scene.insert(NodeTarget::mesh(name));
         Ok(())
     }
 
@@ -221,7 +355,10 @@ impl Renderer {
         self.meshes.get(name)
     }
 
+    /// Removes the mesh, its children stay where they are and move up to its parent
     pub fn destroy_mesh(&mut self, name: &str) -> Result<(), String> {
+        self.remove_node(&NodeTarget::mesh(name))?;
+
         if self.meshes.remove(name).is_some() {
             Ok(())
         } else {
@@ -230,13 +367,3 @@ impl Renderer {
     }
 }
 
-
-impl Drop for Mesh {
-    fn drop(&mut self) {
-        unsafe {
-            DeleteVertexArrays(1, &self.VAO);
-            DeleteBuffers(1, &self.EBO);
-            DeleteBuffers(1, &self.VBO);
-        }
-    }
-}
\ No newline at end of file
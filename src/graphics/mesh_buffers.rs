@@ -0,0 +1,273 @@
+use std::{cell::Cell, ffi::c_void, mem::{size_of, size_of_val}, ops::Range, ptr};
+
+use gl::{types::GLenum, *};
+
+use crate::{BufferUsage, IndexFormat, Mesh, Vertex};
+
+// Changing a mesh after `setup_mesh`. Edits through these (and `set_color`) are
+// remembered and uploaded right before the mesh is next drawn, after editing
+// `vertices` or `indices` directly mark them with `mark_vertices_dirty` / `mark_indices_dirty`.
+// Skin and morph target data only goes up with `setup_mesh`. Clones share buffers until
+// one of them calls `setup_mesh`, so their edits show up on each other
+
+// what a mesh's buffers currently hold. clones share it along with the buffers, so
+// one drawing after another rewrote them uses the new count and index type
+#[derive(Debug, PartialEq)]
+pub(crate) struct UploadState {
+    // vertices and indices
+    pub counts: Cell<(usize, usize)>,
+    // UNSIGNED_SHORT or UNSIGNED_INT
+    pub index_type: Cell<GLenum>,
+}
+
+impl UploadState {
+    pub fn new() -> Self {
+        Self { counts: Cell::new((0, 0)), index_type: Cell::new(UNSIGNED_INT) }
+    }
+}
+
+impl Mesh {
+    /// Takes effect the next time the buffers are made or outgrown
+    pub fn set_usage(&mut self, usage: BufferUsage) {
+        self.usage = usage;
+    }
+
+    /// Marks `range` of `vertices` as changed, it's merged with anything marked before
+    pub fn mark_vertices_dirty(&self, range: Range<usize>) {
+        let merged = match self.dirty_vertices.get() {
+            Some((start, end)) => (start.min(range.start), end.max(range.end)),
+            None => (range.start, range.end),
+        };
+        self.dirty_vertices.set(Some(merged));
+    }
+
+    pub fn mark_indices_dirty(&self) {
+        self.dirty_indices.set(true);
+    }
+
+    /// Marks all vertices and indices as changed
+    pub fn mark_dirty(&self) {
+        self.mark_vertices_dirty(0..self.vertices.len());
+        self.mark_indices_dirty();
+    }
+
+    /// Replaces the vertices from `offset` on with `vertices`, growing the mesh if they
+    /// go past its end. An `offset` past the end is clamped to it, appending `vertices`
+    pub fn write_vertices(&mut self, offset: usize, vertices: &[Vertex]) {
+        let offset = offset.min(self.vertices.len());
+        let overlap = (self.vertices.len() - offset).min(vertices.len());
+        self.vertices[offset..offset + overlap].copy_from_slice(&vertices[..overlap]);
+        self.vertices.extend_from_slice(&vertices[overlap..]);
+
+        self.mark_vertices_dirty(offset..offset + vertices.len());
+    }
+
+    /// Replaces all of the vertices, for meshes rebuilt every frame
+    pub fn set_vertices(&mut self, vertices: Vec<Vertex>) {
+        self.vertices = vertices;
+        self.mark_vertices_dirty(0..self.vertices.len());
+    }
+
+    pub fn set_indices(&mut self, indices: Vec<u32>) {
+        self.indices = indices;
+        self.mark_indices_dirty();
+    }
+
+    /// Whether something changed that hasn't been uploaded yet
+    pub fn is_dirty(&self) -> bool {
+        self.dirty_vertices.get().is_some() || self.dirty_indices.get() || self.vertex_streams.iter().any(|s| s.dirty.get())
+    }
+
+    /// Uploads the marked vertices now instead of at the next draw
+    pub fn update_vertices(&self) {
+        if let Some((start, end)) = self.dirty_vertices.take() {
+            unsafe { self.upload_vertices(start..end) };
+        }
+    }
+
+    /// Uploads the indices now instead of at the next draw
+    pub fn update_indices(&self) {
+        if self.dirty_indices.take() {
+            unsafe { self.upload_indices() };
+        }
+    }
+
+    pub(crate) unsafe fn upload_changes(&self) {
+        self.update_vertices();
+        self.update_indices();
+        for stream in &self.vertex_streams {
+            stream.upload_changes(self.usage);
+        }
+    }
+
+    // changed ranges go up with `BufferSubData`. when the whole buffer is replaced, or the
+    // count changed, the old storage is orphaned first so the driver doesn't have to wait
+    // for draws still reading it. indices always go up whole
+    unsafe fn upload_vertices(&self, range: Range<usize>) {
+        if self.VBO.is_null() {
+            return;
+        }
+
+        let len = self.vertices.len();
+        let (uploaded_len, indices_len) = self.uploaded.counts.get();
+        let stride = size_of::<Vertex>();
+
+        BindBuffer(ARRAY_BUFFER, self.VBO.id());
+        if len != uploaded_len || (range.start == 0 && range.end >= len) {
+            BufferData(ARRAY_BUFFER, (len * stride) as isize, ptr::null(), self.usage.gl_enum());
+            if len > 0 {
+                BufferSubData(ARRAY_BUFFER, 0, (len * stride) as isize, self.vertices.as_ptr() as *const c_void);
+            }
+            self.uploaded.counts.set((len, indices_len));
+        } else {
+            let end = range.end.min(len);
+            if range.start < end {
+                BufferSubData(
+                    ARRAY_BUFFER,
+                    (range.start * stride) as isize,
+                    ((end - range.start) * stride) as isize,
+                    self.vertices[range.start..end].as_ptr() as *const c_void,
+                );
+            }
+        }
+        BindBuffer(ARRAY_BUFFER, 0);
+    }
+
+    unsafe fn upload_indices(&self) {
+        if self.EBO.is_null() {
+            return;
+        }
+
+        let len = self.indices.len();
+        let (vertices_len, _) = self.uploaded.counts.get();
+
+        // the index buffer binding belongs to the vao
+        BindVertexArray(self.VAO.id());
+        BindBuffer(ELEMENT_ARRAY_BUFFER, self.EBO.id());
+        self.write_index_buffer();
+        BindVertexArray(0);
+
+        self.uploaded.counts.set((vertices_len, len));
+    }
+
+    /// Whether the indices go up as `u16`, with `index_format` and the current indices
+    pub fn uses_16_bit_indices(&self) -> bool {
+        let fits = self.indices.iter().all(|&i| i <= u16::MAX as u32);
+        match self.index_format {
+            IndexFormat::Auto | IndexFormat::U16 => fits,
+            IndexFormat::U32 => false,
+        }
+    }
+
+    // fills the bound ELEMENT_ARRAY_BUFFER, orphaning what it had
+    pub(crate) unsafe fn write_index_buffer(&self) {
+        let usage = self.usage.gl_enum();
+
+        if self.index_format == IndexFormat::U16 {
+            if let Some(index) = self.indices.iter().find(|&&i| i > u16::MAX as u32) {
+                eprintln!("Mesh index {} doesn't fit in IndexFormat::U16, uploading 32 bit indices instead", index);
+            }
+        }
+
+        if self.uses_16_bit_indices() {
+            let indices: Vec<u16> = self.indices.iter().map(|&i| i as u16).collect();
+            let size = size_of_val(indices.as_slice()) as isize;
+            BufferData(ELEMENT_ARRAY_BUFFER, size, ptr::null(), usage);
+            if !indices.is_empty() {
+                BufferSubData(ELEMENT_ARRAY_BUFFER, 0, size, indices.as_ptr() as *const c_void);
+            }
+            self.uploaded.index_type.set(UNSIGNED_SHORT);
+        } else {
+            let size = size_of_val(self.indices.as_slice()) as isize;
+            BufferData(ELEMENT_ARRAY_BUFFER, size, ptr::null(), usage);
+            if !self.indices.is_empty() {
+                BufferSubData(ELEMENT_ARRAY_BUFFER, 0, size, self.indices.as_ptr() as *const c_void);
+            }
+            self.uploaded.index_type.set(UNSIGNED_INT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec3, Vec2, Vec3, Vec4};
+
+    use super::*;
+    use crate::{Material, Shader};
+
+    // vertex i sits at x = i
+    fn vertex(x: f32) -> Vertex {
+        Vertex::new(vec3(x, 0.0, 0.0), Vec4::ONE, Vec2::ZERO, Vec3::Z)
+    }
+
+    fn line(count: usize) -> Mesh {
+        let vertices: Vec<Vertex> = (0..count).map(|i| vertex(i as f32)).collect();
+        Mesh::with_material(&vertices, &[], Material::new(Shader { id: 0 }).shared())
+    }
+
+    fn xs(mesh: &Mesh) -> Vec<f32> {
+        mesh.vertices.iter().map(|v| v.position.x).collect()
+    }
+
+    #[test]
+    fn dirty_ranges_merge_until_uploaded() {
+        let mesh = line(10);
+        assert!(!mesh.is_dirty());
+
+        mesh.mark_vertices_dirty(2..4);
+        mesh.mark_vertices_dirty(7..9);
+        assert_eq!(mesh.dirty_vertices.get(), Some((2, 9)));
+        mesh.mark_vertices_dirty(0..1);
+        assert_eq!(mesh.dirty_vertices.get(), Some((0, 9)));
+        assert!(mesh.is_dirty());
+
+        // without buffers there's nothing to upload, but the range is used up
+        mesh.update_vertices();
+        assert!(!mesh.is_dirty());
+    }
+
+    #[test]
+    fn write_vertices_overwrites_then_grows() {
+        let mut mesh = line(4);
+        mesh.write_vertices(2, &[vertex(10.0), vertex(11.0), vertex(12.0)]);
+
+        assert_eq!(xs(&mesh), vec![0.0, 1.0, 10.0, 11.0, 12.0]);
+        assert_eq!(mesh.dirty_vertices.get(), Some((2, 5)));
+    }
+
+    #[test]
+    fn write_vertices_past_the_end_appends() {
+        let mut mesh = line(3);
+        mesh.write_vertices(10, &[vertex(10.0), vertex(11.0)]);
+
+        assert_eq!(xs(&mesh), vec![0.0, 1.0, 2.0, 10.0, 11.0]);
+        assert_eq!(mesh.dirty_vertices.get(), Some((3, 5)));
+    }
+
+    #[test]
+    fn set_vertices_marks_everything() {
+        let mut mesh = line(3);
+        mesh.set_vertices((0..6).map(|i| vertex(i as f32)).collect());
+        assert_eq!(mesh.dirty_vertices.get(), Some((0, 6)));
+
+        mesh.set_indices(vec![0, 1, 2]);
+        assert!(mesh.dirty_indices.get());
+    }
+
+    #[test]
+    fn index_formats() {
+        let mut mesh = line(3);
+        mesh.indices = vec![0, 1, 2];
+        for (format, expected) in [(IndexFormat::Auto, true), (IndexFormat::U16, true), (IndexFormat::U32, false)] {
+            mesh.index_format = format;
+            assert_eq!(mesh.uses_16_bit_indices(), expected);
+        }
+
+        // too big for u16 whatever was asked for
+        mesh.indices.push(70_000);
+        for format in [IndexFormat::Auto, IndexFormat::U16, IndexFormat::U32] {
+            mesh.index_format = format;
+            assert!(!mesh.uses_16_bit_indices());
+        }
+    }
+}
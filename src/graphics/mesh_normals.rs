@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+
+use glam::{Vec2, Vec3};
+
+use crate::{Mesh, PrimitiveMode, Vertex};
+
+// Normal, tangent and vertex sharing utilities. They only touch `vertices` and
+// `indices`, call `setup_mesh` afterwards to upload the result. Strips and fans are
+// read as the triangles they make, the ones that rebuild `indices` turn them into a
+// plain `Triangles` list. Lines and points have no faces, the ones that rebuild the
+// vertices leave them alone
+impl Mesh {
+    /// Smooth normals, the area weighted average of the triangles around each vertex
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vec3::ZERO; self.vertices.len()];
+
+        for (a, b, c) in self.triangles() {
+            // not normalized, so bigger triangles count more
+            let normal = self.face_normal(a, b, c);
+            for i in [a, b, c] {
+                normals[i] += normal;
+            }
+        }
+
+        for (vert, normal) in self.vertices.iter_mut().zip(normals) {
+            vert.normal = normal.normalize_or_zero();
+        }
+    }
+
+    /// Faceted look, every triangle gets its own vertices pointing along its normal
+    pub fn compute_flat_normals(&mut self) {
+        if !self.primitive_mode.is_triangles() {
+            return;
+        }
+
+        self.split_vertices();
+
+        // split, so each run of three vertices is one triangle
+        for tri in self.vertices.chunks_exact_mut(3) {
+            let normal = (tri[1].position - tri[0].position).cross(tri[2].position - tri[0].position).normalize_or_zero();
+            for vert in tri {
+                vert.normal = normal;
+            }
+        }
+    }
+
+    /// Smooth normals across edges where the triangles meet at less than `threshold`
+    /// (radians) and hard ones elsewhere, vertices on hard edges get split. Corners
+    /// are matched by exact position, `weld_vertices` first if the mesh is split up
+    pub fn compute_normals_with_angle(&mut self, threshold: f32) {
+        if !self.primitive_mode.is_triangles() {
+            return;
+        }
+
+        let triangles = self.triangles();
+        let faces: Vec<Vec3> = triangles.iter().map(|&(a, b, c)| self.face_normal(a, b, c)).collect();
+        let units: Vec<Vec3> = faces.iter().map(|n| n.normalize_or_zero()).collect();
+        let min_cos = threshold.cos();
+
+        // triangles touching each position
+        let mut around: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+        for (t, &(a, b, c)) in triangles.iter().enumerate() {
+            for i in [a, b, c] {
+                around.entry(position_key(self.vertices[i].position)).or_default().push(t);
+            }
+        }
+
+        let mut vertices = Vec::with_capacity(self.vertices.len());
+        let mut sources = Vec::with_capacity(self.vertices.len());
+        let mut indices = Vec::with_capacity(triangles.len() * 3);
+        // corners with the same vertex and normal keep sharing one
+        let mut shared: HashMap<(usize, [u32; 3]), u32> = HashMap::new();
+
+        for (t, &(a, b, c)) in triangles.iter().enumerate() {
+            for i in [a, b, c] {
+                let normal = around[&position_key(self.vertices[i].position)].iter()
+                    .filter(|&&s| s == t || units[t].dot(units[s]) >= min_cos)
+                    .map(|&s| faces[s])
+                    .sum::<Vec3>()
+                    .normalize_or_zero();
+
+                let index = *shared.entry((i, position_key(normal))).or_insert_with(|| {
+                    vertices.push(Vertex { normal, ..self.vertices[i] });
+                    sources.push(i);
+                    vertices.len() as u32 - 1
+                });
+                indices.push(index);
+            }
+        }
+
+        self.vertices = vertices;
+        self.indices = indices;
+        self.primitive_mode = PrimitiveMode::Triangles;
+        self.remap_vertex_data(&sources);
+    }
+
+    /// Gives every triangle corner its own vertex, so nothing is shared between triangles
+    pub fn split_vertices(&mut self) {
+        if !self.primitive_mode.is_triangles() {
+            return;
+        }
+
+        let sources: Vec<usize> = self.triangles().into_iter().flat_map(|(a, b, c)| [a, b, c]).collect();
+
+        self.vertices = sources.iter().map(|&i| self.vertices[i]).collect();
+        self.indices = (0..sources.len() as u32).collect();
+        self.primitive_mode = PrimitiveMode::Triangles;
+        self.remap_vertex_data(&sources);
+    }
+
+    /// Merges vertices whose position, uv and color are within `tolerance` of each
+    /// other, keeping the first one's normal and tangent. Triangles that collapse
+    /// are dropped. Nearby values can still land on either side of a grid cell
+    pub fn weld_vertices(&mut self, tolerance: f32) {
+        let tolerance = tolerance.max(f32::EPSILON);
+        let cell = |v: f32| (v / tolerance).round() as i64;
+
+        let mut vertices = Vec::with_capacity(self.vertices.len());
+        let mut sources = Vec::with_capacity(self.vertices.len());
+        let mut remap = Vec::with_capacity(self.vertices.len());
+        let mut merged: HashMap<[i64; 9], u32> = HashMap::new();
+
+        for (i, vert) in self.vertices.iter().enumerate() {
+            let (p, uv, c) = (vert.position, vert.tex_coords, vert.color);
+            let key = [cell(p.x), cell(p.y), cell(p.z), cell(uv.x), cell(uv.y), cell(c.x), cell(c.y), cell(c.z), cell(c.w)];
+
+            remap.push(*merged.entry(key).or_insert_with(|| {
+                vertices.push(*vert);
+                sources.push(i);
+                vertices.len() as u32 - 1
+            }));
+        }
+
+        let mut indices = Vec::with_capacity(self.indices.len());
+        if self.primitive_mode.is_triangles() {
+            for (a, b, c) in self.triangles() {
+                let (a, b, c) = (remap[a], remap[b], remap[c]);
+                if a != b && b != c && a != c {
+                    indices.extend([a, b, c]);
+                }
+            }
+            self.primitive_mode = PrimitiveMode::Triangles;
+        } else {
+            indices.extend(self.indices.iter().filter_map(|&i| remap.get(i as usize)));
+        }
+
+        self.vertices = vertices;
+        self.indices = indices;
+        self.remap_vertex_data(&sources);
+    }
+
+    /// Box projected uvs for meshes that came without any, each vertex is projected
+    /// along the axis its normal points the most towards
+    pub fn generate_uvs(&mut self) {
+        let Some(bounds) = self.bounds() else {
+            return;
+        };
+        let size = bounds.size().max_element().max(f32::EPSILON);
+
+        for vert in &mut self.vertices {
+            let p = (vert.position - bounds.min) / size;
+            let n = vert.normal.abs();
+            vert.tex_coords = if n.x >= n.y && n.x >= n.z {
+                Vec2::new(p.z, p.y)
+            } else if n.y >= n.z {
+                Vec2::new(p.x, p.z)
+            } else {
+                Vec2::new(p.x, p.y)
+            };
+        }
+    }
+
+    /// Per vertex tangents from the triangles' uv directions, for normal mapping.
+    /// `setup_mesh` does this on its own when none were given, call it again after
+    /// changing the normals
+    pub fn compute_tangents(&mut self) {
+        let mut tangents = vec![Vec3::ZERO; self.vertices.len()];
+        let mut bitangents = vec![Vec3::ZERO; self.vertices.len()];
+
+        for (a, b, c) in self.triangles() {
+            let (v0, v1, v2) = (&self.vertices[a], &self.vertices[b], &self.vertices[c]);
+
+            let e1 = v1.position - v0.position;
+            let e2 = v2.position - v0.position;
+            let d1: Vec2 = v1.tex_coords - v0.tex_coords;
+            let d2: Vec2 = v2.tex_coords - v0.tex_coords;
+
+            let det = d1.x * d2.y - d2.x * d1.y;
+            if det.abs() < f32::EPSILON {
+                continue;
+            }
+            let r = 1.0 / det;
+            let t = (e1 * d2.y - e2 * d1.y) * r;
+            let bt = (e2 * d1.x - e1 * d2.x) * r;
+
+            for i in [a, b, c] {
+                tangents[i] += t;
+                bitangents[i] += bt;
+            }
+        }
+
+        for (i, vert) in self.vertices.iter_mut().enumerate() {
+            let n = vert.normal.normalize_or_zero();
+            // gram-schmidt against the normal
+            let t = (tangents[i] - n * n.dot(tangents[i])).normalize_or_zero();
+            let w = if n.cross(t).dot(bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+            vert.tangent = t.extend(w);
+        }
+    }
+
+    /// Bitangent of each vertex, rebuilt from its normal and tangent the same way the shaders do
+    pub fn bitangents(&self) -> Vec<Vec3> {
+        self.vertices.iter()
+            .map(|v| v.normal.cross(v.tangent.truncate()) * v.tangent.w)
+            .collect()
+    }
+
+    // keeps per vertex data stored outside `vertices` in step with them, `sources`
+    // being the old vertex each new one was made from
+    fn remap_vertex_data(&mut self, sources: &[usize]) {
+        if !self.skin_vertices.is_empty() {
+            self.skin_vertices = sources.iter().map(|&i| self.skin_vertices[i]).collect();
+        }
+        for target in &mut self.morph_targets {
+            let remap = |deltas: &Vec<Vec3>| -> Vec<Vec3> {
+                if deltas.is_empty() {
+                    return Vec::new();
+                }
+                sources.iter().map(|&i| deltas.get(i).copied().unwrap_or(Vec3::ZERO)).collect()
+            };
+            target.positions = remap(&target.positions);
+            target.normals = remap(&target.normals);
+        }
+        for stream in &mut self.vertex_streams {
+            stream.remap(sources);
+        }
+    }
+
+    // triangles as vertex indices, skipping any that point past the vertices. strips
+    // flip every other triangle to keep the winding, lines and points have none
+    fn triangles(&self) -> Vec<(usize, usize, usize)> {
+        let len = self.vertices.len();
+        let index = |i: usize| self.indices[i] as usize;
+        let count = self.indices.len();
+
+        let triangles: Vec<(usize, usize, usize)> = match self.primitive_mode {
+            PrimitiveMode::Triangles => (0..count / 3).map(|t| (index(t * 3), index(t * 3 + 1), index(t * 3 + 2))).collect(),
+            PrimitiveMode::TriangleStrip => (2..count)
+                .map(|i| if i % 2 == 0 { (index(i - 2), index(i - 1), index(i)) } else { (index(i - 1), index(i - 2), index(i)) })
+                .collect(),
+            PrimitiveMode::TriangleFan => (2..count).map(|i| (index(0), index(i - 1), index(i))).collect(),
+            _ => Vec::new(),
+        };
+
+        triangles.into_iter().filter(|&(a, b, c)| a < len && b < len && c < len).collect()
+    }
+
+    // unnormalized, its length is twice the triangle's area
+    fn face_normal(&self, a: usize, b: usize, c: usize) -> Vec3 {
+        let (p0, p1, p2) = (self.vertices[a].position, self.vertices[b].position, self.vertices[c].position);
+        (p1 - p0).cross(p2 - p0)
+    }
+}
+
+fn position_key(v: Vec3) -> [u32; 3] {
+    // +0.0 and -0.0 are the same place
+    (v + Vec3::ZERO).to_array().map(f32::to_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec3, Vec4};
+
+    use super::*;
+    use crate::{Material, Shader};
+
+    // two unit triangles meeting at a right angle along the z axis, one facing +y and one +x
+    fn bent_quad() -> Mesh {
+        let positions = [vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0), vec3(1.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0)];
+        let vertices: Vec<Vertex> = positions.iter().map(|&p| Vertex::new(p, Vec4::ONE, Vec2::ZERO, Vec3::ZERO)).collect();
+
+        Mesh::with_material(&vertices, &[0, 1, 2, 0, 3, 1], Material::new(Shader { id: 0 }).shared())
+    }
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn smooth_normals_average_the_faces() {
+        let mut mesh = bent_quad();
+        mesh.compute_normals();
+
+        let ridge = vec3(1.0, 1.0, 0.0).normalize();
+        assert_near(mesh.vertices[0].normal, ridge);
+        assert_near(mesh.vertices[1].normal, ridge);
+        assert_near(mesh.vertices[2].normal, Vec3::Y);
+        assert_near(mesh.vertices[3].normal, Vec3::X);
+    }
+
+    #[test]
+    fn flat_normals_split_every_triangle() {
+        let mut mesh = bent_quad();
+        mesh.compute_flat_normals();
+
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 3, 4, 5]);
+        for vert in &mesh.vertices[..3] {
+            assert_near(vert.normal, Vec3::Y);
+        }
+        for vert in &mesh.vertices[3..] {
+            assert_near(vert.normal, Vec3::X);
+        }
+    }
+
+    #[test]
+    fn angle_threshold_picks_hard_or_smooth_edges() {
+        let mut hard = bent_quad();
+        hard.compute_normals_with_angle(60f32.to_radians());
+        // the two ridge vertices are split, one for each face
+        assert_eq!(hard.vertices.len(), 6);
+        for (t, face) in [Vec3::Y, Vec3::X].into_iter().enumerate() {
+            for &i in &hard.indices[t * 3..t * 3 + 3] {
+                assert_near(hard.vertices[i as usize].normal, face);
+            }
+        }
+
+        let mut smooth = bent_quad();
+        smooth.compute_normals_with_angle(120f32.to_radians());
+        assert_eq!(smooth.vertices.len(), 4);
+        assert_near(smooth.vertices[0].normal, vec3(1.0, 1.0, 0.0).normalize());
+    }
+
+    #[test]
+    fn welding_undoes_a_split() {
+        let mut mesh = bent_quad();
+        mesh.split_vertices();
+        mesh.vertices[3].position += Vec3::splat(1e-5);
+        mesh.weld_vertices(1e-3);
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 3, 1]);
+    }
+
+    #[test]
+    fn welding_drops_collapsed_triangles() {
+        let mut mesh = bent_quad();
+        mesh.vertices[3].position = mesh.vertices[0].position;
+        mesh.weld_vertices(1e-3);
+
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn lines_are_left_alone() {
+        let mut mesh = bent_quad();
+        mesh.primitive_mode = PrimitiveMode::Lines;
+        let before = (mesh.vertices.clone(), mesh.indices.clone());
+
+        mesh.compute_flat_normals();
+        mesh.compute_normals_with_angle(1.0);
+        mesh.split_vertices();
+
+        assert_eq!((mesh.vertices, mesh.indices), before);
+    }
+}
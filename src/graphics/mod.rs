@@ -2,6 +2,9 @@ mod renderer;
 mod camera;
 mod shader;
 mod mesh;
+mod mesh_normals;
+mod mesh_buffers;
+mod vertex_layout;
 mod test_scene;
 mod SHADERS;
 mod r#macro;
@@ -9,9 +12,28 @@ mod instance_mesh;
 mod texture;
 mod shader_types;
 mod light;
+mod light_buffer;
+mod material;
+mod cubemap;
+mod environment;
 mod font;
 mod model;
+mod gltf_model;
 mod particle;
+mod screenshot;
+mod render_target;
+mod post_process;
+mod shadow;
+mod gpu;
+mod scene;
+mod transform;
+mod bounds;
+mod skin;
+mod animation;
+mod morph;
+mod debug_draw;
+mod grid;
+mod gizmo;
 
 pub use shader_types::*;
 pub use texture::*;
@@ -24,6 +46,24 @@ pub use SHADERS::*;
 pub use r#macro::*;
 pub use instance_mesh::*;
 pub use light::*;
+pub use light_buffer::*;
+pub use material::*;
+pub use cubemap::*;
+pub use environment::*;
 pub use font::*;
 pub use model::*;
-pub use particle::*;
\ No newline at end of file
+pub use particle::*;
+pub use screenshot::*;
+pub use render_target::*;
+pub use post_process::*;
+pub use shadow::*;
+pub use gpu::*;
+pub use scene::*;
+pub use transform::*;
+pub use bounds::*;pub use skin::*;
+pub use animation::*;
+pub use morph::*;
+pub use vertex_layout::*;
+pub use debug_draw::*;
+pub use grid::*;
+pub use gizmo::*;
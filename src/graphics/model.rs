@@ -1,107 +1,160 @@
-use std::path::Path;
+use std::{collections::HashMap, path::{Path, PathBuf}};
 
-use glam::{vec2, vec3, vec4, Vec3, Vec4};
+use glam::{vec2, vec3, vec4, Mat4, Quat, Vec2, Vec3, Vec4};
 use tobj::LoadOptions;
 
-use crate::{EventLoop, Mesh, Renderer, Texture, Vertex};
+use crate::{load_texture, Aabb, AnimationClip, Animator, Error, EventLoop, GpuHandle, Material, Mesh, NodeTarget, Renderer, ShaderType, SharedMaterial, Skin, Texture, TextureSlot, Transform, Vertex};
+
+/// A node of a model's own hierarchy, like the ones glTF files come with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelNode {
+    pub name: String,
+    /// Relative to the parent node
+    pub transform: Transform,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    /// Indices into `Model::meshes`
+    pub meshes: Vec<usize>,
+    /// Morph target weights for the node's meshes, empty when they have no targets
+    pub weights: Vec<f32>,
+}
 
 #[derive(Default)]
 pub struct Model {
     pub meshes: Vec<Mesh>,
+    /// Empty for OBJ files, meshes no node points to only use their own transform
+    pub nodes: Vec<ModelNode>,
+    /// Applied on top of every mesh's own transform
+    pub transform: Transform,
+    pub skins: Vec<Skin>,
+    pub animations: Vec<AnimationClip>,
+    pub animator: Animator,
     pub loaded_textures: Vec<Texture>,
+    /// Problems that didn't stop loading, like a missing .mtl file or texture.
+    /// Whatever they affected falls back to the default material
+    pub warnings: Vec<Error>,
 }
 
 impl Model {
-    pub fn new(path: &str) -> Self {
+    /// Loads an OBJ, or a glTF for `.gltf` and `.glb` paths
+    pub fn new(path: &str) -> Result<Self, Error> {
         let mut model = Model::default();
-        model.load(path);
 
-        model
+        let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+        match extension.as_str() {
+            "obj" => model.load(path)?,
+            "gltf" | "glb" => model.load_gltf(path)?,
+            _ => return Err(Error::UnsupportedFormat { path: path.into(), format: format!("'.{}' models", extension) }),
+        }
+
+        Ok(model)
     }
 
-    pub fn extract_mesh(&mut self, path: &str, u: usize) -> Mesh {
+    /// Loads the OBJ's meshes into the model and returns a copy of the `u`-th one
+    pub fn extract_mesh(&mut self, path: &str, u: usize) -> Result<Mesh, Error> {
         let path = Path::new(path);
-        
-        let obj = tobj::load_obj(path, &LoadOptions::default()).expect("Failed to load OBJ file");
-        let (models, _) = obj;
+        let (models, _) = tobj::load_obj(path, &obj_load_options()).map_err(|e| Error::from_obj(path, e))?;
 
         for model in models {
-            let mesh = &model.mesh;
-            let num_vertices = mesh.positions.len() / 3;
-            let indices: Vec<u32> = mesh.indices.clone();
-
-            let mut vertices = Vec::with_capacity(num_vertices);
-
-            let (p, n, t, c) = (&mesh.positions, &mesh.normals, &mesh.texcoords, &mesh.vertex_color);
-
-            for i in 0..num_vertices {
-                let pos = vec3(p[i*3], p[i*3+1], p[i*3+2]);
-                let tex_coords = vec2(t[i*2], t[i*2+1]);
-                let normal = if n.len() >= (i + 1) * 3 {
-                    vec3(n[i * 3], n[i * 3 + 1], n[i * 3 + 2])
-                } else {
-                    Vec3::ZERO
-                };
-                let color = if c.len() >= (i + 1) * 3 {
-                    vec4(c[i * 3], c[i * 3 + 1], c[i * 3 + 2], 1.0)
-                } else {
-                    Vec4::ONE
-                };
-                vertices.push(
-                    Vertex::new(pos, color, tex_coords, normal)
-                );
-            }
-
-            
-            self.meshes.push(Mesh::new(&vertices, &indices));
+            self.meshes.push(obj_mesh(&model.mesh));
         }
 
-        self.meshes[u].clone()
+        self.meshes.get(u).cloned().ok_or_else(|| Error::Parse {
+            path: path.to_owned(),
+            reason: format!("there's no mesh {}, the model has {}", u, self.meshes.len()),
+        })
     }
 
-    pub fn load(&mut self, path: &str) {
+    /// Loads an OBJ, meshes get `Full` materials from its .mtl file when it has one.
+    /// Textures the .mtl points to are looked up next to the OBJ file. A broken .mtl or a
+    /// texture that can't be loaded ends up in `warnings`. Missing normals and uvs are generated
+    pub fn load(&mut self, path: &str) -> Result<(), Error> {
         let path = Path::new(path);
 
-        let obj = tobj::load_obj(path, &LoadOptions::default()).expect("Failed to load OBJ file");
-        let (models, _) = obj;
+        let (models, mtl) = tobj::load_obj(path, &obj_load_options()).map_err(|e| Error::from_obj(path, e))?;
+
+        // no mtllib line gives an empty list, one naming a file that's missing or broken
+        // is a warning and the meshes keep their default materials
+        let mtl = mtl.unwrap_or_else(|e| {
+            self.warnings.push(Error::from_mtl(path, e));
+            Vec::new()
+        });
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut textures: HashMap<PathBuf, GpuHandle> = HashMap::new();
+        let materials: Vec<SharedMaterial> = mtl.iter()
+            .map(|m| self.obj_material(m, dir, &mut textures).shared())
+            .collect();
 
         for model in models {
             let mesh = &model.mesh;
-            let num_vertices = mesh.positions.len() / 3;
-            let indices: Vec<u32> = mesh.indices.clone();
-
-            let mut vertices = Vec::with_capacity(num_vertices);
-
-            let (p, n, t, c) = (&mesh.positions, &mesh.normals, &mesh.texcoords, &mesh.vertex_color);
-
-            for i in 0..num_vertices {
-                let pos = vec3(p[i*3], p[i*3+1], p[i*3+2]);
-                let tex_coords = vec2(t[i*2], t[i*2+1]);
-                let normal = if n.len() >= (i + 1) * 3 {
-                    vec3(n[i * 3], n[i * 3 + 1], n[i * 3 + 2])
-                } else {
-                    Vec3::ZERO
-                };
-                let color = if c.len() >= (i + 1) * 3 {
-                    vec4(c[i * 3], c[i * 3 + 1], c[i * 3 + 2], 1.0)
-                } else {
-                    Vec4::ONE
-                };
-                vertices.push(
-                    Vertex::new(pos, color, tex_coords, normal)
-                );
+            if mesh.indices.iter().any(|&i| i as usize * 3 >= mesh.positions.len()) {
+                return Err(Error::Parse { path: path.to_owned(), reason: format!("'{}' has faces pointing past its vertices", model.name) });
             }
 
-            let mut final_mesh = Mesh::new(&vertices, &indices);
+            let mut final_mesh = obj_mesh(mesh);
 
             for face in &mut final_mesh.indices.chunks_mut(6) {
                 face.reverse();
             }
 
+            if let Some(material) = mesh.material_id.and_then(|id| materials.get(id)) {
+                final_mesh.set_material(material.clone());
+            }
+
             final_mesh.setup_mesh();
 
             self.meshes.push(final_mesh);
         }
+
+        Ok(())
+    }
+
+    fn obj_material(&mut self, mtl: &tobj::Material, dir: &Path, textures: &mut HashMap<PathBuf, GpuHandle>) -> Material {
+        let mut material = Material::from_shader_type(&ShaderType::Full);
+
+        // `Tr` is the inverse of `d`, some exporters only write that one
+        let alpha = mtl.dissolve
+            .or_else(|| mtl.unknown_param.get("Tr").and_then(|t| t.trim().parse::<f32>().ok()).map(|t| 1.0 - t))
+            .unwrap_or(1.0);
+        let diffuse = mtl.diffuse.map_or(Vec3::ONE, Vec3::from);
+        material.set_uniform("base_color", diffuse.extend(alpha));
+
+        if let Some(ambient) = mtl.ambient {
+            material.set_uniform("ambient_color", Vec3::from(ambient));
+        }
+        if let Some(specular) = mtl.specular {
+            material.set_uniform("specular_strength", 1.0);
+            material.set_uniform("specular_color", Vec3::from(specular));
+        }
+        if let Some(shininess) = mtl.shininess {
+            material.set_uniform("shininess", shininess.max(1.0));
+        }
+
+        let maps = [
+            (TextureSlot::Albedo, &mtl.diffuse_texture),
+            (TextureSlot::Normal, &mtl.normal_texture),
+            (TextureSlot::Specular, &mtl.specular_texture),
+        ];
+        for (slot, map) in maps {
+            let Some(file) = map.as_deref().and_then(|m| resolve_texture_path(dir, m)) else {
+                continue;
+            };
+
+            if let Some(texture) = textures.get(&file) {
+                material.set_texture(slot, texture.clone());
+                continue;
+            }
+            match unsafe { load_texture(&file.to_string_lossy()) } {
+                Ok(texture) => {
+                    self.loaded_textures.push(Texture::Loaded(texture.clone()));
+                    textures.insert(file, texture.clone());
+                    material.set_texture(slot, texture);
+                }
+                Err(e) => self.warnings.push(e),
+            }
+        }
+
+        material
     }
 
     pub fn load_texture(&mut self, path: &str) -> Texture {
@@ -110,17 +163,194 @@ impl Model {
         texture
     }
 
+    /// Gives every mesh of the model the same material
+    pub fn set_material(&mut self, material: SharedMaterial) {
+        for mesh in &mut self.meshes {
+            mesh.set_material(material.clone());
+        }
+    }
+
+    /// Switches every mesh to one of the built in shaders, each keeps its textures
+    pub fn set_shader_type(&mut self, shader_type: &ShaderType) {
+        for mesh in &mut self.meshes {
+            mesh.set_shader_type(shader_type);
+        }
+    }
+
+    pub fn set_position(&mut self, position: Vec3) {
+        self.transform.set_position(position);
+    }
+
+    pub fn add_position(&mut self, position: Vec3) {
+        self.transform.translate(position);
+    }
+
+    pub fn set_rotation(&mut self, rotation: Quat) {
+        self.transform.set_rotation(rotation);
+    }
+
+    pub fn rotate(&mut self, rotation: Quat) {
+        self.transform.rotate(rotation);
+    }
+
+    pub fn set_scale(&mut self, scale: Vec3) {
+        self.transform.set_scale(scale);
+    }
+
+    pub fn find_node(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|n| n.name == name)
+    }
+
+    /// Every node's matrix with its parents applied, in the model's own space
+    pub fn node_matrices(&self) -> Vec<Mat4> {
+        let mut matrices: Vec<Option<Mat4>> = vec![None; self.nodes.len()];
+
+        for i in 0..self.nodes.len() {
+            // walk up to the closest parent that's already done
+            let mut chain = vec![i];
+            let mut current = self.nodes[i].parent;
+            while let Some(p) = current {
+                if matrices[p].is_some() {
+                    break;
+                }
+                chain.push(p);
+                current = self.nodes[p].parent;
+            }
+
+            let mut matrix = current.and_then(|p| matrices[p]).unwrap_or(Mat4::IDENTITY);
+            for &node in chain.iter().rev() {
+                if let Some(done) = matrices[node] {
+                    matrix = done;
+                    continue;
+                }
+                matrix *= self.nodes[node].transform.matrix();
+                matrices[node] = Some(matrix);
+            }
+        }
+
+        matrices.into_iter().map(|m| m.unwrap()).collect()
+    }
+
+    // each mesh's matrix in the model's own space, through the node pointing to it.
+    // skinned meshes are placed by their joints instead
+    fn local_mesh_matrices(&self) -> Vec<Mat4> {
+        let mut matrices: Vec<Mat4> = self.meshes.iter().map(|m| m.model_matrix()).collect();
+
+        for (node, world) in self.nodes.iter().zip(self.node_matrices()) {
+            for &mesh in node.meshes.iter().filter(|&&m| self.meshes[m].skin.is_none()) {
+                matrices[mesh] = world * matrices[mesh];
+            }
+        }
+
+        matrices
+    }
+
+    /// Each mesh's world matrix, the root transform, nodes and its own transform combined
+    pub fn mesh_matrices(&self) -> Vec<Mat4> {
+        self.mesh_matrices_with(self.transform.matrix())
+    }
+
+    /// Same as `mesh_matrices` with `root` in place of `transform`, like a scene graph parent applied to it
+    pub fn mesh_matrices_with(&self, root: Mat4) -> Vec<Mat4> {
+        self.local_mesh_matrices().into_iter().map(|m| root * m).collect()
+    }
+
+    /// Bounds of all the meshes in the model's own space, None when it has no vertices
+    pub fn local_bounds(&self) -> Option<Aabb> {
+        self.meshes.iter().zip(self.local_mesh_matrices())
+            .filter_map(|(mesh, matrix)| Some(mesh.bounds()?.transformed(matrix)))
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// Bounds with `transform` applied, in the world unless the model has a scene graph parent
+    pub fn bounds(&self) -> Option<Aabb> {
+        Some(self.local_bounds()?.transformed(self.transform.matrix()))
+    }
+
+    /// Each skin's joint matrices for the current pose
+    pub fn skin_matrices(&self) -> Vec<Vec<Mat4>> {
+        let nodes = self.node_matrices();
+        self.skins.iter().map(|s| s.joint_matrices(&nodes)).collect()
+    }
+
     pub unsafe fn draw(&self) {
-        for mesh in &self.meshes {
-            mesh.draw();
+        self.draw_with_matrix(self.transform.matrix());
+    }
+
+    /// Draws with `root` in place of the model's own transform
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn draw_with_matrix(&self, root: Mat4) {
+        let skins = self.skin_matrices();
+
+        for (mesh, matrix) in self.meshes.iter().zip(self.mesh_matrices_with(root)) {
+            match mesh.skin.and_then(|s| skins.get(s)) {
+                Some(joints) => mesh.draw_skinned(matrix, joints),
+                None => mesh.draw_with_matrix(matrix),
+            }
         }
-    } 
+    }
+}
+
+
+// one index for every attribute, so normals and uvs line up with the positions
+fn obj_load_options() -> LoadOptions {
+    LoadOptions { single_index: true, triangulate: true, ..Default::default() }
+}
+
+// a mesh from an OBJ's attributes, making up normals and uvs when there are none
+fn obj_mesh(mesh: &tobj::Mesh) -> Mesh {
+    let (p, n, t, c) = (&mesh.positions, &mesh.normals, &mesh.texcoords, &mesh.vertex_color);
+    let num_vertices = p.len() / 3;
+    let has_normals = n.len() >= num_vertices * 3;
+    let has_tex_coords = t.len() >= num_vertices * 2;
+
+    let mut vertices = Vec::with_capacity(num_vertices);
+    for i in 0..num_vertices {
+        let pos = vec3(p[i * 3], p[i * 3 + 1], p[i * 3 + 2]);
+        let tex_coords = if has_tex_coords { vec2(t[i * 2], t[i * 2 + 1]) } else { Vec2::ZERO };
+        let normal = if has_normals { vec3(n[i * 3], n[i * 3 + 1], n[i * 3 + 2]) } else { Vec3::ZERO };
+        let color = if c.len() >= (i + 1) * 3 {
+            vec4(c[i * 3], c[i * 3 + 1], c[i * 3 + 2], 1.0)
+        } else {
+            Vec4::ONE
+        };
+        vertices.push(Vertex::new(pos, color, tex_coords, normal));
+    }
+
+    let mut mesh = Mesh::new(&vertices, &mesh.indices);
+    if !has_normals {
+        mesh.compute_normals();
+    }
+    if !has_tex_coords {
+        mesh.generate_uvs();
+    }
+
+    mesh
 }
 
+// the file a .mtl map statement points to, relative to the OBJ. options like `-bm 0.5`
+// come before the file name. exporters often write absolute paths from the artist's
+// machine, so the bare file name next to the OBJ is tried too
+fn resolve_texture_path(dir: &Path, map: &str) -> Option<PathBuf> {
+    let map = map.trim();
+    let file = if map.starts_with('-') { map.split_whitespace().last()? } else { map };
+
+    let path = dir.join(file);
+    if path.exists() {
+        return Some(path);
+    }
+
+    let name = Path::new(&file.replace('\\', "/")).file_name()?.to_owned();
+    let fallback = dir.join(name);
+    Some(if fallback.exists() { fallback } else { path })
+}
 
 impl Renderer {
     pub fn add_model(&mut self, name: &str, model: Model) {
         self.models.insert(name.to_string(), model);
+        self.scene.insert(NodeTarget::model(name));
     }
 
     pub fn get_model(&self, name: &str) -> Option<&Model> {
@@ -130,4 +360,15 @@ impl Renderer {
     pub fn get_model_mut(&mut self, name: &str) -> Option<&mut Model> {
         self.models.get_mut(name)
     }
+
+    /// Removes the model, its children stay where they are and move up to its parent
+    pub fn destroy_model(&mut self, name: &str) -> Result<(), String> {
+        self.remove_node(&NodeTarget::model(name))?;
+
+        if self.models.remove(name).is_some() {
+            Ok(())
+        } else {
+            Err(format!("No model found with name '{}'", name))
+        }
+    }
 }
\ No newline at end of file
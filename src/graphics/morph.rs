@@ -0,0 +1,122 @@
+use std::ffi::CString;
+
+use gl::*;
+use glam::{Vec3, Vec4};
+
+use crate::{cstr, GpuHandle, GpuResourceKind, Mesh, Shader};
+
+/// Texture unit the morph deltas buffer is bound to
+pub const MORPH_UNIT: u32 = 14;
+/// Size of the shaders' `morph_weights` array, targets past this are ignored when drawing
+pub const MAX_MORPH_TARGETS: usize = 16;
+
+/// A blend shape, offsets from the mesh's own vertices that get added on by `Mesh::morph_weights`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MorphTarget {
+    pub name: String,
+    /// One per vertex
+    pub positions: Vec<Vec3>,
+    /// One per vertex, or empty when the target doesn't bend the normals
+    pub normals: Vec<Vec3>,
+}
+
+impl MorphTarget {
+    pub fn new(name: &str, positions: Vec<Vec3>, normals: Vec<Vec3>) -> Self {
+        Self { name: name.to_owned(), positions, normals }
+    }
+}
+
+impl Mesh {
+    /// Adds a target with a weight of 0
+    pub fn add_morph_target(&mut self, target: MorphTarget) {
+        self.morph_targets.push(target);
+        self.morph_weights.push(0.0);
+    }
+
+    /// Adds a target that moves the vertices to `positions`, one for each vertex
+    pub fn add_morph_shape(&mut self, name: &str, positions: &[Vec3]) {
+        let deltas = self.vertices.iter().zip(positions).map(|(v, p)| *p - v.position).collect();
+        self.add_morph_target(MorphTarget::new(name, deltas, Vec::new()));
+    }
+
+    pub fn find_morph_target(&self, name: &str) -> Option<usize> {
+        self.morph_targets.iter().position(|t| t.name == name)
+    }
+
+    pub fn set_morph_weight(&mut self, index: usize, weight: f32) {
+        if index >= self.morph_weights.len() {
+            self.morph_weights.resize(index + 1, 0.0);
+        }
+        self.morph_weights[index] = weight;
+    }
+
+    /// The vertex positions with the targets applied by their current weights, as the shaders do it
+    pub fn morphed_positions(&self) -> Vec<Vec3> {
+        let mut positions: Vec<Vec3> = self.vertices.iter().map(|v| v.position).collect();
+
+        for (target, weight) in self.morph_targets.iter().zip(&self.morph_weights).take(MAX_MORPH_TARGETS) {
+            for (p, d) in positions.iter_mut().zip(&target.positions) {
+                *p += *d * *weight;
+            }
+        }
+
+        positions
+    }
+
+    // every target's deltas in one texture buffer, a position and a normal texel per vertex
+    pub(crate) unsafe fn setup_morphs(&mut self) {
+        if self.morph_targets.is_empty() {
+            return;
+        }
+
+        let mut deltas: Vec<Vec4> = Vec::with_capacity(self.morph_targets.len() * self.vertices.len() * 2);
+        for target in &self.morph_targets {
+            for i in 0..self.vertices.len() {
+                deltas.push(target.positions.get(i).copied().unwrap_or(Vec3::ZERO).extend(0.0));
+                deltas.push(target.normals.get(i).copied().unwrap_or(Vec3::ZERO).extend(0.0));
+            }
+        }
+
+        self.MORPH_BUFFER = GpuHandle::new(GpuResourceKind::Buffer);
+        BindBuffer(TEXTURE_BUFFER, self.MORPH_BUFFER.id());
+        BufferData(
+            TEXTURE_BUFFER,
+            (deltas.len() * std::mem::size_of::<Vec4>()) as isize,
+            deltas.as_ptr() as *const std::ffi::c_void,
+            STATIC_DRAW,
+        );
+        BindBuffer(TEXTURE_BUFFER, 0);
+
+        self.MORPH_TEXTURE = GpuHandle::new(GpuResourceKind::Texture);
+        BindTexture(TEXTURE_BUFFER, self.MORPH_TEXTURE.id());
+        TexBuffer(TEXTURE_BUFFER, RGBA32F, self.MORPH_BUFFER.id());
+        BindTexture(TEXTURE_BUFFER, 0);
+
+        self.morph_layout = (self.vertices.len(), self.morph_targets.len());
+    }
+
+    // every draw sets these, the uniforms would otherwise stay from the last morphed mesh.
+    // the buffer keeps the layout `setup_mesh` gave it until it's called again, so vertices
+    // and targets added since then go without deltas
+    pub(crate) unsafe fn send_morph_uniforms(&self, shader: &Shader) {
+        let (vertex_count, target_count) = self.morph_layout;
+        let count = self.morph_targets.len().min(target_count).min(MAX_MORPH_TARGETS);
+
+        shader.uniform_1i(cstr!("morph_deltas"), MORPH_UNIT as i32);
+        shader.uniform_1i(cstr!("morph_count"), count as i32);
+        if count == 0 {
+            return;
+        }
+
+        let mut weights = [0.0; MAX_MORPH_TARGETS];
+        for (w, weight) in weights.iter_mut().zip(&self.morph_weights) {
+            *w = *weight;
+        }
+        shader.uniform_1i(cstr!("morph_vertex_count"), vertex_count as i32);
+        shader.uniform_1fv(cstr!("morph_weights"), &weights[..count]);
+
+        ActiveTexture(TEXTURE0 + MORPH_UNIT);
+        BindTexture(TEXTURE_BUFFER, self.MORPH_TEXTURE.id());
+        ActiveTexture(TEXTURE0);
+    }
+}
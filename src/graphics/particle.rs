@@ -4,7 +4,7 @@ use gl::{*, types::*};
 use glam::{vec2, vec3, Mat4, Quat, Vec3, Vec4};
 use once_cell::sync::Lazy;
 
-use crate::{bind_buffer, cstr, gen_attrib_pointers, rand_betw, rand_vec3, EventLoop, InstanceData, Renderer, Shader, Vertex, PARTICLE_SHADER_FS, PARTICLE_SHADER_VS};
+use crate::{bind_buffer, cstr, gen_attrib_pointers, GpuHandle, GpuResourceKind, Transform, rand_betw, rand_vec3, EventLoop, InstanceData, Renderer, Shader, Vertex, PARTICLE_SHADER_FS, PARTICLE_SHADER_VS};
 
 pub static PARTICLE_SHADER: Lazy<Shader> = Lazy::new(|| {
     Shader::new_pipeline(PARTICLE_SHADER_VS, PARTICLE_SHADER_FS)
@@ -21,11 +21,11 @@ pub struct ParticleInstanceData {
 #[derive(Default)]
 pub struct Particle {
     /* particle stuff */
-    pub position: Vec3,
+    /// Where the particles spawn and how big they are
+    pub transform: Transform,
     pub velocity: Vec3,
 
     pub has_gravity: bool,
-    pub size: f32,
 
     pub count: i32,
 
@@ -41,13 +41,13 @@ pub struct ParticleMesh {
     vertices: Vec<Vertex>,
     indices: Vec<u32>,
 
-    pub VAO: u32,
-    EBO: u32,
-    VBO: u32,
+    pub VAO: GpuHandle,
+    EBO: GpuHandle,
+    VBO: GpuHandle,
     
     pub instance_data: Vec<ParticleInstanceData>,
 
-    pub instance_buffer: u32,
+    pub instance_buffer: GpuHandle,
 
     shader: Shader,
 }
@@ -81,10 +81,10 @@ impl ParticleMesh {
         let mut mesh = Self {
             vertices,
             indices,
-            VAO: 0,
-            VBO: 0,
-            EBO: 0,
-            instance_buffer: 0,
+            VAO: GpuHandle::null(GpuResourceKind::VertexArray),
+            VBO: GpuHandle::null(GpuResourceKind::Buffer),
+            EBO: GpuHandle::null(GpuResourceKind::Buffer),
+            instance_buffer: GpuHandle::null(GpuResourceKind::Buffer),
             instance_data,
             shader: *PARTICLE_SHADER,
         };
@@ -95,7 +95,7 @@ impl ParticleMesh {
     pub fn update_instance_data(&mut self, data: Vec<ParticleInstanceData>) {
         self.instance_data = data;
         unsafe {
-            BindBuffer(ARRAY_BUFFER, self.instance_buffer);
+            BindBuffer(ARRAY_BUFFER, self.instance_buffer.id());
             BufferSubData(
                 ARRAY_BUFFER, 
                 0, 
@@ -106,18 +106,18 @@ impl ParticleMesh {
     }
     
     pub unsafe fn setup_mesh(&mut self) {
-        GenVertexArrays(1, &mut self.VAO);
-        GenBuffers(1, &mut self.VBO);
-        GenBuffers(1, &mut self.EBO);
+        self.VAO = GpuHandle::new(GpuResourceKind::VertexArray);
+        self.VBO = GpuHandle::new(GpuResourceKind::Buffer);
+        self.EBO = GpuHandle::new(GpuResourceKind::Buffer);
         
-        BindVertexArray(self.VAO);
+        BindVertexArray(self.VAO.id());
         
-        bind_buffer!(ARRAY_BUFFER, self.VBO, self.vertices);
-        bind_buffer!(ELEMENT_ARRAY_BUFFER, self.EBO, self.indices);
+        bind_buffer!(ARRAY_BUFFER, self.VBO.id(), self.vertices);
+        bind_buffer!(ELEMENT_ARRAY_BUFFER, self.EBO.id(), self.indices);
         gen_attrib_pointers!(Vertex, 0 => position: 3, 1 => color: 4);
 
-        GenBuffers(1, &mut self.instance_buffer);
-        bind_buffer!(ARRAY_BUFFER, self.instance_buffer, self.instance_data);
+        self.instance_buffer = GpuHandle::new(GpuResourceKind::Buffer);
+        bind_buffer!(ARRAY_BUFFER, self.instance_buffer.id(), self.instance_data);
         
         gen_attrib_pointers!(ParticleInstanceData, 2 => position: 3);
         VertexAttribDivisor(2, 1);
@@ -125,16 +125,16 @@ impl ParticleMesh {
         BindVertexArray(0);
     }
 
+    /// Releases the GL objects now instead of when the mesh is dropped
     pub fn destroy(&mut self) {
-        unsafe {
-            DeleteVertexArrays(1, &self.VAO);
-            DeleteBuffers(1, &self.EBO);
-            DeleteBuffers(1, &self.VBO);
-        }
+        self.VAO = GpuHandle::null(GpuResourceKind::VertexArray);
+        self.VBO = GpuHandle::null(GpuResourceKind::Buffer);
+        self.EBO = GpuHandle::null(GpuResourceKind::Buffer);
+        self.instance_buffer = GpuHandle::null(GpuResourceKind::Buffer);
     }
 
     pub unsafe fn draw(&self, model_matrix: Mat4) {
-        BindVertexArray(self.VAO);
+        BindVertexArray(self.VAO.id());
         self.shader.use_shader();
 
         self.shader.uniform_mat4fv(cstr!("model"), &model_matrix.to_cols_array());
@@ -170,10 +170,9 @@ impl Particle {
         unsafe { mesh.setup_mesh(); };
 
         Self {
-            position,
+            transform: Transform::new(position, Quat::IDENTITY, Vec3::splat(size)),
             velocity,
             count,
-            size,
             has_gravity,
             spread,
             mesh,
@@ -186,7 +185,8 @@ impl Particle {
 
         for i in 0..self.count {
             let mut particle = instance_data[i as usize];
-            particle.position += particle.velocity * el.dt * 1.0 / self.size;
+            // positions are in the scaled space, so the speed doesn't change with the scale
+            particle.position += particle.velocity * el.dt / self.transform.scale();
             particle.lifespan -= el.dt;
 
             if particle.lifespan <= 0.0 {
@@ -206,14 +206,8 @@ impl Particle {
 
 
     pub fn draw(&self) {
-        let model_matrix = 
-            Mat4::from_translation(self.position) *
-            Mat4::from_quat(Quat::default()) *
-            Mat4::from_scale(vec3(self.size, self.size, self.size));
-        
-            
         unsafe {
-            self.mesh.draw(model_matrix);
+            self.mesh.draw(self.transform.matrix());
         }
     }
 }
@@ -0,0 +1,290 @@
+use std::{cell::Cell, ffi::CString};
+
+use gl::types::{GLint, GLsizei, GLuint, GLvoid};
+use glam::vec2;
+use once_cell::sync::Lazy;
+
+use crate::{cstr, ColorFormat, Error, EventLoop, GpuHandle, GpuResourceKind, RenderTarget, Renderer, Shader, POST_BLOOM_COMPOSITE_FS, POST_BLUR_FS, POST_BRIGHT_FS, POST_COLOR_GRADE_FS, POST_COPY_FS, POST_FXAA_FS, POST_PROCESS_VS, POST_TONEMAP_FS, POST_VIGNETTE_FS};
+
+pub static POST_COPY_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(POST_PROCESS_VS, POST_COPY_FS)
+});
+
+pub static POST_TONEMAP_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(POST_PROCESS_VS, POST_TONEMAP_FS)
+});
+
+pub static POST_FXAA_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(POST_PROCESS_VS, POST_FXAA_FS)
+});
+
+pub static POST_BRIGHT_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(POST_PROCESS_VS, POST_BRIGHT_FS)
+});
+
+pub static POST_BLUR_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(POST_PROCESS_VS, POST_BLUR_FS)
+});
+
+pub static POST_BLOOM_COMPOSITE_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(POST_PROCESS_VS, POST_BLOOM_COMPOSITE_FS)
+});
+
+pub static POST_VIGNETTE_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(POST_PROCESS_VS, POST_VIGNETTE_FS)
+});
+
+pub static POST_COLOR_GRADE_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(POST_PROCESS_VS, POST_COLOR_GRADE_FS)
+});
+
+/// One full screen pass. `Custom` takes a shader built with
+/// `Shader::new_pipeline(POST_PROCESS_VS, your_fs)`, which gets `in vec2 TexCoord`
+/// and the `screen` sampler, `resolution` and `time` uniforms
+#[derive(Debug, Clone, PartialEq)]
+pub enum PostEffect {
+    Tonemap { exposure: f32, gamma: f32 },
+    Fxaa,
+    Bloom { threshold: f32, intensity: f32, iterations: u32 },
+    Vignette { strength: f32, radius: f32 },
+    /// The lut texture is deleted with the last clone of the effect
+    ColorGrade { lut: GpuHandle, size: u32 },
+    Custom(Shader),
+}
+
+impl PostEffect {
+    /// Loads a strip lut image (`size * size` wide, `size` tall)
+    pub fn color_grade(lut_path: &str) -> Result<Self, String> {
+        let img = image::open(lut_path)
+            .map_err(|e| format!("Failed to load lut '{}': {}", lut_path, e))?
+            .to_rgba8();
+
+        let (width, height) = img.dimensions();
+        if width != height * height {
+            return Err(format!("Lut '{}' should be {}x{}, got {}x{}", lut_path, height * height, height, width, height));
+        }
+
+        let lut = unsafe { GpuHandle::new(GpuResourceKind::Texture) };
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, lut.id());
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+
+            // not flipped like `load_texture`, the shader expects the first row at v = 0
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                img.as_raw().as_ptr() as *const GLvoid,
+            );
+
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        Ok(PostEffect::ColorGrade { lut, size: height })
+    }
+}
+
+/// A chain of full screen passes run on the scene after it's drawn,
+/// in the order of `effects`. The scene is rendered into a float target so
+/// put `Tonemap` after anything that works on hdr values (like `Bloom`)
+pub struct PostProcess {
+    pub effects: Vec<PostEffect>,
+
+    scene: RenderTarget,
+    ping: RenderTarget,
+    pong: RenderTarget,
+    bloom_a: RenderTarget,
+    bloom_b: RenderTarget,
+
+    // the framebuffer bound when `apply` started, where the last pass goes
+    destination: Cell<GLuint>,
+    VAO: GpuHandle,
+}
+
+impl PostProcess {
+    /// Fails if the float render targets can't be created
+    pub fn new(effects: Vec<PostEffect>) -> Result<Self, Error> {
+        Ok(Self {
+            effects,
+            scene: RenderTarget::with_format(1, 1, ColorFormat::Rgba16F)?,
+            ping: RenderTarget::with_format(1, 1, ColorFormat::Rgba16F)?,
+            pong: RenderTarget::with_format(1, 1, ColorFormat::Rgba16F)?,
+            bloom_a: RenderTarget::with_format(1, 1, ColorFormat::Rgba16F)?,
+            bloom_b: RenderTarget::with_format(1, 1, ColorFormat::Rgba16F)?,
+            destination: Cell::new(0),
+            VAO: unsafe { GpuHandle::new(GpuResourceKind::VertexArray) },
+        })
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.scene.resize(width, height);
+        self.ping.resize(width, height);
+        self.pong.resize(width, height);
+        self.bloom_a.resize(width / 2, height / 2);
+        self.bloom_b.resize(width / 2, height / 2);
+    }
+
+    /// The target the scene gets drawn into before the passes run
+    pub fn scene_target(&self) -> &RenderTarget {
+        &self.scene
+    }
+
+    // draws a full screen triangle sampling `input`, into `output` or the destination when it's None
+    unsafe fn pass(&self, shader: &Shader, input: GLuint, output: Option<&RenderTarget>, time: f32, setup: impl FnOnce(&Shader)) {
+        let (w, h) = match output {
+            Some(target) => {
+                target.bind();
+                (target.width, target.height)
+            }
+            None => {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, self.destination.get());
+                (self.scene.width, self.scene.height)
+            }
+        };
+
+        shader.use_shader();
+        shader.uniform_1i(cstr!("screen"), 0);
+        shader.uniform_vec2f(cstr!("resolution"), &vec2(w as f32, h as f32));
+        shader.uniform_1f(cstr!("time"), time);
+        setup(shader);
+
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_2D, input);
+
+        gl::BindVertexArray(self.VAO.id());
+        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        gl::BindVertexArray(0);
+
+        gl::UseProgram(0);
+
+        if let Some(target) = output {
+            target.unbind();
+        }
+    }
+
+    unsafe fn bloom(&self, input: GLuint, output: Option<&RenderTarget>, time: f32, threshold: f32, intensity: f32, iterations: u32) {
+        self.pass(&POST_BRIGHT_SHADER, input, Some(&self.bloom_a), time, |s| {
+            s.uniform_1f(cstr!("threshold"), threshold);
+        });
+
+        for _ in 0..iterations.max(1) {
+            self.pass(&POST_BLUR_SHADER, self.bloom_a.texture(), Some(&self.bloom_b), time, |s| {
+                s.uniform_vec2f(cstr!("direction"), &vec2(1.0, 0.0));
+            });
+            self.pass(&POST_BLUR_SHADER, self.bloom_b.texture(), Some(&self.bloom_a), time, |s| {
+                s.uniform_vec2f(cstr!("direction"), &vec2(0.0, 1.0));
+            });
+        }
+
+        gl::ActiveTexture(gl::TEXTURE1);
+        gl::BindTexture(gl::TEXTURE_2D, self.bloom_a.texture());
+        self.pass(&POST_BLOOM_COMPOSITE_SHADER, input, output, time, |s| {
+            s.uniform_1i(cstr!("bloom"), 1);
+            s.uniform_1f(cstr!("intensity"), intensity);
+        });
+        gl::ActiveTexture(gl::TEXTURE1);
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+        gl::ActiveTexture(gl::TEXTURE0);
+    }
+
+    /// Runs every effect on the scene target, the last one writes to whatever framebuffer
+    /// is bound when this is called, usually the window
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn apply(&self, time: f32) {
+        let mut destination = 0;
+        gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut destination);
+        self.destination.set(destination as GLuint);
+
+        let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+        let blend = gl::IsEnabled(gl::BLEND) == gl::TRUE;
+        gl::Disable(gl::DEPTH_TEST);
+        gl::Disable(gl::BLEND);
+
+        if self.effects.is_empty() {
+            self.pass(&POST_COPY_SHADER, self.scene.texture(), None, time, |_| {});
+        }
+
+        let mut input = self.scene.texture();
+        let mut use_ping = true;
+
+        for (i, effect) in self.effects.iter().enumerate() {
+            let output = if i + 1 == self.effects.len() {
+                None
+            } else if use_ping {
+                Some(&self.ping)
+            } else {
+                Some(&self.pong)
+            };
+
+            match *effect {
+                PostEffect::Tonemap { exposure, gamma } => {
+                    self.pass(&POST_TONEMAP_SHADER, input, output, time, |s| {
+                        s.uniform_1f(cstr!("exposure"), exposure);
+                        s.uniform_1f(cstr!("gamma"), gamma);
+                    });
+                }
+                PostEffect::Fxaa => {
+                    self.pass(&POST_FXAA_SHADER, input, output, time, |_| {});
+                }
+                PostEffect::Bloom { threshold, intensity, iterations } => {
+                    self.bloom(input, output, time, threshold, intensity, iterations);
+                }
+                PostEffect::Vignette { strength, radius } => {
+                    self.pass(&POST_VIGNETTE_SHADER, input, output, time, |s| {
+                        s.uniform_1f(cstr!("strength"), strength);
+                        s.uniform_1f(cstr!("radius"), radius);
+                    });
+                }
+                PostEffect::ColorGrade { ref lut, size } => {
+                    gl::ActiveTexture(gl::TEXTURE1);
+                    gl::BindTexture(gl::TEXTURE_2D, lut.id());
+                    self.pass(&POST_COLOR_GRADE_SHADER, input, output, time, |s| {
+                        s.uniform_1i(cstr!("lut"), 1);
+                        s.uniform_1f(cstr!("lut_size"), size as f32);
+                    });
+                    gl::ActiveTexture(gl::TEXTURE1);
+                    gl::BindTexture(gl::TEXTURE_2D, 0);
+                    gl::ActiveTexture(gl::TEXTURE0);
+                }
+                PostEffect::Custom(shader) => {
+                    self.pass(&shader, input, output, time, |_| {});
+                }
+            }
+
+            if let Some(target) = output {
+                input = target.texture();
+                use_ping = !use_ping;
+            }
+        }
+
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+        if depth_test { gl::Enable(gl::DEPTH_TEST) }
+        if blend { gl::Enable(gl::BLEND) }
+    }
+}
+
+impl Renderer {
+    /// Draws the scene into `post`'s float target and runs its effects on top, ending up
+    /// in the bound framebuffer (the window). Draw the ui after this so it isn't post processed
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn draw_post(&self, el: &EventLoop, post: &mut PostProcess) {
+        let (w, h) = el.window.get_framebuffer_size();
+        post.resize(w.max(1) as u32, h.max(1) as u32);
+
+        self.draw_to(el, &post.scene);
+        post.apply(el.time);
+    }
+}
@@ -0,0 +1,204 @@
+use std::{cell::Cell, ptr};
+
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+use image::RgbaImage;
+
+use crate::{read_framebuffer, Camera, Error, EventLoop, GpuHandle, GpuResourceKind, Renderer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorFormat {
+    Rgba8,
+    Rgba16F,
+    Rgba32F,
+}
+
+impl ColorFormat {
+    fn gl_format(&self) -> (GLint, GLenum) {
+        match self {
+            ColorFormat::Rgba8 => (gl::RGBA8 as GLint, gl::UNSIGNED_BYTE),
+            ColorFormat::Rgba16F => (gl::RGBA16F as GLint, gl::HALF_FLOAT),
+            ColorFormat::Rgba32F => (gl::RGBA32F as GLint, gl::FLOAT),
+        }
+    }
+}
+
+/// An offscreen framebuffer with a color texture and a depth(-stencil) texture.
+/// The color texture can be used as a `Mesh` texture or shown in imgui
+#[derive(Debug)]
+pub struct RenderTarget {
+    pub FBO: GpuHandle,
+    color: GpuHandle,
+    depth: GpuHandle,
+
+    pub width: u32,
+    pub height: u32,
+    pub format: ColorFormat,
+
+    last_viewport: Cell<[GLint; 4]>,
+    last_framebuffer: Cell<GLint>,
+}
+
+impl RenderTarget {
+    pub fn new(width: u32, height: u32) -> Result<Self, Error> {
+        Self::with_format(width, height, ColorFormat::Rgba8)
+    }
+
+    /// Fails with `Error::IncompleteFramebuffer` if the driver can't render to `format`
+    pub fn with_format(width: u32, height: u32, format: ColorFormat) -> Result<Self, Error> {
+        let mut target = Self {
+            FBO: GpuHandle::null(GpuResourceKind::Framebuffer),
+            color: GpuHandle::null(GpuResourceKind::Texture),
+            depth: GpuHandle::null(GpuResourceKind::Texture),
+            width,
+            height,
+            format,
+            last_viewport: Cell::new([0; 4]),
+            last_framebuffer: Cell::new(0),
+        };
+
+        unsafe {
+            target.FBO = GpuHandle::new(GpuResourceKind::Framebuffer);
+            target.color = GpuHandle::new(GpuResourceKind::Texture);
+            target.depth = GpuHandle::new(GpuResourceKind::Texture);
+
+            target.allocate();
+
+            let mut previous = 0;
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.FBO.id());
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, target.color.id(), 0);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::TEXTURE_2D, target.depth.id(), 0);
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous as GLuint);
+
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(Error::IncompleteFramebuffer { width, height, format, status });
+            }
+        }
+
+        Ok(target)
+    }
+
+    // (re)specifies the storage of both attachments, the texture ids stay the same
+    // so meshes already using `texture()` keep working after a resize
+    unsafe fn allocate(&mut self) {
+        let (internal_format, data_type) = self.format.gl_format();
+
+        gl::BindTexture(gl::TEXTURE_2D, self.color.id());
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            internal_format,
+            self.width as GLsizei,
+            self.height as GLsizei,
+            0,
+            gl::RGBA,
+            data_type,
+            ptr::null(),
+        );
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+
+        gl::BindTexture(gl::TEXTURE_2D, self.depth.id());
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::DEPTH24_STENCIL8 as GLint,
+            self.width as GLsizei,
+            self.height as GLsizei,
+            0,
+            gl::DEPTH_STENCIL,
+            gl::UNSIGNED_INT_24_8,
+            ptr::null(),
+        );
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if width == self.width && height == self.height {
+            return;
+        }
+
+        self.width = width;
+        self.height = height;
+        unsafe { self.allocate() };
+    }
+
+    /// Binds the framebuffer and sets the viewport to cover it,
+    /// `unbind` restores whatever framebuffer and viewport were there before
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn bind(&self) {
+        let mut viewport = [0; 4];
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        self.last_viewport.set(viewport);
+
+        let mut framebuffer = 0;
+        gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut framebuffer);
+        self.last_framebuffer.set(framebuffer);
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.FBO.id());
+        gl::Viewport(0, 0, self.width as GLsizei, self.height as GLsizei);
+    }
+
+    /// Goes back to the framebuffer and viewport saved by `bind`
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn unbind(&self) {
+        let [x, y, w, h] = self.last_viewport.get();
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.last_framebuffer.get() as GLuint);
+        gl::Viewport(x, y, w, h);
+    }
+
+    pub fn texture(&self) -> GLuint {
+        self.color.id()
+    }
+
+    /// Shares the color texture, it outlives the target as long as a handle is kept
+    pub fn texture_handle(&self) -> GpuHandle {
+        self.color.clone()
+    }
+
+    pub fn depth_texture(&self) -> GLuint {
+        self.depth.id()
+    }
+
+    // remember GL textures are upside down for imgui, use uv0 = [0, 1] and uv1 = [1, 0]
+    pub fn imgui_texture_id(&self) -> imgui::TextureId {
+        imgui::TextureId::new(self.color.id() as usize)
+    }
+
+    pub fn read_pixels(&self) -> RgbaImage {
+        unsafe { read_framebuffer(self.FBO.id(), self.width, self.height) }
+    }
+}
+
+impl Renderer {
+    /// Clears `target` and draws the whole scene into it from `self.camera`
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn draw_to(&self, el: &EventLoop, target: &RenderTarget) {
+        self.draw_to_with_camera(el, target, &self.camera);
+    }
+
+    /// Same as `draw_to` but seen from another camera, handy for mirrors and minimaps
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn draw_to_with_camera(&self, el: &EventLoop, target: &RenderTarget, camera: &Camera) {
+        target.bind();
+        gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+        self.draw_with_camera(el, camera);
+        target.unbind();
+    }
+}
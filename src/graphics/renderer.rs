@@ -1,9 +1,9 @@
 use std::{collections::HashMap, ffi::CString};
 
-use gl::{types::GLuint, UseProgram};
+use gl::UseProgram;
 use glam::{vec3, Vec2, Vec3, Vec4};
 
-use crate::{cstr, load_texture, Camera, EventLoop, InstanceMesh, Light, Model, Particle, Shader, Texture, DEFAULT_SHADER, FULL_SHADER, INSTANCE_SHADER, LIGHT_SHADER, PARTICLE_SHADER};
+use crate::{cstr, load_texture, Camera, DebugDraw, EditorGrid, Gizmo, Selection, Environment, Error, EventLoop, GpuHandle, Skybox, SKYBOX_UNIT, InstanceMesh, Light, LightBuffer, Model, Particle, SceneGraph, Shader, ShadowMaps, Texture, DEFAULT_SHADER, FULL_SHADER, INSTANCE_SHADER, LIGHT_SHADER, PARTICLE_SHADER, PBR_SHADER, SKINNED_FULL_SHADER, SKINNED_PBR_SHADER};
 
 use super::Mesh;
 
@@ -13,6 +13,8 @@ pub struct Vertex {
     pub color: Vec4,
     pub tex_coords: Vec2,
     pub normal: Vec3,
+    /// xyz along +u, w the handedness of the bitangent. zero until computed
+    pub tangent: Vec4,
 }
 
 impl Vertex {
@@ -22,6 +24,7 @@ impl Vertex {
             color,
             tex_coords,
             normal,
+            tangent: Vec4::ZERO,
         }
     }
 }
@@ -34,8 +37,19 @@ pub struct Renderer {
     pub particles: HashMap<String, Particle>,
 
     pub camera: Camera,
+    pub scene: SceneGraph,
 
-    textures: HashMap<String, GLuint>,
+    pub shadows: Option<ShadowMaps>,
+    pub environment: Option<Environment>,
+    pub skybox: Option<Skybox>,
+
+    pub debug: DebugDraw,
+    pub grid: Option<EditorGrid>,
+    pub gizmo: Gizmo,
+    pub selection: Option<Selection>,
+
+    pub(crate) light_buffer: LightBuffer,
+    textures: HashMap<String, GpuHandle>,
 }
 
 impl Renderer {
@@ -51,62 +65,162 @@ impl Renderer {
             particles: HashMap::new(),
 
             camera,
+            scene: SceneGraph::new(),
+            shadows: None,
+            environment: None,
+            skybox: None,
+            debug: DebugDraw::new(),
+            grid: None,
+            gizmo: Gizmo::new(),
+            selection: None,
+            light_buffer: LightBuffer::new(),
             textures: HashMap::new(),
         }
     }
 
-    pub fn add_texture(&mut self, texture_name: String, texture_path: String){
-        self.textures.insert(texture_name, unsafe { load_texture(&texture_path) });
+    pub fn add_texture(&mut self, texture_name: String, texture_path: String) -> Result<(), Error> {
+        self.textures.insert(texture_name, unsafe { load_texture(&texture_path)? });
+        Ok(())
     }
 
-    pub fn get_texture(&self, texture_name: String) -> GLuint{
-        *self.textures.get(&texture_name).unwrap()
+    pub fn get_texture(&self, texture_name: String) -> GpuHandle{
+        self.textures.get(&texture_name).unwrap().clone()
     }
 
     pub fn update(&mut self, el: &EventLoop) {
         for particle in self.particles.values_mut() {
             particle.update(&el);
         }
+
+        for model in self.models.values_mut() {
+            model.update(el.dt);
+        }
+
+        self.debug.update(el.dt);
+        self.update_gizmo(el);
     }
 
     pub unsafe fn draw(&self, el: &EventLoop) {
+        self.draw_with_camera(el, &self.camera);
+    }
+
+    /// Draws the scene seen from `camera` instead of `self.camera`
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn draw_with_camera(&self, el: &EventLoop, camera: &Camera) {
+        let shadow_frame = self.render_shadows(camera);
+        let light_frame = self.upload_lights(camera);
+
         INSTANCE_SHADER.use_shader();
-        self.camera.send_uniforms(&INSTANCE_SHADER);
+        camera.send_uniforms(&INSTANCE_SHADER);
         UseProgram(0);
 
         PARTICLE_SHADER.use_shader();
-        self.camera.send_uniforms(&PARTICLE_SHADER);
+        camera.send_uniforms(&PARTICLE_SHADER);
         UseProgram(0);
 
         DEFAULT_SHADER.use_shader();
-        self.camera.send_uniforms(&DEFAULT_SHADER);
+        camera.send_uniforms(&DEFAULT_SHADER);
         UseProgram(0);
 
         LIGHT_SHADER.use_shader();
-        self.camera.send_uniforms(&LIGHT_SHADER);
-        self.send_light_uniforms(&LIGHT_SHADER);
+        camera.send_uniforms(&LIGHT_SHADER);
+        self.send_light_frame(&LIGHT_SHADER, &light_frame);
         UseProgram(0);
 
         FULL_SHADER.use_shader();
-        self.camera.send_uniforms(&FULL_SHADER);
-        self.send_light_uniforms(&FULL_SHADER);
+        camera.send_uniforms(&FULL_SHADER);
+        self.send_light_frame(&FULL_SHADER, &light_frame);
+        self.send_shadow_uniforms(&FULL_SHADER, &shadow_frame);
+        self.send_reflection_uniforms(&FULL_SHADER);
         UseProgram(0);
 
+        PBR_SHADER.use_shader();
+        camera.send_uniforms(&PBR_SHADER);
+        self.send_light_frame(&PBR_SHADER, &light_frame);
+        self.send_shadow_uniforms(&PBR_SHADER, &shadow_frame);
+        Environment::send_uniforms(&PBR_SHADER, self.environment.as_ref());
+        UseProgram(0);
+
+        SKINNED_FULL_SHADER.use_shader();
+        camera.send_uniforms(&SKINNED_FULL_SHADER);
+        self.send_light_frame(&SKINNED_FULL_SHADER, &light_frame);
+        self.send_shadow_uniforms(&SKINNED_FULL_SHADER, &shadow_frame);
+        self.send_reflection_uniforms(&SKINNED_FULL_SHADER);
+        UseProgram(0);
+
+        SKINNED_PBR_SHADER.use_shader();
+        camera.send_uniforms(&SKINNED_PBR_SHADER);
+        self.send_light_frame(&SKINNED_PBR_SHADER, &light_frame);
+        self.send_shadow_uniforms(&SKINNED_PBR_SHADER, &shadow_frame);
+        Environment::send_uniforms(&SKINNED_PBR_SHADER, self.environment.as_ref());
+        UseProgram(0);
+
+        // materials with their own shaders get the same camera and lights
+        for shader in self.material_shaders() {
+            shader.use_shader();
+            camera.send_uniforms(&shader);
+            self.send_light_frame(&shader, &light_frame);
+            UseProgram(0);
+        }
+
 
         for value in &self.instance_meshes {
             value.1.draw(&el);
         }
         
-        for value in &self.meshes {
-            value.1.draw();
+        let worlds = self.world_matrices();
+        for (name, mesh) in &self.meshes {
+            mesh.draw_with_matrix(worlds.meshes[name.as_str()]);
+        }
+
+        for (name, model) in &self.models {
+            model.draw_with_matrix(worlds.models[name.as_str()]);
         }
 
-        for model in &self.models {
-            model.1.draw();
+        if let Some(skybox) = &self.skybox {
+            skybox.draw(camera);
+        }
+
+        if let Some(grid) = &self.grid {
+            grid.draw(camera);
         }
 
         for particle in &self.particles {
             particle.1.draw();
         }
+
+        self.debug.draw(camera);
+    }
+
+    // the skybox, or the environment when there's no sky, as the cubemap `Full` materials reflect
+    unsafe fn send_reflection_uniforms(&self, shader: &Shader) {
+        let cubemap = self.skybox.as_ref().map(|s| &s.cubemap)
+            .or(self.environment.as_ref().map(|e| &e.cubemap));
+
+        shader.use_shader();
+        shader.uniform_1i(cstr!("reflectionMap"), SKYBOX_UNIT as i32);
+        shader.uniform_1i(cstr!("has_reflection_map"), cubemap.is_some() as i32);
+
+        gl::ActiveTexture(gl::TEXTURE0 + SKYBOX_UNIT);
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, cubemap.map_or(0, |c| c.id()));
+        gl::ActiveTexture(gl::TEXTURE0);
+    }
+
+    // every shader used by a mesh or model material that isn't one of the built in ones
+    fn material_shaders(&self) -> Vec<Shader> {
+        let builtin = [*DEFAULT_SHADER, *LIGHT_SHADER, *FULL_SHADER, *PBR_SHADER, *SKINNED_FULL_SHADER, *SKINNED_PBR_SHADER];
+        let mut shaders = Vec::new();
+
+        let meshes = self.meshes.values().chain(self.models.values().flat_map(|m| m.meshes.iter()));
+        for mesh in meshes {
+            let shader = mesh.material.borrow().shader;
+            if !builtin.contains(&shader) && !shaders.contains(&shader) {
+                shaders.push(shader);
+            }
+        }
+
+        shaders
     }
 }
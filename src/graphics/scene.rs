@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+
+use glam::Mat4;
+
+use crate::{Renderer, Transform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+/// What a scene node places, by its name in `Renderer::meshes` or `Renderer::models`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NodeTarget {
+    Mesh(String),
+    Model(String),
+}
+
+impl NodeTarget {
+    pub fn mesh(name: &str) -> Self {
+        NodeTarget::Mesh(name.to_owned())
+    }
+
+    pub fn model(name: &str) -> Self {
+        NodeTarget::Model(name.to_owned())
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            NodeTarget::Mesh(name) | NodeTarget::Model(name) => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneNode {
+    /// The renderer mesh or model this node places
+    pub target: NodeTarget,
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
+}
+
+/// Parent/child links between the renderer's meshes and models. Their position, rotation
+/// and scale are relative to their parent, `Renderer::draw` composes them down the tree
+#[derive(Debug, Default)]
+pub struct SceneGraph {
+    nodes: Vec<Option<SceneNode>>,
+    targets: HashMap<NodeTarget, NodeId>,
+}
+
+// world matrices of everything in the renderer for one frame. a model's is its root
+// matrix, what its nodes and meshes are placed under
+#[derive(Debug, Default)]
+pub(crate) struct WorldMatrices<'a> {
+    pub meshes: HashMap<&'a str, Mat4>,
+    pub models: HashMap<&'a str, Mat4>,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a root node for `target`, or returns the one it already has
+    pub fn insert(&mut self, target: NodeTarget) -> NodeId {
+        if let Some(id) = self.id(&target) {
+            return id;
+        }
+
+        let node = SceneNode { target: target.clone(), parent: None, children: Vec::new() };
+        let id = match self.nodes.iter().position(Option::is_none) {
+            Some(i) => {
+                self.nodes[i] = Some(node);
+                NodeId(i)
+            }
+            None => {
+                self.nodes.push(Some(node));
+                NodeId(self.nodes.len() - 1)
+            }
+        };
+
+        self.targets.insert(target, id);
+        id
+    }
+
+    /// Removes a node, its children move up to its parent
+    pub fn remove(&mut self, id: NodeId) -> Option<SceneNode> {
+        let node = self.nodes.get_mut(id.0)?.take()?;
+        self.targets.remove(&node.target);
+
+        if let Some(parent) = node.parent.and_then(|p| self.node_mut(p)) {
+            parent.children.retain(|c| *c != id);
+        }
+        for &child in &node.children {
+            self.node_mut(child).unwrap().parent = node.parent;
+        }
+        if let Some(parent) = node.parent.and_then(|p| self.node_mut(p)) {
+            parent.children.extend(&node.children);
+        }
+
+        Some(node)
+    }
+
+    pub fn id(&self, target: &NodeTarget) -> Option<NodeId> {
+        self.targets.get(target).copied()
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&SceneNode> {
+        self.nodes.get(id.0)?.as_ref()
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> Option<&mut SceneNode> {
+        self.nodes.get_mut(id.0)?.as_mut()
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id)?.parent
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        self.node(id).map_or(&[], |n| &n.children)
+    }
+
+    pub fn roots(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes.iter().enumerate()
+            .filter(|(_, n)| n.as_ref().is_some_and(|n| n.parent.is_none()))
+            .map(|(i, _)| NodeId(i))
+    }
+
+    /// Moves `child` under `parent` (None makes it a root), refusing to create cycles
+    pub fn set_parent(&mut self, child: NodeId, parent: Option<NodeId>) -> Result<(), String> {
+        let name = match self.node(child) {
+            Some(node) => node.target.name().to_owned(),
+            None => return Err(format!("No node with id {:?}", child)),
+        };
+        if let Some(parent) = parent {
+            if self.node(parent).is_none() {
+                return Err(format!("No node with id {:?}", parent));
+            }
+            if parent == child || self.is_ancestor(child, parent) {
+                return Err(format!("'{}' can't be parented to its own descendant", name));
+            }
+        }
+
+        let old = self.node(child).unwrap().parent;
+        if let Some(old) = old {
+            self.node_mut(old).unwrap().children.retain(|c| *c != child);
+        }
+        if let Some(parent) = parent {
+            self.node_mut(parent).unwrap().children.push(child);
+        }
+        self.node_mut(child).unwrap().parent = parent;
+
+        Ok(())
+    }
+
+    /// Whether `ancestor` is somewhere above `id`
+    pub fn is_ancestor(&self, ancestor: NodeId, id: NodeId) -> bool {
+        let mut current = self.parent(id);
+        while let Some(node) = current {
+            if node == ancestor {
+                return true;
+            }
+            current = self.parent(node);
+        }
+        false
+    }
+
+    /// Every node under `id`, depth first
+    pub fn descendants(&self, id: NodeId) -> Vec<NodeId> {
+        let mut out = Vec::new();
+        let mut stack: Vec<NodeId> = self.children(id).iter().rev().copied().collect();
+        while let Some(node) = stack.pop() {
+            out.push(node);
+            stack.extend(self.children(node).iter().rev());
+        }
+        out
+    }
+
+    /// Every node, depth first from the roots so parents always come before their children
+    pub fn traverse(&self) -> Vec<NodeId> {
+        let mut out = Vec::new();
+        for root in self.roots() {
+            out.push(root);
+            out.extend(self.descendants(root));
+        }
+        out
+    }
+}
+
+impl Renderer {
+    /// Attaches `child` to `parent` (None detaches it), keeping where it currently is
+    /// in the world. Shear from non uniform scales can't be kept
+    pub fn set_parent(&mut self, child: &NodeTarget, parent: Option<&NodeTarget>) -> Result<(), String> {
+        let Some(child_id) = self.scene.id(child) else {
+            return Err(format!("No {:?} in the scene", child));
+        };
+        let parent_id = match parent {
+            Some(target) => match self.scene.id(target) {
+                Some(id) => Some(id),
+                None => return Err(format!("No {:?} in the scene", target)),
+            },
+            None => None,
+        };
+
+        let world = self.world_matrix(child).unwrap_or(Mat4::IDENTITY);
+        let parent_world = parent.and_then(|p| self.world_matrix(p)).unwrap_or(Mat4::IDENTITY);
+        self.scene.set_parent(child_id, parent_id)?;
+
+        if let Some(transform) = self.target_transform_mut(child) {
+            *transform = Transform::from_matrix(parent_world.inverse() * world);
+        }
+
+        Ok(())
+    }
+
+    /// The mesh's model matrix, or the model's root matrix, with all of its parents applied
+    pub fn world_matrix(&self, target: &NodeTarget) -> Option<Mat4> {
+        Some(self.parent_world_matrix(target) * self.local_matrix(target)?)
+    }
+
+    /// What the target's transform is relative to, its parents' matrices. Identity at the root
+    pub fn parent_world_matrix(&self, target: &NodeTarget) -> Mat4 {
+        let mut matrix = Mat4::IDENTITY;
+
+        let mut current = self.scene.id(target).and_then(|id| self.scene.parent(id));
+        while let Some(id) = current {
+            let node = self.scene.node(id).unwrap();
+            if let Some(parent) = self.local_matrix(&node.target) {
+                matrix = parent * matrix;
+            }
+            current = node.parent;
+        }
+
+        matrix
+    }
+
+    fn local_matrix(&self, target: &NodeTarget) -> Option<Mat4> {
+        match target {
+            NodeTarget::Mesh(name) => Some(self.meshes.get(name)?.model_matrix()),
+            NodeTarget::Model(name) => Some(self.models.get(name)?.transform.matrix()),
+        }
+    }
+
+    pub(crate) fn target_transform_mut(&mut self, target: &NodeTarget) -> Option<&mut Transform> {
+        match target {
+            NodeTarget::Mesh(name) => Some(&mut self.meshes.get_mut(name)?.transform),
+            NodeTarget::Model(name) => Some(&mut self.models.get_mut(name)?.transform),
+        }
+    }
+
+    // drops `target`'s node, its children keep their place in the world under its parent
+    pub(crate) fn remove_node(&mut self, target: &NodeTarget) -> Result<(), String> {
+        let Some(id) = self.scene.id(target) else {
+            return Ok(());
+        };
+
+        let parent = self.scene.parent(id).map(|p| self.scene.node(p).unwrap().target.clone());
+        let children: Vec<NodeTarget> = self.scene.children(id).iter()
+            .map(|c| self.scene.node(*c).unwrap().target.clone())
+            .collect();
+        for child in children {
+            self.set_parent(&child, parent.as_ref())?;
+        }
+        self.scene.remove(id);
+
+        Ok(())
+    }
+
+    // world matrices of every mesh and model in one pass over the tree, ones that were
+    // put into `meshes` or `models` directly have no node and are treated as roots
+    pub(crate) fn world_matrices(&self) -> WorldMatrices<'_> {
+        let mut worlds = WorldMatrices {
+            meshes: HashMap::with_capacity(self.meshes.len()),
+            models: HashMap::with_capacity(self.models.len()),
+        };
+        let mut node_worlds: HashMap<NodeId, Mat4> = HashMap::new();
+
+        for id in self.scene.traverse() {
+            let node = self.scene.node(id).unwrap();
+            let parent = node.parent.and_then(|p| node_worlds.get(&p).copied()).unwrap_or(Mat4::IDENTITY);
+            let world = parent * self.local_matrix(&node.target).unwrap_or(Mat4::IDENTITY);
+            node_worlds.insert(id, world);
+
+            match &node.target {
+                NodeTarget::Mesh(name) => worlds.meshes.insert(name.as_str(), world),
+                NodeTarget::Model(name) => worlds.models.insert(name.as_str(), world),
+            };
+        }
+
+        for (name, mesh) in &self.meshes {
+            worlds.meshes.entry(name.as_str()).or_insert_with(|| mesh.model_matrix());
+        }
+        for (name, model) in &self.models {
+            worlds.models.entry(name.as_str()).or_insert_with(|| model.transform.matrix());
+        }
+
+        worlds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // two meshes and a model
+    fn nodes(scene: &mut SceneGraph) -> (NodeId, NodeId, NodeId) {
+        (scene.insert(NodeTarget::mesh("a")), scene.insert(NodeTarget::mesh("b")), scene.insert(NodeTarget::model("c")))
+    }
+
+    #[test]
+    fn reparenting_moves_the_child() {
+        let mut scene = SceneGraph::new();
+        let (a, b, c) = nodes(&mut scene);
+
+        scene.set_parent(c, Some(a)).unwrap();
+        assert_eq!(scene.parent(c), Some(a));
+        assert_eq!(scene.children(a), &[c]);
+
+        scene.set_parent(c, Some(b)).unwrap();
+        assert_eq!(scene.parent(c), Some(b));
+        assert!(scene.children(a).is_empty());
+        assert_eq!(scene.children(b), &[c]);
+
+        scene.set_parent(c, None).unwrap();
+        assert_eq!(scene.parent(c), None);
+        assert_eq!(scene.roots().count(), 3);
+    }
+
+    #[test]
+    fn reparenting_refuses_cycles() {
+        let mut scene = SceneGraph::new();
+        let (a, b, c) = nodes(&mut scene);
+        scene.set_parent(b, Some(a)).unwrap();
+        scene.set_parent(c, Some(b)).unwrap();
+
+        assert!(scene.set_parent(a, Some(c)).is_err());
+        assert!(scene.set_parent(a, Some(a)).is_err());
+        assert_eq!(scene.parent(a), None);
+        assert!(scene.is_ancestor(a, c));
+    }
+
+    #[test]
+    fn parents_come_before_children() {
+        let mut scene = SceneGraph::new();
+        let (a, b, c) = nodes(&mut scene);
+        scene.set_parent(a, Some(c)).unwrap();
+        scene.set_parent(c, Some(b)).unwrap();
+
+        assert_eq!(scene.traverse(), vec![b, c, a]);
+        assert_eq!(scene.descendants(b), vec![c, a]);
+    }
+
+    #[test]
+    fn removing_lifts_the_children() {
+        let mut scene = SceneGraph::new();
+        let (a, b, c) = nodes(&mut scene);
+        scene.set_parent(b, Some(a)).unwrap();
+        scene.set_parent(c, Some(b)).unwrap();
+
+        scene.remove(b).unwrap();
+        assert_eq!(scene.parent(c), Some(a));
+        assert_eq!(scene.children(a), &[c]);
+        assert_eq!(scene.id(&NodeTarget::mesh("b")), None);
+    }
+}
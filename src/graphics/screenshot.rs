@@ -0,0 +1,48 @@
+use gl::types::{GLsizei, GLuint, GLvoid};
+use image::{imageops, RgbaImage};
+
+use crate::EventLoop;
+
+/// Reads back `w` x `h` pixels from `fbo` (0 is the window's back buffer).
+/// GL rows start at the bottom so the result gets flipped to image order
+///
+/// # Safety
+/// Needs a current GL context on this thread and `fbo` to be at least `w` x `h`
+pub unsafe fn read_framebuffer(fbo: GLuint, w: u32, h: u32) -> RgbaImage {
+    let mut last_fbo = 0;
+    gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut last_fbo);
+
+    gl::BindFramebuffer(gl::READ_FRAMEBUFFER, fbo);
+    gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+
+    let mut pixels = vec![0u8; (w * h * 4) as usize];
+    gl::ReadPixels(
+        0,
+        0,
+        w as GLsizei,
+        h as GLsizei,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        pixels.as_mut_ptr() as *mut GLvoid,
+    );
+
+    gl::BindFramebuffer(gl::READ_FRAMEBUFFER, last_fbo as GLuint);
+
+    let img = RgbaImage::from_raw(w, h, pixels).unwrap();
+    imageops::flip_vertical(&img)
+}
+
+impl EventLoop {
+    /// Grabs whatever has been drawn this frame, call it before the next `update`
+    /// since that swaps the buffers
+    pub fn screenshot(&self) -> RgbaImage {
+        let (w, h) = self.window.get_framebuffer_size();
+        unsafe { read_framebuffer(0, w as u32, h as u32) }
+    }
+
+    pub fn save_screenshot(&self, path: &str) -> Result<(), String> {
+        self.screenshot()
+            .save(path)
+            .map_err(|e| format!("Failed to save screenshot '{}': {}", path, e))
+    }
+}
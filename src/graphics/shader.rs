@@ -1,6 +1,6 @@
 use gl::*;
 use gl::types::*;
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Vec2, Vec3, Vec4};
 
 use std::ptr;
 use std::ffi::{CStr, CString};
@@ -70,6 +70,13 @@ impl Shader {
             GetUniformLocation(self.id, name.as_ptr()), val);
     }
 
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn uniform_3i(&self, name: &CStr, x: i32, y: i32, z: i32) {
+        Uniform3i(
+            GetUniformLocation(self.id, name.as_ptr()), x, y, z);
+    }
+
     pub unsafe fn uniform_mat4fv(&self, name: &CStr, mat: &[f32; 16]) {
         UniformMatrix4fv(
             GetUniformLocation(self.id, name.as_ptr()), 
@@ -79,12 +86,51 @@ impl Shader {
         );
     }
 
+    /// Fills a `float` array uniform starting at its first element
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn uniform_1fv(&self, name: &CStr, vals: &[f32]) {
+        Uniform1fv(GetUniformLocation(self.id, name.as_ptr()), vals.len() as GLsizei, vals.as_ptr());
+    }
+
+    /// Fills a `mat4` array uniform starting at its first element
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn uniform_mat4fv_array(&self, name: &CStr, mats: &[Mat4]) {
+        UniformMatrix4fv(
+            GetUniformLocation(self.id, name.as_ptr()),
+            mats.len() as GLsizei,
+            FALSE,
+            mats.as_ptr() as *const f32
+        );
+    }
+
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn uniform_vec2f(&self, name: &CStr, vec: &Vec2) {
+        Uniform2f(
+            GetUniformLocation(self.id, name.as_ptr()),
+            vec.x, vec.y
+        );
+    }
+
     pub unsafe fn uniform_vec3f(&self, name: &CStr, vec: &Vec3) {
         Uniform3f(
             GetUniformLocation(self.id, name.as_ptr()),
             vec.x, vec.y, vec.z
         );
     }
+
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn uniform_vec4f(&self, name: &CStr, vec: &Vec4) {
+        Uniform4f(
+            GetUniformLocation(self.id, name.as_ptr()),
+            vec.x, vec.y, vec.z, vec.w
+        );
+    }
 }
 
 pub unsafe fn check_shader_error(shader: u32) {
@@ -1,5 +1,6 @@
 pub enum ShaderType{
     Default,
     Light,
-    Full
+    Full,
+    Pbr,
 }
\ No newline at end of file
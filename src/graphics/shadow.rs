@@ -0,0 +1,427 @@
+use std::{collections::HashMap, ffi::CString, ptr};
+
+use gl::types::{GLint, GLsizei};
+use glam::{vec3, vec4, Mat4, Vec3, Vec4Swizzles};
+use once_cell::sync::Lazy;
+
+use crate::{cstr, Camera, GpuHandle, GpuResourceKind, LightKind, Renderer, Shader, POINT_SHADOW_DEPTH_FS, POINT_SHADOW_DEPTH_VS, SHADOW_DEPTH_FS, SHADOW_DEPTH_VS, SKINNED_POINT_SHADOW_DEPTH_SHADER, SKINNED_SHADOW_DEPTH_SHADER};
+
+pub const SHADOW_MAP_UNIT: u32 = 5;
+pub const POINT_SHADOW_MAP_UNIT: u32 = 6;
+pub const MAX_CASCADES: usize = 4;
+
+pub static SHADOW_DEPTH_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(SHADOW_DEPTH_VS, SHADOW_DEPTH_FS)
+});
+
+pub static POINT_SHADOW_DEPTH_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(POINT_SHADOW_DEPTH_VS, POINT_SHADOW_DEPTH_FS)
+});
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowSettings {
+    /// name of the light casting the cascaded shadows. directional lights use their
+    /// direction, any other light is treated as a sun far away at its position
+    /// shining towards the origin
+    pub light: String,
+    pub resolution: u32,
+    pub cascades: usize,
+    /// how far from the camera shadows are drawn
+    pub distance: f32,
+    /// 0.0 splits the cascades evenly, 1.0 logarithmically
+    pub split_lambda: f32,
+    pub bias: f32,
+    /// samples (2r + 1)^2 texels around each lookup, 0 for hard shadows
+    pub pcf_radius: i32,
+
+    /// point light that also gets cube map shadows
+    pub point_light: Option<String>,
+    pub point_resolution: u32,
+    pub point_far: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            light: String::new(),
+            resolution: 2048,
+            cascades: 3,
+            distance: 100.0,
+            split_lambda: 0.75,
+            bias: 0.005,
+            pcf_radius: 1,
+
+            point_light: None,
+            point_resolution: 1024,
+            point_far: 100.0,
+        }
+    }
+}
+
+pub struct ShadowMaps {
+    pub settings: ShadowSettings,
+
+    FBO: GpuHandle,
+    depth_array: GpuHandle,
+
+    // null without a point light
+    point_FBO: GpuHandle,
+    depth_cube: GpuHandle,
+}
+
+// what the lit shaders need to know about this frame's shadow pass
+pub(crate) struct ShadowFrame {
+    pub light_space: [Mat4; MAX_CASCADES],
+    pub splits: [f32; MAX_CASCADES],
+    pub cascades: usize,
+    pub light_index: i32,
+
+    pub point_index: i32,
+}
+
+impl ShadowMaps {
+    pub fn new(settings: ShadowSettings) -> Self {
+        let border = [1.0f32; 4];
+
+        unsafe {
+            let mut maps = Self {
+                settings,
+                FBO: GpuHandle::new(GpuResourceKind::Framebuffer),
+                depth_array: GpuHandle::new(GpuResourceKind::Texture),
+                point_FBO: GpuHandle::null(GpuResourceKind::Framebuffer),
+                depth_cube: GpuHandle::null(GpuResourceKind::Texture),
+            };
+            let res = maps.settings.resolution as GLsizei;
+
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, maps.depth_array.id());
+            gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                gl::DEPTH_COMPONENT32F as GLint,
+                res,
+                res,
+                MAX_CASCADES as GLsizei,
+                0,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+                ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+            // anything outside the map counts as lit
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER as GLint);
+            gl::TexParameterfv(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_BORDER_COLOR, border.as_ptr());
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, maps.FBO.id());
+            gl::FramebufferTextureLayer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, maps.depth_array.id(), 0, 0);
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+
+            if maps.settings.point_light.is_some() {
+                let res = maps.settings.point_resolution as GLsizei;
+
+                maps.depth_cube = GpuHandle::new(GpuResourceKind::Texture);
+                gl::BindTexture(gl::TEXTURE_CUBE_MAP, maps.depth_cube.id());
+                for face in 0..6 {
+                    gl::TexImage2D(
+                        gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                        0,
+                        gl::DEPTH_COMPONENT32F as GLint,
+                        res,
+                        res,
+                        0,
+                        gl::DEPTH_COMPONENT,
+                        gl::FLOAT,
+                        ptr::null(),
+                    );
+                }
+                gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+                gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+                gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+                gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+                gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as GLint);
+
+                maps.point_FBO = GpuHandle::new(GpuResourceKind::Framebuffer);
+                gl::BindFramebuffer(gl::FRAMEBUFFER, maps.point_FBO.id());
+                gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::TEXTURE_CUBE_MAP_POSITIVE_X, maps.depth_cube.id(), 0);
+                gl::DrawBuffer(gl::NONE);
+                gl::ReadBuffer(gl::NONE);
+            }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+
+            maps
+        }
+    }
+
+    /// Fits one orthographic light matrix around each slice of `camera`'s frustum
+    pub fn cascade_matrices(&self, camera: &Camera, light_dir: Vec3) -> (Vec<Mat4>, Vec<f32>) {
+        let settings = &self.settings;
+        let inv = (camera.proj * camera.view).inverse();
+
+        let unproject = |x: f32, y: f32, z: f32| {
+            let p = inv * vec4(x, y, z, 1.0);
+            p.xyz() / p.w
+        };
+
+        let mut near_corners = [Vec3::ZERO; 4];
+        let mut far_corners = [Vec3::ZERO; 4];
+        for (i, (x, y)) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].into_iter().enumerate() {
+            near_corners[i] = unproject(x, y, -1.0);
+            far_corners[i] = unproject(x, y, 1.0);
+        }
+
+        // orthographic cameras get a single cascade
+        let planes = camera.clip_planes();
+        let is_perspective = planes.is_some();
+        let (near, far, cascades) = match planes {
+            Some((near, far)) => (near, far, settings.cascades.clamp(1, MAX_CASCADES)),
+            None => (0.0, 1.0, 1),
+        };
+
+        let shadow_far = if is_perspective { far.min(settings.distance) } else { far };
+
+        let mut matrices = vec![];
+        let mut splits = vec![];
+        let mut last_split = near;
+
+        for i in 1..=cascades {
+            let p = i as f32 / cascades as f32;
+            let log = near * (shadow_far / near).powf(p);
+            let uniform = near + (shadow_far - near) * p;
+            let split = if is_perspective {
+                settings.split_lambda * log + (1.0 - settings.split_lambda) * uniform
+            } else {
+                far
+            };
+
+            let t0 = (last_split - near) / (far - near);
+            let t1 = (split - near) / (far - near);
+
+            let mut corners = vec![];
+            for c in 0..4 {
+                corners.push(near_corners[c].lerp(far_corners[c], t0));
+                corners.push(near_corners[c].lerp(far_corners[c], t1));
+            }
+
+            let center = corners.iter().fold(Vec3::ZERO, |acc, c| acc + *c) / corners.len() as f32;
+            let mut radius = corners.iter().map(|c| c.distance(center)).fold(0.0, f32::max);
+            // keeping the size fixed while the camera turns stops the edges from shimmering
+            radius = (radius * 16.0).ceil() / 16.0;
+
+            let up = if light_dir.abs().y > 0.99 { Vec3::Z } else { Vec3::Y };
+            let view = Mat4::look_at_rh(center - light_dir, center, up);
+            let mut proj = Mat4::orthographic_rh_gl(-radius, radius, -radius, radius, -radius - settings.distance, radius);
+
+            // snap the projection to whole texels
+            let half_res = settings.resolution as f32 / 2.0;
+            let origin = (proj * view * vec4(0.0, 0.0, 0.0, 1.0)).xy() * half_res;
+            let offset = (origin.round() - origin) / half_res;
+            proj.w_axis.x += offset.x;
+            proj.w_axis.y += offset.y;
+
+            matrices.push(proj * view);
+            splits.push(if is_perspective { split } else { f32::MAX });
+            last_split = split;
+        }
+
+        (matrices, splits)
+    }
+}
+
+// world matrices of the meshes, and each model's mesh and joint matrices
+struct ShadowCasters<'a> {
+    worlds: HashMap<&'a str, Mat4>,
+    models: Vec<(Vec<Mat4>, Vec<Vec<Mat4>>)>,
+}
+
+// +x, -x, +y, -y, +z, -z as GL lays out the faces
+pub(crate) fn cube_face_views(pos: Vec3) -> [Mat4; 6] {
+    [
+        Mat4::look_at_rh(pos, pos + vec3(1.0, 0.0, 0.0), vec3(0.0, -1.0, 0.0)),
+        Mat4::look_at_rh(pos, pos + vec3(-1.0, 0.0, 0.0), vec3(0.0, -1.0, 0.0)),
+        Mat4::look_at_rh(pos, pos + vec3(0.0, 1.0, 0.0), vec3(0.0, 0.0, 1.0)),
+        Mat4::look_at_rh(pos, pos + vec3(0.0, -1.0, 0.0), vec3(0.0, 0.0, -1.0)),
+        Mat4::look_at_rh(pos, pos + vec3(0.0, 0.0, 1.0), vec3(0.0, -1.0, 0.0)),
+        Mat4::look_at_rh(pos, pos + vec3(0.0, 0.0, -1.0), vec3(0.0, -1.0, 0.0)),
+    ]
+}
+
+impl Renderer {
+    pub fn enable_shadows(&mut self, settings: ShadowSettings) {
+        self.shadows = Some(ShadowMaps::new(settings));
+    }
+
+    pub fn disable_shadows(&mut self) {
+        self.shadows = None;
+    }
+
+    // the matrices every pass draws the casters with, the same for the whole frame
+    fn shadow_casters(&self) -> ShadowCasters<'_> {
+        let worlds = self.world_matrices();
+        let models = self.models.iter().map(|(name, model)| {
+            let skins = if model.skins.is_empty() { Vec::new() } else { model.skin_matrices() };
+            (model.mesh_matrices_with(worlds.models[name.as_str()]), skins)
+        });
+
+        ShadowCasters {
+            models: models.collect(),
+            worlds: worlds.meshes,
+        }
+    }
+
+    // draws every caster with `shader`, or `skinned` for skinned meshes. `setup` sends
+    // the pass's uniforms to whichever of the two is about to be used
+    unsafe fn draw_shadow_casters(&self, casters: &ShadowCasters, shader: &Shader, skinned: &Shader, setup: impl Fn(&Shader)) {
+        shader.use_shader();
+        setup(shader);
+
+        for (name, mesh) in &self.meshes {
+            if mesh.cast_shadows {
+                mesh.draw_depth(shader, casters.worlds[name.as_str()]);
+            }
+        }
+
+        let mut skinned_casters = Vec::new();
+        for (model, (matrices, skins)) in self.models.values().zip(&casters.models) {
+            for (mesh, &matrix) in model.meshes.iter().zip(matrices) {
+                if !mesh.cast_shadows {
+                    continue;
+                }
+                match mesh.skin.and_then(|s| skins.get(s)) {
+                    Some(joints) => skinned_casters.push((mesh, matrix, joints)),
+                    None => mesh.draw_depth(shader, matrix),
+                }
+            }
+        }
+
+        if skinned_casters.is_empty() {
+            return;
+        }
+
+        skinned.use_shader();
+        setup(skinned);
+        for (mesh, matrix, joints) in skinned_casters {
+            mesh.draw_depth_skinned(skinned, matrix, joints);
+        }
+    }
+
+    /// Renders the shadow maps for `camera`, None when shadows are off or the light is gone
+    pub(crate) unsafe fn render_shadows(&self, camera: &Camera) -> Option<ShadowFrame> {
+        let maps = self.shadows.as_ref()?;
+        let settings = &maps.settings;
+
+        let light = self.lights.get(&settings.light)?;
+        let light_index = self.lights.keys().position(|name| *name == settings.light)? as i32;
+        let light_dir = match light.kind {
+            LightKind::Directional => light.direction().unwrap(),
+            _ => (-light.position()).normalize_or_zero(),
+        };
+        if light_dir == Vec3::ZERO {
+            return None;
+        }
+
+        let mut last_fbo = 0;
+        let mut last_viewport = [0; 4];
+        gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut last_fbo);
+        gl::GetIntegerv(gl::VIEWPORT, last_viewport.as_mut_ptr());
+
+        let (matrices, split_list) = maps.cascade_matrices(camera, light_dir);
+        let casters = self.shadow_casters();
+
+        let mut frame = ShadowFrame {
+            light_space: [Mat4::IDENTITY; MAX_CASCADES],
+            splits: [0.0; MAX_CASCADES],
+            cascades: matrices.len(),
+            light_index,
+            point_index: -1,
+        };
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, maps.FBO.id());
+        gl::Viewport(0, 0, settings.resolution as GLsizei, settings.resolution as GLsizei);
+
+        for (i, light_space) in matrices.iter().enumerate() {
+            gl::FramebufferTextureLayer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, maps.depth_array.id(), 0, i as GLint);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+
+            self.draw_shadow_casters(&casters, &SHADOW_DEPTH_SHADER, &SKINNED_SHADOW_DEPTH_SHADER, |shader| {
+                shader.uniform_mat4fv(cstr!("lightSpace"), &light_space.to_cols_array());
+            });
+
+            frame.light_space[i] = *light_space;
+            frame.splits[i] = split_list[i];
+        }
+
+        if let Some(point_name) = &settings.point_light {
+            let point_index = self.lights.keys().position(|name| name == point_name);
+
+            if let (Some(point), Some(point_index)) = (self.lights.get(point_name), point_index) {
+                let proj = Mat4::perspective_rh_gl(90.0f32.to_radians(), 1.0, 0.05, settings.point_far);
+
+                gl::BindFramebuffer(gl::FRAMEBUFFER, maps.point_FBO.id());
+                gl::Viewport(0, 0, settings.point_resolution as GLsizei, settings.point_resolution as GLsizei);
+
+                for (face, view) in cube_face_views(point.position()).iter().enumerate() {
+                    gl::FramebufferTexture2D(
+                        gl::FRAMEBUFFER,
+                        gl::DEPTH_ATTACHMENT,
+                        gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                        maps.depth_cube.id(),
+                        0,
+                    );
+                    gl::Clear(gl::DEPTH_BUFFER_BIT);
+
+                    self.draw_shadow_casters(&casters, &POINT_SHADOW_DEPTH_SHADER, &SKINNED_POINT_SHADOW_DEPTH_SHADER, |shader| {
+                        shader.uniform_vec3f(cstr!("lightPos"), &point.position());
+                        shader.uniform_1f(cstr!("far_plane"), settings.point_far);
+                        shader.uniform_mat4fv(cstr!("lightSpace"), &(proj * *view).to_cols_array());
+                    });
+                }
+
+                frame.point_index = point_index as i32;
+            }
+        }
+
+        gl::UseProgram(0);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, last_fbo as u32);
+        gl::Viewport(last_viewport[0], last_viewport[1], last_viewport[2], last_viewport[3]);
+
+        Some(frame)
+    }
+
+    pub(crate) unsafe fn send_shadow_uniforms(&self, shader: &Shader, frame: &Option<ShadowFrame>) {
+        shader.use_shader();
+        shader.uniform_1i(cstr!("shadowMap"), SHADOW_MAP_UNIT as i32);
+        shader.uniform_1i(cstr!("pointShadowMap"), POINT_SHADOW_MAP_UNIT as i32);
+
+        let (Some(frame), Some(maps)) = (frame, &self.shadows) else {
+            shader.uniform_1i(cstr!("shadows_enabled"), 0);
+            shader.uniform_1i(cstr!("shadow_light"), -1);
+            shader.uniform_1i(cstr!("point_shadow_light"), -1);
+            return;
+        };
+
+        shader.uniform_1i(cstr!("shadows_enabled"), 1);
+        shader.uniform_1i(cstr!("shadow_light"), frame.light_index);
+        shader.uniform_1i(cstr!("num_cascades"), frame.cascades as i32);
+        shader.uniform_1f(cstr!("shadow_bias"), maps.settings.bias);
+        shader.uniform_1i(cstr!("pcf_radius"), maps.settings.pcf_radius);
+
+        for i in 0..frame.cascades {
+            shader.uniform_mat4fv(cstr!(format!("lightSpace[{}]", i)), &frame.light_space[i].to_cols_array());
+            shader.uniform_1f(cstr!(format!("cascadeSplits[{}]", i)), frame.splits[i]);
+        }
+
+        shader.uniform_1i(cstr!("point_shadow_light"), frame.point_index);
+        shader.uniform_1f(cstr!("point_shadow_far"), maps.settings.point_far);
+
+        gl::ActiveTexture(gl::TEXTURE0 + SHADOW_MAP_UNIT);
+        gl::BindTexture(gl::TEXTURE_2D_ARRAY, maps.depth_array.id());
+        gl::ActiveTexture(gl::TEXTURE0 + POINT_SHADOW_MAP_UNIT);
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, maps.depth_cube.id());
+        gl::ActiveTexture(gl::TEXTURE0);
+    }
+}
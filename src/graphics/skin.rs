@@ -0,0 +1,102 @@
+use std::ffi::CString;
+
+use gl::*;
+use glam::{Mat4, Vec4};
+use once_cell::sync::Lazy;
+
+use crate::{bind_buffer, cstr, GpuHandle, GpuResourceKind, Mesh, Shader, VertexLayout, FULL_SHADER, FULL_SHADER_FS, PBR_SHADER, PBR_SHADER_FS, POINT_SHADOW_DEPTH_FS, SHADOW_DEPTH_FS, SKINNED_POINT_SHADOW_DEPTH_VS, SKINNED_SHADER_VS, SKINNED_SHADOW_DEPTH_VS};
+
+/// Size of the shaders' `joint_matrices` array, skins can't have more joints than this
+pub const MAX_JOINTS: usize = 128;
+
+pub static SKINNED_FULL_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(SKINNED_SHADER_VS, FULL_SHADER_FS)
+});
+
+pub static SKINNED_PBR_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(SKINNED_SHADER_VS, PBR_SHADER_FS)
+});
+
+pub static SKINNED_SHADOW_DEPTH_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(SKINNED_SHADOW_DEPTH_VS, SHADOW_DEPTH_FS)
+});
+
+pub static SKINNED_POINT_SHADOW_DEPTH_SHADER: Lazy<Shader> = Lazy::new(|| {
+    Shader::new_pipeline(SKINNED_POINT_SHADOW_DEPTH_VS, POINT_SHADOW_DEPTH_FS)
+});
+
+/// The skinning half of a vertex, kept next to `Mesh::vertices` in `Mesh::skin_vertices`
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct SkinVertex {
+    /// Indices into the skin's joints
+    pub joints: [u32; 4],
+    /// How much each joint moves the vertex, adding up to 1
+    pub weights: Vec4,
+}
+
+/// The joints a skinned mesh is bound to, as nodes of its model
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Skin {
+    pub name: String,
+    /// Indices into `Model::nodes`
+    pub joints: Vec<usize>,
+    /// Takes the mesh from model space into each joint's space at bind time
+    pub inverse_bind_matrices: Vec<Mat4>,
+}
+
+impl Skin {
+    /// What the shaders get as `joint_matrices`, from the model's `node_matrices`
+    pub fn joint_matrices(&self, node_matrices: &[Mat4]) -> Vec<Mat4> {
+        self.joints.iter().enumerate().map(|(i, &joint)| {
+            let bind = self.inverse_bind_matrices.get(i).copied().unwrap_or(Mat4::IDENTITY);
+            node_matrices.get(joint).copied().unwrap_or(Mat4::IDENTITY) * bind
+        }).collect()
+    }
+}
+
+/// The skinned version of a built in shader, other shaders are returned as they are
+pub fn skinned_shader(shader: Shader) -> Shader {
+    if shader == *FULL_SHADER {
+        *SKINNED_FULL_SHADER
+    } else if shader == *PBR_SHADER {
+        *SKINNED_PBR_SHADER
+    } else {
+        shader
+    }
+}
+
+impl Mesh {
+    // the joints and weights go in their own buffer, attributes 5 and 6
+    pub(crate) unsafe fn setup_skin(&mut self) {
+        if self.skin_vertices.is_empty() {
+            return;
+        }
+
+        self.SKIN_VBO = GpuHandle::new(GpuResourceKind::Buffer);
+        bind_buffer!(ARRAY_BUFFER, self.SKIN_VBO.id(), self.skin_vertices);
+        VertexLayout::of_skin_vertex().apply();
+    }
+
+    /// Draws with the vertices moved by `joint_matrices`, the material's shader has to be a skinned one
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn draw_skinned(&self, model_matrix: Mat4, joint_matrices: &[Mat4]) {
+        let material = self.material.borrow();
+        material.shader.use_shader();
+        material.shader.uniform_mat4fv_array(cstr!("joint_matrices"), &joint_matrices[..joint_matrices.len().min(MAX_JOINTS)]);
+        drop(material);
+
+        self.draw_with_matrix(model_matrix);
+    }
+
+    /// `draw_depth` for skinned meshes, `shader` being one of the skinned depth shaders
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread
+    pub unsafe fn draw_depth_skinned(&self, shader: &Shader, model_matrix: Mat4, joint_matrices: &[Mat4]) {
+        shader.uniform_mat4fv_array(cstr!("joint_matrices"), &joint_matrices[..joint_matrices.len().min(MAX_JOINTS)]);
+        self.draw_depth(shader, model_matrix);
+    }
+}
@@ -1,24 +1,34 @@
-use std::{path::PathBuf, rc::Rc};
+use std::{path::{Path, PathBuf}, rc::Rc};
 
-use gl::types::{GLint, GLsizei, GLuint, GLvoid};
+use gl::types::{GLint, GLsizei, GLvoid};
+
+use crate::{Error, GpuHandle, GpuResourceKind};
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Texture{
     Path(String),
-    Loaded(GLuint),
+    Loaded(GpuHandle),
     None,
 }
 
-pub unsafe fn load_texture(path: &str) -> GLuint {
-    let img = image::open(path).expect("Failed to load image");
+/// Loads an image into a mipmapped texture, deleted once the last handle is dropped
+pub unsafe fn load_texture(path: &str) -> Result<GpuHandle, Error> {
+    let img = image::open(path).map_err(|e| Error::from_image(Path::new(path), e))?;
     let img = img.flipv();
     let width = img.width();
     let height = img.height();
     let raw_pixels = img.to_rgba8().into_raw();
 
-    let mut texture: GLuint = 0;
-    gl::GenTextures(1, &mut texture);
-    gl::BindTexture(gl::TEXTURE_2D, texture);
+    Ok(create_texture(width, height, &raw_pixels))
+}
+
+/// Uploads tightly packed rgba8 pixels, the first row ends up at v = 0
+///
+/// # Safety
+/// Needs a current GL context on this thread, `rgba` has to hold `width * height * 4` bytes
+pub unsafe fn create_texture(width: u32, height: u32, rgba: &[u8]) -> GpuHandle {
+    let texture = GpuHandle::new(GpuResourceKind::Texture);
+    gl::BindTexture(gl::TEXTURE_2D, texture.id());
 
     gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as GLint);
     gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as GLint);
@@ -34,7 +44,7 @@ pub unsafe fn load_texture(path: &str) -> GLuint {
         0,
         gl::RGBA,
         gl::UNSIGNED_BYTE,
-        raw_pixels.as_ptr() as *const GLvoid,
+        rgba.as_ptr() as *const GLvoid,
     );
 
     gl::GenerateMipmap(gl::TEXTURE_2D);
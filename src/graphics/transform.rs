@@ -0,0 +1,184 @@
+use std::cell::Cell;
+
+use glam::{Mat3, Mat4, Quat, Vec3};
+
+/// Translation, rotation and scale, applied in scale -> rotate -> translate order.
+/// The matrix is only rebuilt when something changed since it was last asked for.
+/// Forward is -Z, like the camera
+#[derive(Debug, Clone)]
+pub struct Transform {
+    position: Vec3,
+    rotation: Quat,
+    scale: Vec3,
+
+    matrix: Cell<Mat4>,
+    dirty: Cell<bool>,
+}
+
+impl Transform {
+    pub fn new(position: Vec3, rotation: Quat, scale: Vec3) -> Self {
+        Self {
+            position,
+            rotation,
+            scale,
+            matrix: Cell::new(Mat4::IDENTITY),
+            dirty: Cell::new(true),
+        }
+    }
+
+    pub fn from_position(position: Vec3) -> Self {
+        Self::new(position, Quat::IDENTITY, Vec3::ONE)
+    }
+
+    /// Decomposes `matrix`, shear can't be represented and is lost
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let (scale, rotation, position) = matrix.to_scale_rotation_translation();
+        Self::new(position, rotation, scale)
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn rotation(&self) -> Quat {
+        self.rotation
+    }
+
+    pub fn scale(&self) -> Vec3 {
+        self.scale
+    }
+
+    pub fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+        self.dirty.set(true);
+    }
+
+    pub fn set_rotation(&mut self, rotation: Quat) {
+        self.rotation = rotation.normalize();
+        self.dirty.set(true);
+    }
+
+    pub fn set_scale(&mut self, scale: Vec3) {
+        self.scale = scale;
+        self.dirty.set(true);
+    }
+
+    pub fn translate(&mut self, offset: Vec3) {
+        self.set_position(self.position + offset);
+    }
+
+    /// Applies `rotation` on top of the current one, around the parent's axes
+    pub fn rotate(&mut self, rotation: Quat) {
+        self.set_rotation(rotation * self.rotation);
+    }
+
+    /// Applies `rotation` around the transform's own axes
+    pub fn rotate_local(&mut self, rotation: Quat) {
+        self.set_rotation(self.rotation * rotation);
+    }
+
+    /// Spins in place by `angle` radians around `axis`
+    pub fn rotate_around_axis(&mut self, axis: Vec3, angle: f32) {
+        self.rotate(Quat::from_axis_angle(axis.normalize(), angle));
+    }
+
+    /// Moves around `point` by `angle` radians around `axis`, turning to keep facing the same way relative to it
+    pub fn rotate_around_point(&mut self, point: Vec3, axis: Vec3, angle: f32) {
+        let rotation = Quat::from_axis_angle(axis.normalize(), angle);
+        self.set_position(point + rotation * (self.position - point));
+        self.rotate(rotation);
+    }
+
+    pub fn scale_by(&mut self, scale: Vec3) {
+        self.set_scale(self.scale * scale);
+    }
+
+    /// Turns so `forward` points at `target`
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) {
+        self.look_to(target - self.position, up);
+    }
+
+    /// Turns so `forward` points along `direction`
+    pub fn look_to(&mut self, direction: Vec3, up: Vec3) {
+        if let Some(rotation) = look_rotation(direction, up) {
+            self.set_rotation(rotation);
+        }
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.rotation * Vec3::X
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.rotation * Vec3::Y
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        if self.dirty.get() {
+            self.matrix.set(Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position));
+            self.dirty.set(false);
+        }
+        self.matrix.get()
+    }
+
+    /// Local space point to the space this transform lives in
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.matrix().transform_point3(point)
+    }
+
+    /// The opposite of `transform_point`
+    pub fn inverse_transform_point(&self, point: Vec3) -> Vec3 {
+        self.matrix().inverse().transform_point3(point)
+    }
+
+    /// Like `transform_point` for directions, unaffected by the position
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        self.matrix().transform_vector3(vector)
+    }
+
+    pub fn inverse_transform_vector(&self, vector: Vec3) -> Vec3 {
+        self.matrix().inverse().transform_vector3(vector)
+    }
+
+    /// This transform placed under `parent`, as one transform in `parent`'s space
+    pub fn to_world(&self, parent: &Transform) -> Transform {
+        Self::from_matrix(parent.matrix() * self.matrix())
+    }
+
+    /// The transform that ends up at `world` once placed under `parent`
+    pub fn to_local(world: &Transform, parent: &Transform) -> Transform {
+        Self::from_matrix(parent.matrix().inverse() * world.matrix())
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::new(Vec3::ZERO, Quat::IDENTITY, Vec3::ONE)
+    }
+}
+
+impl PartialEq for Transform {
+    fn eq(&self, other: &Self) -> bool {
+        self.position == other.position && self.rotation == other.rotation && self.scale == other.scale
+    }
+}
+
+/// The rotation turning -Z towards `direction` with +Y as close to `up` as it can,
+/// None when `direction` is zero
+pub fn look_rotation(direction: Vec3, up: Vec3) -> Option<Quat> {
+    let forward = direction.try_normalize()?;
+    // looking straight along up, any other axis works
+    let up = if forward.cross(up).length_squared() < 1e-6 {
+        if forward.y.abs() < 0.99 { Vec3::Y } else { Vec3::Z }
+    } else {
+        up
+    };
+
+    let right = forward.cross(up).normalize();
+    let up = right.cross(forward);
+    Some(Quat::from_mat3(&Mat3::from_cols(right, up, -forward)))
+}
@@ -0,0 +1,296 @@
+use std::{cell::Cell, ffi::c_void, mem::{offset_of, size_of}, ptr};
+
+use bytemuck::Pod;
+use gl::{*, types::{GLenum, GLsizei}};
+
+use crate::{BufferUsage, GpuHandle, GpuResourceKind, Mesh, SkinVertex, Vertex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeType {
+    Float,
+    HalfFloat,
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+}
+
+impl AttributeType {
+    pub fn gl_enum(&self) -> GLenum {
+        match self {
+            AttributeType::Float => FLOAT,
+            AttributeType::HalfFloat => HALF_FLOAT,
+            AttributeType::Byte => BYTE,
+            AttributeType::UnsignedByte => UNSIGNED_BYTE,
+            AttributeType::Short => SHORT,
+            AttributeType::UnsignedShort => UNSIGNED_SHORT,
+            AttributeType::Int => INT,
+            AttributeType::UnsignedInt => UNSIGNED_INT,
+        }
+    }
+
+    /// Bytes per component
+    pub fn size(&self) -> usize {
+        match self {
+            AttributeType::Byte | AttributeType::UnsignedByte => 1,
+            AttributeType::HalfFloat | AttributeType::Short | AttributeType::UnsignedShort => 2,
+            AttributeType::Float | AttributeType::Int | AttributeType::UnsignedInt => 4,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !matches!(self, AttributeType::Float | AttributeType::HalfFloat)
+    }
+}
+
+/// One input of a vertex shader, as it's laid out in a vertex buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexAttribute {
+    pub name: String,
+    pub location: u32,
+    /// 1 to 4
+    pub components: i32,
+    pub kind: AttributeType,
+    /// Integers read as floats in 0..1 (or -1..1 when signed), like colors packed into bytes.
+    /// Integers that aren't normalized reach the shader as `int`/`uint` inputs
+    pub normalized: bool,
+    /// Bytes from the start of the vertex
+    pub offset: usize,
+}
+
+impl VertexAttribute {
+    pub fn new(name: &str, location: u32, components: i32, kind: AttributeType) -> Self {
+        Self { name: name.to_owned(), location, components, kind, normalized: false, offset: 0 }
+    }
+
+    pub fn normalized(mut self) -> Self {
+        self.normalized = true;
+        self
+    }
+
+    pub fn size(&self) -> usize {
+        self.components as usize * self.kind.size()
+    }
+}
+
+/// How the attributes of one vertex buffer are laid out, the stride being the size of a vertex
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VertexLayout {
+    pub attributes: Vec<VertexAttribute>,
+    pub stride: usize,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `attribute` right after the previous one
+    pub fn with(self, attribute: VertexAttribute) -> Self {
+        let offset = self.attributes.iter().map(|a| a.offset + a.size()).max().unwrap_or(0);
+        self.with_at(attribute, offset)
+    }
+
+    /// Adds `attribute` at `offset` bytes, for structs where the fields are padded
+    pub fn with_at(mut self, mut attribute: VertexAttribute, offset: usize) -> Self {
+        attribute.offset = offset;
+        self.stride = self.stride.max(offset + attribute.size());
+        self.attributes.push(attribute);
+        self
+    }
+
+    /// Sets the stride, when a vertex is bigger than its attributes (trailing padding)
+    pub fn with_stride(mut self, stride: usize) -> Self {
+        self.stride = stride;
+        self
+    }
+
+    /// The layout of `Vertex`, what every mesh has unless a stream replaces it
+    pub fn of_vertex() -> Self {
+        Self::new()
+            .with_at(VertexAttribute::new("position", 0, 3, AttributeType::Float), offset_of!(Vertex, position))
+            .with_at(VertexAttribute::new("color", 1, 4, AttributeType::Float), offset_of!(Vertex, color))
+            .with_at(VertexAttribute::new("tex_coords", 2, 2, AttributeType::Float), offset_of!(Vertex, tex_coords))
+            .with_at(VertexAttribute::new("normal", 3, 3, AttributeType::Float), offset_of!(Vertex, normal))
+            .with_at(VertexAttribute::new("tangent", 4, 4, AttributeType::Float), offset_of!(Vertex, tangent))
+            .with_stride(size_of::<Vertex>())
+    }
+
+    /// The layout of `SkinVertex`, joints and weights at locations 5 and 6
+    pub fn of_skin_vertex() -> Self {
+        Self::new()
+            .with_at(VertexAttribute::new("joints", 5, 4, AttributeType::UnsignedInt), offset_of!(SkinVertex, joints))
+            .with_at(VertexAttribute::new("weights", 6, 4, AttributeType::Float), offset_of!(SkinVertex, weights))
+            .with_stride(size_of::<SkinVertex>())
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&VertexAttribute> {
+        self.attributes.iter().find(|a| a.name == name)
+    }
+
+    pub fn has_location(&self, location: u32) -> bool {
+        self.attributes.iter().any(|a| a.location == location)
+    }
+
+    /// Points the attributes at the bound `ARRAY_BUFFER`, for the bound vertex array
+    ///
+    /// # Safety
+    /// Needs a current GL context on this thread with a vertex array and `ARRAY_BUFFER` bound
+    pub unsafe fn apply(&self) {
+        let stride = self.stride as GLsizei;
+
+        for attribute in &self.attributes {
+            let offset = attribute.offset as *const c_void;
+            EnableVertexAttribArray(attribute.location);
+
+            if attribute.kind.is_integer() && !attribute.normalized {
+                VertexAttribIPointer(attribute.location, attribute.components, attribute.kind.gl_enum(), stride, offset);
+            } else {
+                let normalized = if attribute.normalized { TRUE } else { FALSE };
+                VertexAttribPointer(attribute.location, attribute.components, attribute.kind.gl_enum(), normalized, stride, offset);
+            }
+        }
+    }
+}
+
+/// An extra vertex buffer of a mesh, for attributes `Vertex` doesn't have like a second
+/// uv set or packed colors. One that has location 0 replaces the mesh's `Vertex` buffer
+/// entirely, letting big meshes use a compact format
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexStream {
+    pub layout: VertexLayout,
+    /// Raw bytes, `layout.stride` per vertex
+    pub data: Vec<u8>,
+    pub(crate) buffer: GpuHandle,
+    // set by `Mesh::set_stream_vertices`, uploaded before the next draw
+    pub(crate) dirty: Cell<bool>,
+}
+
+impl VertexStream {
+    /// `vertices` are copied byte for byte, they should be a `#[repr(C)]` type matching
+    /// `layout`. `Pod` (see the re-exported `bytemuck`) makes sure there's no padding to read
+    pub fn new<T: Pod>(layout: VertexLayout, vertices: &[T]) -> Self {
+        Self {
+            layout,
+            data: bytemuck::cast_slice(vertices).to_vec(),
+            buffer: GpuHandle::null(GpuResourceKind::Buffer),
+            dirty: Cell::new(false),
+        }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.data.len() / self.layout.stride.max(1)
+    }
+
+    // vertex i becomes the old vertex `sources[i]`, ones past the end are zeroed
+    pub(crate) fn remap(&mut self, sources: &[usize]) {
+        let stride = self.layout.stride;
+        if stride == 0 || self.data.is_empty() {
+            return;
+        }
+
+        let mut data = Vec::with_capacity(sources.len() * stride);
+        for &i in sources {
+            match self.data.get(i * stride..(i + 1) * stride) {
+                Some(vertex) => data.extend_from_slice(vertex),
+                None => data.resize(data.len() + stride, 0),
+            }
+        }
+        self.data = data;
+    }
+
+    // makes the buffer and points the layout at it, for the bound vertex array
+    pub(crate) unsafe fn setup(&mut self, usage: BufferUsage) {
+        self.buffer = GpuHandle::new(GpuResourceKind::Buffer);
+        BindBuffer(ARRAY_BUFFER, self.buffer.id());
+        BufferData(ARRAY_BUFFER, self.data.len() as isize, self.data.as_ptr() as *const c_void, usage.gl_enum());
+        self.layout.apply();
+        self.dirty.set(false);
+    }
+
+    // replaces the whole contents if they changed, orphaning the old storage
+    pub(crate) unsafe fn upload_changes(&self, usage: BufferUsage) {
+        if !self.dirty.take() || self.buffer.is_null() {
+            return;
+        }
+
+        BindBuffer(ARRAY_BUFFER, self.buffer.id());
+        BufferData(ARRAY_BUFFER, self.data.len() as isize, ptr::null(), usage.gl_enum());
+        if !self.data.is_empty() {
+            BufferSubData(ARRAY_BUFFER, 0, self.data.len() as isize, self.data.as_ptr() as *const c_void);
+        }
+        BindBuffer(ARRAY_BUFFER, 0);
+    }
+}
+
+impl Mesh {
+    /// Adds a stream, it's uploaded by `setup_mesh`. Returns its index
+    pub fn add_vertex_stream(&mut self, stream: VertexStream) -> usize {
+        self.vertex_streams.push(stream);
+        self.vertex_streams.len() - 1
+    }
+
+    /// Replaces a stream's vertices, they're uploaded before the mesh is next drawn
+    pub fn set_stream_vertices<T: Pod>(&mut self, index: usize, vertices: &[T]) {
+        let Some(stream) = self.vertex_streams.get_mut(index) else {
+            return;
+        };
+
+        stream.data = bytemuck::cast_slice(vertices).to_vec();
+        stream.dirty.set(true);
+    }
+
+    // whether a stream takes the place of the `Vertex` buffer
+    pub(crate) fn has_custom_positions(&self) -> bool {
+        self.vertex_streams.iter().any(|s| s.layout.has_location(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packed() -> VertexLayout {
+        VertexLayout::new()
+            .with(VertexAttribute::new("position", 0, 3, AttributeType::Float))
+            .with(VertexAttribute::new("color", 1, 4, AttributeType::UnsignedByte).normalized())
+            .with(VertexAttribute::new("uv", 2, 2, AttributeType::HalfFloat))
+    }
+
+    #[test]
+    fn attributes_follow_each_other() {
+        let layout = packed();
+
+        let offsets: Vec<usize> = layout.attributes.iter().map(|a| a.offset).collect();
+        assert_eq!(offsets, vec![0, 12, 16]);
+        assert_eq!(layout.stride, 20);
+        assert!(layout.attribute("color").unwrap().normalized);
+        assert!(layout.has_location(2) && !layout.has_location(3));
+    }
+
+    #[test]
+    fn explicit_offsets_and_stride() {
+        let layout = VertexLayout::new()
+            .with_at(VertexAttribute::new("uv", 2, 2, AttributeType::Float), 16)
+            .with_at(VertexAttribute::new("position", 0, 3, AttributeType::Float), 0);
+        assert_eq!(layout.stride, 24);
+
+        // padding at the end only shows up through `with_stride`
+        assert_eq!(layout.with_stride(32).stride, 32);
+        assert_eq!(VertexLayout::of_vertex().stride, size_of::<Vertex>());
+    }
+
+    #[test]
+    fn remap_moves_whole_vertices() {
+        let layout = VertexLayout::new().with(VertexAttribute::new("id", 5, 2, AttributeType::UnsignedShort));
+        let mut stream = VertexStream::new(layout, &[[0u16, 1], [10, 11], [20, 21]]);
+        assert_eq!(stream.vertex_count(), 3);
+
+        // past the end is zeroed
+        stream.remap(&[2, 0, 0, 7]);
+        let expected: [[u16; 2]; 4] = [[20, 21], [0, 1], [0, 1], [0, 0]];
+        assert_eq!(stream.data, bytemuck::cast_slice::<_, u8>(&expected));
+    }
+}
@@ -1,25 +1,56 @@
 use imgui::Ui;
 
-use crate::Renderer;
+use crate::{EditorGrid, GizmoMode, Renderer, Selection};
 
 pub fn renderer_inspector(renderer: &mut Renderer, frame: &mut Ui) {
     let inspector_window = frame.window("Inspector");
 
     inspector_window.build(|| {
+        frame.checkbox("Gizmo", &mut renderer.gizmo.enabled);
+        frame.same_line();
+        let mut mode = renderer.gizmo.mode;
+        frame.radio_button("Move", &mut mode, GizmoMode::Translate);
+        frame.same_line();
+        frame.radio_button("Rotate", &mut mode, GizmoMode::Rotate);
+        frame.same_line();
+        frame.radio_button("Scale", &mut mode, GizmoMode::Scale);
+        if mode != renderer.gizmo.mode {
+            renderer.gizmo.set_mode(mode);
+        }
+
+        let mut show_grid = renderer.grid.is_some();
+        if frame.checkbox("Grid", &mut show_grid) {
+            renderer.grid = show_grid.then(EditorGrid::new);
+        }
+        frame.separator();
+
+        // clicking an entry selects it for the gizmo, clicking it again clears the selection
+        let mut clicked = None;
+
         for mesh in &renderer.meshes {
-            let txt = format!("Mesh: {:?}; P: {:.1}; R: {:.1}; S: {:.1}", mesh.0, mesh.1.position, mesh.1.rotation, mesh.1.scale);
-            frame.text(txt);
+            let txt = format!("Mesh: {:?}; P: {:.1}; R: {:.1}; S: {:.1}###mesh {}", mesh.0, mesh.1.transform.position(), mesh.1.transform.rotation(), mesh.1.transform.scale(), mesh.0);
+            let selection = Selection::Mesh(mesh.0.clone());
+            if frame.selectable_config(txt).selected(renderer.selection.as_ref() == Some(&selection)).build() {
+                clicked = Some(selection);
+            }
         }
 
         for light in &renderer.lights {
-            let txt = format!("Light: {:?}; P: {:.1}; C: {:.1}", light.0, light.1.position, light.1.color);
+            let txt = format!("Light: {:?}; P: {:.1}; C: {:.1}; I: {:.1}; {:?}", light.0, light.1.position(), light.1.color, light.1.intensity, light.1.kind);
             frame.text(txt);
         }
 
         for model in &renderer.models {
-            let txt = format!("Model: M: {:?}", model.0);
-            frame.text(txt);
+            let txt = format!("Model: M: {:?}; P: {:.1}; R: {:.1}; S: {:.1}###model {}", model.0, model.1.transform.position(), model.1.transform.rotation(), model.1.transform.scale(), model.0);
+            let selection = Selection::Model(model.0.clone());
+            if frame.selectable_config(txt).selected(renderer.selection.as_ref() == Some(&selection)).build() {
+                clicked = Some(selection);
+            }
         }
 
+        if let Some(selection) = clicked {
+            let deselect = renderer.selection.as_ref() == Some(&selection);
+            renderer.select(if deselect { None } else { Some(selection) });
+        }
     });
-}
\ No newline at end of file
+}
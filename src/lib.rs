@@ -4,12 +4,15 @@ mod graphics;
 mod events;
 mod utils;
 mod gui;
+mod error;
 
 pub use graphics::*;
 pub use events::*;
 pub use utils::*;
 pub use gui::*;
+pub use error::*;
 
+pub use bytemuck;
 pub use gl;
 pub use glfw;
 pub use glam;
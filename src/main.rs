@@ -1,10 +1,12 @@
+use std::rc::Rc;
+
 use gl::{BlendFunc, CullFace, DepthFunc, Enable, FrontFace, PolygonMode, BACK, CULL_FACE, CW, DEPTH_BUFFER_BIT, DEPTH_TEST, FILL, FRONT, LESS, LINE};
 use glam::{Quat, Vec3};
 use glfw::Key;
 use imgui::ImColor32;
 use tiny_game_framework::gl::{Clear, COLOR_BUFFER_BIT};
 use tiny_game_framework::glam::{vec2, vec3, vec4, Vec4};
-use tiny_game_framework::{lerp, rand_betw, rand_vec2, rand_vec3, rand_vec4, renderer_inspector, Circle, Cuboid, EventLoop, Font, InstanceData, Light, Particle, Quad, ShaderType, Sphere, Texture};
+use tiny_game_framework::{lerp, rand_betw, rand_vec2, rand_vec3, rand_vec4, renderer_inspector, Circle, Cubemap, Cuboid, EventLoop, Font, InstanceData, Light, Particle, Quad, ShaderType, Skybox, Sphere, Texture};
 use tiny_game_framework::Renderer;
 
 fn main() {
@@ -23,8 +25,7 @@ fn main() {
     let cobble_tex = "examples/assets/images/cobble_tex.png";
     let roblux_tex = "examples/assets/images/hqdefault.jpg";
 
-    renderer.add_texture("cobble".to_owned(), cobble_tex.to_owned());
-    renderer.add_texture("roblux".to_owned(), roblux_tex.to_owned());
+    renderer.add_texture("cobble".to_owned(), cobble_tex.to_owned()).unwrap();
 
     let mut c = Cuboid::new(vec3(1., 1., 1.), vec4(1.0, 0.0, 0.0, 1.0)).mesh();
     c.set_shader_type(&ShaderType::Full);
@@ -39,7 +40,7 @@ fn main() {
     c2.add_position(vec3(3., 0., 0.));
     renderer.add_mesh("c2", c2).unwrap();
 
-    //renderer.get_mesh("c").unwrap().clone().add_child(renderer.get_mesh("c2").unwrap().clone());
+    //renderer.set_parent(&NodeTarget::mesh("c2"), Some(&NodeTarget::mesh("c"))).unwrap();
 
     let mut s = Sphere::new(128, 5., Vec4::ONE).mesh();
     s.set_shader_type(&ShaderType::Full);
@@ -48,20 +49,12 @@ fn main() {
     s.add_position(vec3(1.5, 0., 0.));
     renderer.add_mesh("s", s).unwrap();
 
-    let mut t = Sphere::new(32, 100., vec4(0.1, 0.2, 0.3, 1.0)).mesh();
-    t.set_texture("roblux", &renderer);
-    // funky effect
-    for face in t.vertices.chunks_mut(2) {
-        face.reverse();
-    }
-
-    t.setup_mesh();
-    t.add_position(vec3(1.5, 0., 0.));
-    renderer.add_mesh("t", t).unwrap();
+    let sky = Cubemap::from_equirectangular(roblux_tex, 512).unwrap();
+    renderer.set_skybox(Some(Skybox::new(Rc::new(sky))));
 
-    renderer.add_light("light1", Light {position: vec3(100000.0, 100000.0, 100000.0), color: vec3(0.0, 0.0, 1.0)});
-    renderer.add_light("light2", Light {position: vec3(-100000.0, 100000.0, 100000.0), color: vec3(0.0, 1.0, 0.0)});
-    renderer.add_light("light3", Light {position: vec3(-100000.0, 100000.0, -100000.0), color: vec3(1.0, 0.0, 0.0)});
+    renderer.add_light("light1", Light::new(vec3(100000.0, 100000.0, 100000.0), vec3(0.0, 0.0, 1.0)));
+    renderer.add_light("light2", Light::new(vec3(-100000.0, 100000.0, 100000.0), vec3(0.0, 1.0, 0.0)));
+    renderer.add_light("light3", Light::new(vec3(-100000.0, 100000.0, -100000.0), vec3(1.0, 0.0, 0.0)));
 
     let mut particle = Particle::new(vec3(1.0, 1.0, 1.0), vec3(5.0, 3.0, 0.0), 100000, 0.01, true, 0.1);
     renderer.add_particle("particle", particle);
@@ -107,11 +100,6 @@ fn main() {
         }
         m.add_position(pos);
 
-        let cam_pos = renderer.camera.pos;
-
-        let t = renderer.get_mesh_mut("t").unwrap();
-        t.position = cam_pos;
-
         if el.is_key_down(Key::LeftAlt) {
             el.window.set_cursor_mode(glfw::CursorMode::Normal);
         } else {
@@ -124,13 +112,13 @@ fn main() {
         }
 
         let l1 = renderer.get_light_mut("light1").unwrap();
-        l1.position = vec3(el.time.cos() * 10000.0, el.time.sin() * 10000.0, 10000.0);
+        l1.transform.set_position(vec3(el.time.cos() * 10000.0, el.time.sin() * 10000.0, 10000.0));
 
         let l2 = renderer.get_light_mut("light2").unwrap();
-        l2.position = vec3(10000.0, el.time.sin() * 10000.0, el.time.cos() * 10000.0);
+        l2.transform.set_position(vec3(10000.0, el.time.sin() * 10000.0, el.time.cos() * 10000.0));
 
         let l3 = renderer.get_light_mut("light3").unwrap();
-        l3.position = vec3(el.time.sin() * 10000.0, el.time.cos() * 10000.0, 10000.0);
+        l3.transform.set_position(vec3(el.time.sin() * 10000.0, el.time.cos() * 10000.0, 10000.0));
 
         unsafe {
             if el.is_key_down(Key::F1) {
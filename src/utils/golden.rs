@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use image::{Rgba, RgbaImage};
+
+/// Set this env var to (re)write the golden images instead of comparing against them
+pub const UPDATE_GOLDEN_ENV: &str = "TGF_UPDATE_GOLDEN";
+
+pub struct ImageDiff {
+    pub mismatched_pixels: usize,
+    pub max_channel_delta: u8,
+    pub diff: RgbaImage,
+}
+
+// pixels over the tolerance show up red, the rest as a faded copy of `expected`
+pub fn diff_images(actual: &RgbaImage, expected: &RgbaImage, tolerance: u8) -> Result<ImageDiff, String> {
+    if actual.dimensions() != expected.dimensions() {
+        return Err(format!(
+            "Image size mismatch: got {:?}, expected {:?}",
+            actual.dimensions(), expected.dimensions()
+        ));
+    }
+
+    let (w, h) = actual.dimensions();
+    let mut diff = RgbaImage::new(w, h);
+    let mut mismatched_pixels = 0;
+    let mut max_channel_delta = 0;
+
+    for (x, y, a) in actual.enumerate_pixels() {
+        let e = expected.get_pixel(x, y);
+
+        let delta = a.0.iter()
+            .zip(e.0.iter())
+            .map(|(a, e)| a.abs_diff(*e))
+            .max()
+            .unwrap();
+
+        max_channel_delta = max_channel_delta.max(delta);
+
+        if delta > tolerance {
+            mismatched_pixels += 1;
+            diff.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        } else {
+            let luma = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 3 / 4) as u8;
+            diff.put_pixel(x, y, Rgba([luma, luma, luma, 255]));
+        }
+    }
+
+    Ok(ImageDiff { mismatched_pixels, max_channel_delta, diff })
+}
+
+/// Compares `actual` against the png at `golden_path` allowing `tolerance` per channel.
+/// On failure `<golden>.actual.png` and `<golden>.diff.png` are written next to it
+pub fn compare_golden(actual: &RgbaImage, golden_path: &str, tolerance: u8) -> Result<(), String> {
+    let path = Path::new(golden_path);
+
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        return actual.save(path).map_err(|e| format!("Failed to write golden '{}': {}", golden_path, e));
+    }
+
+    if !path.exists() {
+        return Err(format!(
+            "Golden image '{}' doesn't exist, rerun with {}=1 to create it",
+            golden_path, UPDATE_GOLDEN_ENV
+        ));
+    }
+
+    let expected = image::open(path)
+        .map_err(|e| format!("Failed to load golden '{}': {}", golden_path, e))?
+        .to_rgba8();
+
+    let result = diff_images(actual, &expected, tolerance)?;
+    if result.mismatched_pixels == 0 {
+        return Ok(());
+    }
+
+    let actual_path = path.with_extension("actual.png");
+    let diff_path = path.with_extension("diff.png");
+    actual.save(&actual_path).map_err(|e| e.to_string())?;
+    result.diff.save(&diff_path).map_err(|e| e.to_string())?;
+
+    Err(format!(
+        "{} pixels differ from '{}' (max channel delta {}, tolerance {}), see '{}'",
+        result.mismatched_pixels, golden_path, result.max_channel_delta, tolerance, diff_path.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(w: u32, h: u32, color: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(w, h, Rgba(color))
+    }
+
+    #[test]
+    fn identical_images_match() {
+        let image = filled(4, 4, [10, 20, 30, 255]);
+        let result = diff_images(&image, &image, 0).unwrap();
+
+        assert_eq!(result.mismatched_pixels, 0);
+        assert_eq!(result.max_channel_delta, 0);
+    }
+
+    #[test]
+    fn small_deltas_are_within_tolerance() {
+        let expected = filled(4, 4, [10, 20, 30, 255]);
+        let mut actual = expected.clone();
+        actual.put_pixel(1, 2, Rgba([12, 19, 30, 255]));
+
+        let result = diff_images(&actual, &expected, 2).unwrap();
+        assert_eq!(result.mismatched_pixels, 0);
+        assert_eq!(result.max_channel_delta, 2);
+    }
+
+    #[test]
+    fn large_deltas_are_marked_red() {
+        let expected = filled(4, 4, [10, 20, 30, 255]);
+        let mut actual = expected.clone();
+        actual.put_pixel(1, 2, Rgba([10, 20, 90, 255]));
+        actual.put_pixel(3, 0, Rgba([10, 20, 30, 0]));
+
+        let result = diff_images(&actual, &expected, 2).unwrap();
+        assert_eq!(result.mismatched_pixels, 2);
+        assert_eq!(result.max_channel_delta, 255);
+        assert_eq!(*result.diff.get_pixel(1, 2), Rgba([255, 0, 0, 255]));
+        assert_ne!(*result.diff.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn different_sizes_are_an_error() {
+        assert!(diff_images(&filled(4, 4, [0; 4]), &filled(4, 5, [0; 4]), 255).is_err());
+    }
+}
@@ -1,5 +1,7 @@
 mod math;
 mod mesh_geometry;
+mod golden;
 
 pub use math::*;
 pub use mesh_geometry::*;
+pub use golden::*;
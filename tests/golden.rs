@@ -0,0 +1,56 @@
+use tiny_game_framework::{
+    glam::{vec2, vec3, Mat4, Vec3, Vec4},
+    gl::{Clear, ClearColor, COLOR_BUFFER_BIT, DEPTH_BUFFER_BIT},
+    compare_golden, EventLoop, Mesh, Renderer, Vertex,
+};
+
+const SIZE: u32 = 64;
+const CLEAR: [f32; 4] = [0.2, 0.2, 0.2, 1.0];
+
+// x, y from the top left, width and height in pixels, and the color. the edges land on
+// pixel boundaries and the default shader is unlit, so every pixel has one right value
+const RECTS: [([u32; 4], [f32; 4]); 3] = [
+    ([8, 8, 24, 16], [1.0, 0.0, 0.0, 1.0]),
+    ([36, 8, 20, 40], [0.0, 1.0, 0.0, 1.0]),
+    ([8, 32, 24, 24], [0.2, 0.4, 1.0, 1.0]),
+];
+
+// with an identity camera the positions are already in clip space
+fn rect_mesh([x, y, w, h]: [u32; 4], color: [f32; 4]) -> Mesh {
+    let [x, y, w, h] = [x, y, w, h].map(|v| v as f32 / SIZE as f32 * 2.0);
+    let (left, top) = (x - 1.0, 1.0 - y);
+    let (right, bottom) = (left + w, top - h);
+
+    let color = Vec4::from(color);
+    let corners = [vec3(left, bottom, 0.0), vec3(right, bottom, 0.0), vec3(right, top, 0.0), vec3(left, top, 0.0)];
+    let vertices = corners.map(|p| Vertex::new(p, color, vec2(0.0, 0.0), Vec3::Z)).to_vec();
+
+    let mut mesh = Mesh::new(&vertices, &vec![0, 1, 2, 0, 2, 3]);
+    mesh.setup_mesh();
+    mesh
+}
+
+// needs a display, CI runs it under xvfb. locally without one run
+// `xvfb-run cargo test --test golden -- --ignored`
+#[test]
+#[ignore = "needs a display"]
+fn flat_rects_match_golden() {
+    let mut el = EventLoop::new_headless(SIZE, SIZE);
+    let mut renderer = Renderer::new();
+    renderer.camera.view = Mat4::IDENTITY;
+    renderer.camera.proj = Mat4::IDENTITY;
+
+    for (i, (rect, color)) in RECTS.into_iter().enumerate() {
+        renderer.add_mesh(&format!("rect {}", i), rect_mesh(rect, color)).unwrap();
+    }
+
+    el.update();
+    unsafe {
+        ClearColor(CLEAR[0], CLEAR[1], CLEAR[2], CLEAR[3]);
+        Clear(COLOR_BUFFER_BIT | DEPTH_BUFFER_BIT);
+        renderer.draw(&el);
+    }
+
+    let golden = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/flat_rects.png");
+    compare_golden(&el.screenshot(), golden, 2).unwrap();
+}